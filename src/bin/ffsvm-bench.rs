@@ -0,0 +1,201 @@
+//! Measures prediction latency and batch throughput of a libSVM model on this machine.
+//!
+//! ```text
+//! ffsvm-bench [options] model_file
+//!
+//! options:
+//! -d data_file : predict the problems of a libSVM data file instead of random ones
+//! -n iterations : number of single predictions to measure (default 10000)
+//! -b batch_size : number of problems per batch (default 1024)
+//! ```
+//!
+//! Build with `cargo build --release --features cli`, numbers of debug builds are meaningless.
+
+use ffsvm::*;
+use std::{
+    env, fs, process,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "Usage: ffsvm-bench [options] model_file
+options:
+-d data_file : predict the problems of a libSVM data file instead of random ones
+-n iterations : number of single predictions to measure (default 10000)
+-b batch_size : number of problems per batch (default 1024)";
+
+/// Number of batches measured per thread count.
+const BATCH_REPETITIONS: usize = 16;
+
+struct Options {
+    data_file: Option<String>,
+    iterations: usize,
+    batch_size: usize,
+    model_file: String,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut data_file = None;
+    let mut iterations = 10_000;
+    let mut batch_size = 1024;
+    let mut i = 0;
+
+    while i < args.len() && args[i].starts_with('-') {
+        let value = args.get(i + 1)?;
+
+        match args[i].as_str() {
+            "-d" => data_file = Some(value.clone()),
+            "-n" => iterations = value.parse().ok()?,
+            "-b" => batch_size = value.parse().ok()?,
+            _ => return None,
+        }
+
+        i += 2;
+    }
+
+    match &args[i ..] {
+        [model_file] if iterations > 0 && batch_size > 0 => Some(Options {
+            data_file,
+            iterations,
+            batch_size,
+            model_file: model_file.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Reads the features of all problems of a data file, ignoring their labels.
+fn read_inputs(path: &str, num_attributes: usize) -> Result<Vec<Vec<f32>>, Error> {
+    let mut inputs = Vec::new();
+
+    for line in fs::read_to_string(path)?.lines().filter(|l| !l.trim().is_empty()) {
+        let mut features = vec![0.0; num_attributes];
+
+        for token in line.split_whitespace().skip(1) {
+            let mut pair = token.split(':');
+            let index = pair.next()?.parse::<usize>()?;
+            let value = pair.next()?.parse::<f32>()?;
+
+            if index < num_attributes {
+                features[index] = value;
+            }
+        }
+
+        inputs.push(features);
+    }
+
+    Ok(inputs)
+}
+
+fn random_inputs(count: usize, num_attributes: usize) -> Vec<Vec<f32>> { (0 .. count).map(|_| (0 .. num_attributes).map(|_| rand::random::<f32>()).collect()).collect() }
+
+/// Returns the given percentile of the `sorted` latencies in microseconds, or `None` if there are none.
+fn percentile(sorted: &[Duration], percentile: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    let latency = sorted[(last as f64 * percentile / 100.0).round() as usize];
+
+    Some(latency.as_secs() as f64 * 1e6 + f64::from(latency.subsec_nanos()) / 1e3)
+}
+
+/// Returns the widest SIMD instruction set this binary was compiled for.
+fn simd_level() -> &'static str {
+    if cfg!(target_feature = "avx512f") {
+        "AVX-512"
+    } else if cfg!(target_feature = "avx2") {
+        "AVX2"
+    } else if cfg!(target_feature = "avx") {
+        "AVX"
+    } else if cfg!(target_feature = "sse4.2") {
+        "SSE4.2"
+    } else if cfg!(target_feature = "neon") {
+        "NEON"
+    } else {
+        "baseline"
+    }
+}
+
+fn run(options: &Options) -> Result<(), Error> {
+    let mut svm = DenseSVM::from_file(&options.model_file)?;
+
+    let inputs = match &options.data_file {
+        Some(path) => read_inputs(path, svm.attributes())?,
+        None => random_inputs(1024, svm.attributes()),
+    };
+
+    if inputs.is_empty() {
+        return Err(Error::ParsingError("Data file contains no problems.".to_owned()));
+    }
+
+    let mut problems = (0 .. options.batch_size)
+        .map(|i| {
+            let mut problem = Problem::from(&svm);
+            problem.features().as_slice_mut()[.. svm.attributes()].copy_from_slice(&inputs[i % inputs.len()]);
+            problem
+        }).collect::<Vec<_>>();
+
+    let stats = svm.stats();
+
+    println!("Model:      {} SV, {} attributes, {:?}", stats.total_support_vectors, stats.attributes, stats.kernel);
+    println!("SIMD:       {}", simd_level());
+
+    // Single predictions, as in a real-time loop.
+    let mut latencies = Vec::with_capacity(options.iterations);
+
+    for i in 0 .. options.iterations {
+        let problem = &mut problems[i % options.batch_size];
+        let start = Instant::now();
+        svm.predict_value(problem)?;
+        latencies.push(start.elapsed());
+    }
+
+    latencies.sort();
+
+    if let (Some(p50), Some(p90), Some(p99), Some(max)) = (
+        percentile(&latencies, 50.0),
+        percentile(&latencies, 90.0),
+        percentile(&latencies, 99.0),
+        percentile(&latencies, 100.0),
+    ) {
+        println!("Latency:    p50 {:.2} us, p90 {:.2} us, p99 {:.2} us, max {:.2} us", p50, p90, p99, max);
+    }
+
+    // Batches, for each thread count.
+    let max_threads = rayon::current_num_threads();
+    let mut threads = 1;
+
+    while threads <= max_threads {
+        svm.set_threads(Some(threads))?;
+
+        let start = Instant::now();
+
+        for _ in 0 .. BATCH_REPETITIONS {
+            svm.predict_values(&mut problems)?;
+        }
+
+        let elapsed = start.elapsed();
+        let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        let throughput = (BATCH_REPETITIONS * options.batch_size) as f64 / seconds;
+
+        println!("Throughput: {:>3} threads, {:.0} problems/s", threads, throughput);
+
+        threads = if threads == max_threads { max_threads + 1 } else { (threads * 2).min(max_threads) };
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let options = match parse_options(&args) {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,130 @@
+//! Prepares a libSVM model for shipping, for example by pruning it and removing training details.
+//!
+//! ```text
+//! ffsvm-convert [options] input_model output_model
+//!
+//! options:
+//! -c labels : keep only the classes with the comma separated labels
+//! -p threshold : prune support vectors whose coefficients are all below threshold
+//! -s : strip unused kernel parameters and metadata
+//! -r : strip, and shuffle the support vectors within each class
+//! -m key=value : set metadata key to value, can be given multiple times
+//! ```
+//!
+//! Options are applied in the order above, so metadata given via `-m` survives `-s`. The output is
+//! always in libSVM's format, which is the only model format FFSVM reads. There is no binary,
+//! ONNX or PMML output, and support vectors are not quantized. Build with
+//! `cargo build --release --features cli`.
+
+#![feature(try_from)]
+
+use ffsvm::*;
+use std::{convert::TryFrom, env, fs, process};
+
+const USAGE: &str = "Usage: ffsvm-convert [options] input_model output_model
+options:
+-c labels : keep only the classes with the comma separated labels
+-p threshold : prune support vectors whose coefficients are all below threshold
+-s : strip unused kernel parameters and metadata
+-r : strip, and shuffle the support vectors within each class
+-m key=value : set metadata key to value, can be given multiple times";
+
+#[derive(Default)]
+struct Options {
+    classes: Option<Vec<i64>>,
+    prune: Option<f32>,
+    strip: bool,
+    shuffle: bool,
+    metadata: Vec<(String, String)>,
+    input: String,
+    output: String,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options::default();
+    let mut i = 0;
+
+    while i < args.len() && args[i].starts_with('-') {
+        match args[i].as_str() {
+            "-c" => {
+                i += 1;
+                options.classes = Some(args.get(i)?.split(',').map(|l| l.trim().parse().ok()).collect::<Option<_>>()?);
+            }
+            "-p" => {
+                i += 1;
+                options.prune = Some(args.get(i)?.parse().ok()?);
+            }
+            "-s" => options.strip = true,
+            "-r" => options.shuffle = true,
+            "-m" => {
+                i += 1;
+                let mut pair = args.get(i)?.splitn(2, '=');
+                options.metadata.push((pair.next()?.to_owned(), pair.next()?.to_owned()));
+            }
+            _ => return None,
+        }
+
+        i += 1;
+    }
+
+    match &args[i ..] {
+        [input, output] => Some(Options {
+            input: input.clone(),
+            output: output.clone(),
+            ..options
+        }),
+        _ => None,
+    }
+}
+
+fn run(options: &Options) -> Result<(), Error> {
+    let text = fs::read_to_string(&options.input)?;
+    let mut model = ModelFile::try_from(text.as_str())?;
+
+    let problems = model.validate();
+
+    if !problems.is_empty() {
+        return Err(Error::ParsingError(problems.join(" ")));
+    }
+
+    if let Some(labels) = &options.classes {
+        model = model.restrict_classes(labels);
+    }
+
+    if let Some(threshold) = options.prune {
+        model = model.pruned(threshold);
+    }
+
+    if options.strip || options.shuffle {
+        model = model.stripped(options.shuffle);
+    }
+
+    for (key, value) in &options.metadata {
+        model.set_metadata(key, value);
+    }
+
+    // Catches metadata that can't be read back, for example keys that are no identifiers.
+    let output = model.to_string();
+    ModelFile::try_from(output.as_str())?;
+
+    fs::write(&options.output, output)?;
+
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let options = match parse_options(&args) {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,85 @@
+//! Prints a summary of a libSVM model and checks it for internal inconsistencies.
+//!
+//! ```text
+//! ffsvm-inspect model_file
+//! ```
+//!
+//! Exits with a non-zero status if the model is inconsistent or can't be loaded. Build with
+//! `cargo build --release --features cli`.
+
+#![feature(try_from)]
+
+use ffsvm::*;
+use std::{convert::TryFrom, env, fs, process};
+
+const USAGE: &str = "Usage: ffsvm-inspect model_file";
+
+fn print_summary<'a>(kind: &str, stats: &Stats, rho: &[f64], metadata: impl Iterator<Item = (&'a str, &'a str)>) {
+    let labels = stats.support_vectors.iter().map(|(label, count)| format!("{} ({} SV)", label, count)).collect::<Vec<_>>();
+    let rho = rho.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+
+    println!("SVM type:        {:?}", stats.svm_type);
+    println!("Kernel:          {:?}", stats.kernel);
+    println!("Classes:         {}", stats.support_vectors.len());
+    println!("Labels:          {}", labels.join(", "));
+    println!("Support vectors: {}", stats.total_support_vectors);
+    println!("Attributes:      {}", stats.attributes);
+    println!("rho:             {}", if rho.is_empty() { "-".to_string() } else { rho.join(" ") });
+    println!("Probabilities:   {}", if stats.probabilities { "yes" } else { "no" });
+    println!("Density:         {:.1}%", 100.0 * stats.density);
+    println!("Memory:          {} bytes as {}", stats.heap_bytes, kind);
+
+    for (key, value) in metadata {
+        println!("Metadata:        {} = {}", key, value);
+    }
+}
+
+fn run(path: &str) -> Result<bool, Error> {
+    let text = fs::read_to_string(path)?;
+    let model = ModelFile::try_from(text.as_str())?;
+    let problems = model.validate();
+
+    println!("Model:           {}", path);
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("Inconsistent:    {}", problem);
+        }
+
+        return Ok(false);
+    }
+
+    // Dense models need all attributes of all support vectors, otherwise we fall back to sparse.
+    match DenseSVM::try_from(&model) {
+        Ok(svm) => print_summary("DenseSVM", &svm.stats(), model.rho(), svm.metadata_entries()),
+        Err(_) => {
+            let svm = SparseSVM::try_from(&model)?;
+            print_summary("SparseSVM", &svm.stats(), model.rho(), svm.metadata_entries())
+        }
+    }
+
+    println!("Consistency:     OK");
+
+    Ok(true)
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let path = match args.as_slice() {
+        [path] => path,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    match run(path) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Predicts a data file with a libSVM model, mirroring libSVM's `svm-predict`.
+//!
+//! ```text
+//! ffsvm-predict [options] test_file model_file output_file
+//!
+//! options:
+//! -b probability_estimates: whether to predict probability estimates, 0 or 1 (default 0)
+//! -c : test_file is CSV with one problem per line and no labels
+//! -t threads : number of threads scoring each batch, 0 for all cores (default 1)
+//! -q : quiet mode (no outputs)
+//! ```
+//!
+//! Data files use libSVM's format, one problem per line as `<label> <index>:<value> ...`, with
+//! strictly increasing indices. They are streamed and scored in batches, so arbitrarily large
+//! files can be predicted. Build with `cargo build --release --features cli`.
+
+use ffsvm::*;
+use std::{
+    env,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    process,
+    time::Instant,
+};
+
+const USAGE: &str = "Usage: ffsvm-predict [options] test_file model_file output_file
+options:
+-b probability_estimates: whether to predict probability estimates, 0 or 1 (default 0)
+-c : test_file is CSV with one problem per line and no labels
+-t threads : number of threads scoring each batch, 0 for all cores (default 1)
+-q : quiet mode (no outputs)";
+
+/// Number of problems read and scored at once.
+const BATCH_SIZE: usize = 4096;
+
+struct Options {
+    probability: bool,
+    csv: bool,
+    threads: usize,
+    quiet: bool,
+    test_file: String,
+    model_file: String,
+    output_file: String,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut probability = false;
+    let mut csv = false;
+    let mut threads = 1;
+    let mut quiet = false;
+    let mut i = 0;
+
+    while i < args.len() && args[i].starts_with('-') {
+        match args[i].as_str() {
+            "-b" => {
+                i += 1;
+                probability = match args.get(i)?.as_str() {
+                    "0" => false,
+                    "1" => true,
+                    _ => return None,
+                };
+            }
+            "-c" => csv = true,
+            "-t" => {
+                i += 1;
+                threads = args.get(i)?.parse().ok()?;
+            }
+            "-q" => quiet = true,
+            _ => return None,
+        }
+
+        i += 1;
+    }
+
+    match &args[i ..] {
+        [test_file, model_file, output_file] => Some(Options {
+            probability,
+            csv,
+            threads,
+            quiet,
+            test_file: test_file.clone(),
+            model_file: model_file.clone(),
+            output_file: output_file.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Parses one line `<label> <index>:<value> ...` of a data file into the target and the features.
+fn parse_line(line: &str, indices: &mut Vec<u32>, values: &mut Vec<f32>) -> Result<Option<f64>, Error> {
+    let mut tokens = line.split_whitespace();
+    let target = tokens.next()?.parse::<f64>()?;
+
+    indices.clear();
+    values.clear();
+
+    for token in tokens {
+        let mut pair = token.split(':');
+        indices.push(pair.next()?.parse()?);
+        values.push(pair.next()?.parse()?);
+    }
+
+    Ok(Some(target))
+}
+
+/// Parses one line `<value>,<value>,...` of a CSV file into the features, CSV files have no targets.
+fn parse_csv_line(line: &str, indices: &mut Vec<u32>, values: &mut Vec<f32>) -> Result<Option<f64>, Error> {
+    indices.clear();
+    values.clear();
+
+    for (i, value) in line.split(',').enumerate() {
+        indices.push(i as u32);
+        values.push(value.trim().parse()?);
+    }
+
+    Ok(None)
+}
+
+/// Accuracy or regression error of all predictions with a known target.
+#[derive(Default)]
+struct Evaluation {
+    total: u32,
+    correct: u32,
+    error: f64,
+    sum_p: f64,
+    sum_t: f64,
+    sum_pp: f64,
+    sum_tt: f64,
+    sum_pt: f64,
+}
+
+impl Evaluation {
+    fn add(&mut self, predicted: f64, target: f64) {
+        if predicted == target {
+            self.correct += 1;
+        }
+
+        self.error += (predicted - target) * (predicted - target);
+        self.sum_p += predicted;
+        self.sum_t += target;
+        self.sum_pp += predicted * predicted;
+        self.sum_tt += target * target;
+        self.sum_pt += predicted * target;
+        self.total += 1;
+    }
+
+    fn print(&self, regression: bool) {
+        if self.total == 0 {
+            return;
+        }
+
+        let n = f64::from(self.total);
+
+        if regression {
+            let covariance = n * self.sum_pt - self.sum_p * self.sum_t;
+            let squared_correlation = (covariance * covariance) / ((n * self.sum_pp - self.sum_p * self.sum_p) * (n * self.sum_tt - self.sum_t * self.sum_t));
+
+            println!("Mean squared error = {} (regression)", self.error / n);
+            println!("Squared correlation coefficient = {} (regression)", squared_correlation);
+        } else {
+            println!("Accuracy = {}% ({}/{}) (classification)", 100.0 * f64::from(self.correct) / n, self.correct, self.total);
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<(), Error> {
+    let mut svm = SparseSVM::from_file(&options.model_file)?;
+
+    svm.set_threads(match options.threads {
+        0 => None,
+        n => Some(n),
+    })?;
+
+    let input = BufReader::new(File::open(&options.test_file)?);
+    let mut output = BufWriter::new(File::create(&options.output_file)?);
+    let parse = if options.csv { parse_csv_line } else { parse_line };
+
+    let mut problems = (0 .. BATCH_SIZE).map(|_| Problem::from(&svm)).collect::<Vec<_>>();
+    let mut targets = Vec::with_capacity(BATCH_SIZE);
+    let mut indices = Vec::new();
+    let mut values = Vec::new();
+
+    let mut evaluation = Evaluation::default();
+    let mut regression = false;
+    let mut num_predicted = 0;
+    let start = Instant::now();
+
+    if options.probability {
+        let labels = svm.labels().map(|l| l.to_string()).collect::<Vec<_>>();
+        writeln!(output, "labels {}", labels.join(" "))?;
+    }
+
+    let mut lines = input.lines().peekable();
+
+    while lines.peek().is_some() {
+        targets.clear();
+
+        // Read the next batch.
+        while targets.len() < BATCH_SIZE {
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            targets.push(parse(&line, &mut indices, &mut values)?);
+            problems[targets.len() - 1].set_features(&indices, &values)?;
+        }
+
+        let batch = &mut problems[.. targets.len()];
+
+        if options.probability {
+            svm.predict_probabilities(batch)?;
+        } else {
+            svm.predict_values(batch)?;
+        }
+
+        for (problem, target) in batch.iter().zip(&targets) {
+            let predicted = match problem.solution() {
+                Solution::Label(label) => label as f64,
+                Solution::Value(value) => {
+                    regression = true;
+                    f64::from(value)
+                }
+                _ => std::f64::NAN,
+            };
+
+            if options.probability {
+                let probabilities = problem.probabilities().iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                writeln!(output, "{} {}", predicted, probabilities.join(" "))?;
+            } else {
+                writeln!(output, "{}", predicted)?;
+            }
+
+            if let Some(target) = target {
+                evaluation.add(predicted, *target);
+            }
+        }
+
+        num_predicted += targets.len();
+    }
+
+    output.flush()?;
+
+    if options.quiet {
+        return Ok(());
+    }
+
+    evaluation.print(regression);
+
+    let elapsed = start.elapsed();
+    let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+    println!("Predicted {} problems in {:.3} s ({:.0} problems/s)", num_predicted, seconds, num_predicted as f64 / seconds);
+
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let options = match parse_options(&args) {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
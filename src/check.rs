@@ -0,0 +1,95 @@
+use std::{convert::TryFrom, fs, path::Path};
+
+use crate::{parser::ModelFile, svm::DenseSVM};
+
+/// Returns all problems of the model file at `path`, or nothing if it can be used.
+///
+/// The file must be readable, parse, pass [ModelFile::validate] and load as a [DenseSVM]. Models
+/// with a kernel implemented outside of FFSVM can't be loaded this way and always report their
+/// `kernel_type`.
+pub fn check_model_file<P: AsRef<Path>>(path: P) -> Vec<String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return vec![format!("Error reading model: {}", e)],
+    };
+
+    let model = match ModelFile::try_from(text.as_str()) {
+        Ok(model) => model,
+        Err(e) => return vec![e.to_string()],
+    };
+
+    let problems = model.validate();
+
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    match DenseSVM::try_from(&model) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// Checks the model files bundled with an application, meant to be called from its `build.rs`.
+///
+/// # Description
+///
+/// Each model is checked with [check_model_file], so broken models fail the build instead of
+/// the first launch of the application. Cargo is told to rerun the build script if any of the
+/// models change, and each problem found is printed as cargo warning naming its file.
+///
+/// ```rust,no_run
+/// // build.rs, with ffsvm in [build-dependencies]
+/// fn main() { ffsvm::check_bundled_models(&["models/gaze.model", "models/blink.model"]); }
+/// ```
+///
+/// # Panics
+///
+/// If any model has a problem, after all models were checked.
+pub fn check_bundled_models<P: AsRef<Path>>(paths: &[P]) {
+    let mut num_broken = 0;
+
+    for path in paths {
+        let path = path.as_ref();
+        let problems = check_model_file(path);
+
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        for problem in &problems {
+            println!("cargo:warning={}: {}", path.display(), problem);
+        }
+
+        if !problems.is_empty() {
+            num_broken += 1;
+        }
+    }
+
+    if num_broken > 0 {
+        panic!("{} of {} bundled models are broken, see the warnings above.", num_broken, paths.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn checks_model_files() -> Result<(), Error> {
+        let valid = std::env::temp_dir().join(format!("ffsvm_check_valid_{}.model", std::process::id()));
+        let broken = std::env::temp_dir().join(format!("ffsvm_check_broken_{}.model", std::process::id()));
+
+        std::fs::write(&valid, SAMPLE_MODEL)?;
+        std::fs::write(&broken, SAMPLE_MODEL.replace("nr_sv 1 1", "nr_sv 1 2"))?;
+
+        assert!(check_model_file(&valid).is_empty());
+        assert!(!check_model_file(&broken).is_empty());
+        assert!(!check_model_file(std::env::temp_dir().join("ffsvm_check_missing.model")).is_empty());
+
+        check_bundled_models(&[&valid]);
+
+        std::fs::remove_file(&valid)?;
+        std::fs::remove_file(&broken)?;
+
+        Ok(())
+    }
+}
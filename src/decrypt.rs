@@ -0,0 +1,88 @@
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use crate::errors::Error;
+
+/// Decrypts models that are stored encrypted, see [SVMCore::from_encrypted_file]. Requires the
+/// `encryption` feature.
+///
+/// # Description
+///
+/// FFSVM does not implement any cipher itself. Applications shipping license-sensitive models
+/// keep them encrypted at rest with a cipher of their choice, and implement this trait (or pass
+/// a closure) to decrypt them in memory while loading. Failures should be reported as
+/// [Error::Decryption]:
+///
+/// ```rust
+/// use ffsvm::*;
+///
+/// fn main() -> Result<(), Error> {
+///     // Don't do this at home, use an authenticated cipher like AES-GCM instead.
+///     let decrypt = |data: &[u8]| Ok::<_, Error>(data.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>());
+///
+///     let encrypted = SAMPLE_MODEL.bytes().map(|b| b ^ 0x5a).collect::<Vec<u8>>();
+///     let svm = DenseSVM::from_encrypted_reader(encrypted.as_slice(), "bundled/sample.model", &decrypt)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub trait Decrypt {
+    /// Returns the plain text of the model stored as `data`.
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+impl<F> Decrypt for F
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>, Error>,
+{
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> { self(data) }
+}
+
+/// Decrypts `data` with `decrypt` into the text of a model.
+crate fn decrypt_model(data: &[u8], decrypt: &dyn Decrypt) -> Result<String, Error> {
+    match String::from_utf8(decrypt.decrypt(data)?) {
+        Ok(model) => Ok(model),
+        Err(e) => {
+            wipe(e.into_bytes());
+            Err(Error::Decryption("Decrypted model is no UTF-8 text, is the key right?".to_owned()))
+        }
+    }
+}
+
+/// Overwrites the decrypted model in `bytes` before its memory is freed.
+#[allow(unsafe_code)]
+crate fn wipe(mut bytes: Vec<u8>) {
+    for byte in bytes.iter_mut() {
+        // Volatile, so the writes aren't optimized away as the vector is dropped right after.
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+
+    #[test]
+    fn encrypted_models() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("ffsvm_encrypted_{}.model", std::process::id()));
+        let decrypt = |data: &[u8]| Ok::<_, Error>(data.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>());
+
+        std::fs::write(&path, SAMPLE_MODEL.bytes().map(|b| b ^ 0x5a).collect::<Vec<u8>>())?;
+
+        let svm = DenseSVM::from_encrypted_file(&path, &decrypt)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), Solution::Label(42));
+
+        assert!(DenseSVM::from_file(&path).is_err());
+        assert!(SparseSVM::from_encrypted_file(&path, &|_: &[u8]| Err::<Vec<u8>, _>(Error::Decryption("Wrong key.".to_owned()))).is_err());
+
+        let garbage = DenseSVM::from_encrypted_reader(&[0xff_u8, 0xfe][..], "garbage", &decrypt);
+        assert!(garbage.map_err(|e| e.to_string()).unwrap_err().contains("UTF-8"));
+
+        Ok(())
+    }
+}
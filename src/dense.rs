@@ -0,0 +1,116 @@
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix};
+
+use crate::{svm::stats::simd_bytes, util::Pages};
+
+#[cfg(feature = "mlock")]
+use crate::errors::Error;
+
+/// Memory layout of the support vectors of a [DenseSVM].
+///
+/// By default the layout is chosen when the model is loaded, based on the number of
+/// support vectors and attributes. It can be changed later via [DenseSVM::set_layout].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Layout {
+    /// Each support vector is stored in one SIMD row, and kernels vectorize over
+    /// the attributes. Best for the vast majority of models.
+    Rows,
+
+    /// Each attribute is stored in one SIMD column, and kernels vectorize over the support
+    /// vectors. Can be faster for models with huge attribute counts and few support vectors.
+    Columns,
+}
+
+/// If a model has at least this many times more attributes than support vectors we prefer columns.
+const COLUMNS_MIN_ATTRIBUTES_PER_SV: usize = 8;
+
+impl Layout {
+    /// Returns the layout we expect to perform best for a model of the given shape.
+    pub fn for_shape(num_total_sv: usize, num_attributes: usize) -> Layout {
+        if num_attributes >= COLUMNS_MIN_ATTRIBUTES_PER_SV * num_total_sv.max(1) {
+            Layout::Columns
+        } else {
+            Layout::Rows
+        }
+    }
+}
+
+/// Support vectors of a dense class, stored in either [Layout].
+#[derive(Clone, Debug)]
+pub enum DenseMatrix {
+    Rows(SimdMatrix<f32s, RowOptimized>),
+    Columns(SimdMatrix<f32s, ColumnOptimized>),
+}
+
+impl DenseMatrix {
+    /// Creates a zeroed matrix with `rows` support vectors and `columns` attributes.
+    crate fn with_dimension(rows: usize, columns: usize, layout: Layout) -> DenseMatrix {
+        match layout {
+            Layout::Rows => DenseMatrix::Rows(SimdMatrix::with_dimension(rows, columns)),
+            Layout::Columns => DenseMatrix::Columns(SimdMatrix::with_dimension(rows, columns)),
+        }
+    }
+
+    /// Returns the layout of this matrix.
+    crate fn layout(&self) -> Layout {
+        match self {
+            DenseMatrix::Rows(_) => Layout::Rows,
+            DenseMatrix::Columns(_) => Layout::Columns,
+        }
+    }
+
+    /// Returns the attribute `column` of support vector `row`.
+    #[inline]
+    crate fn get(&self, row: usize, column: usize) -> f32 {
+        match self {
+            DenseMatrix::Rows(m) => m.flat()[(row, column)],
+            DenseMatrix::Columns(m) => m.flat()[(row, column)],
+        }
+    }
+
+    /// Sets the attribute `column` of support vector `row`.
+    #[inline]
+    crate fn set(&mut self, row: usize, column: usize, value: f32) {
+        match self {
+            DenseMatrix::Rows(m) => m.flat_mut()[(row, column)] = value,
+            DenseMatrix::Columns(m) => m.flat_mut()[(row, column)] = value,
+        }
+    }
+
+    /// Returns the heap bytes used by a matrix with `rows` support vectors and `columns` attributes.
+    crate fn heap_bytes(&self, rows: usize, columns: usize) -> usize {
+        match self {
+            DenseMatrix::Rows(_) => simd_bytes::<f32s>(rows, columns, f32s::lanes()),
+            DenseMatrix::Columns(_) => simd_bytes::<f32s>(columns, rows, f32s::lanes()),
+        }
+    }
+
+    /// Returns a copy of this matrix in the given layout.
+    crate fn to_layout(&self, rows: usize, columns: usize, layout: Layout) -> DenseMatrix {
+        let mut rval = DenseMatrix::with_dimension(rows, columns, layout);
+
+        for row in 0 .. rows {
+            for column in 0 .. columns {
+                rval.set(row, column, self.get(row, column));
+            }
+        }
+
+        rval
+    }
+}
+
+impl Pages for DenseMatrix {
+    fn touch(&self) {
+        match self {
+            DenseMatrix::Rows(m) => m.touch(),
+            DenseMatrix::Columns(m) => m.touch(),
+        }
+    }
+
+    #[cfg(feature = "mlock")]
+    fn lock(&self) -> Result<(), Error> {
+        match self {
+            DenseMatrix::Rows(m) => m.lock(),
+            DenseMatrix::Columns(m) => m.lock(),
+        }
+    }
+}
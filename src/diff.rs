@@ -0,0 +1,232 @@
+use std::fmt;
+
+use crate::parser::ModelFile;
+
+/// Differences between two models, see [diff].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelDiff {
+    /// All header fields that differ.
+    pub header: Vec<HeaderChange>,
+
+    /// All classes whose number of support vectors differs, including added and removed classes.
+    pub classes: Vec<ClassChange>,
+
+    /// Drift of the `rho` values, if both models have the same number of them.
+    pub rho: Option<Drift>,
+
+    /// Drift of the support vector coefficients, if both models have the same number of support
+    /// vectors per class. Coefficients are compared by position.
+    pub coefficients: Option<Drift>,
+}
+
+/// A header field that differs between two models.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderChange {
+    /// Name of the field, as in the model file.
+    pub field: &'static str,
+
+    /// Value in the old model.
+    pub old: String,
+
+    /// Value in the new model.
+    pub new: String,
+}
+
+/// A class whose number of support vectors differs between two models.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClassChange {
+    /// Label of the class.
+    pub label: i64,
+
+    /// Number of support vectors in the old model, `None` if the class was added.
+    pub old: Option<u32>,
+
+    /// Number of support vectors in the new model, `None` if the class was removed.
+    pub new: Option<u32>,
+}
+
+/// Statistics of the absolute differences between values of two models.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Drift {
+    /// Mean of all absolute differences.
+    pub mean: f64,
+
+    /// Largest absolute difference.
+    pub max: f64,
+
+    /// Root mean square of all differences.
+    pub rms: f64,
+}
+
+impl ModelDiff {
+    /// Returns `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        let no_drift = |drift: &Option<Drift>| drift.map_or(true, |d| d.max == 0.0);
+
+        self.header.is_empty() && self.classes.is_empty() && no_drift(&self.rho) && no_drift(&self.coefficients)
+    }
+}
+
+impl Drift {
+    /// Computes the drift between `old` and `new`, which must have the same length.
+    fn between(old: impl Iterator<Item = f64>, new: impl Iterator<Item = f64>) -> Drift {
+        let mut count = 0u32;
+        let mut sum = 0.0;
+        let mut sum_squared = 0.0;
+        let mut max = 0.0f64;
+
+        for (a, b) in old.zip(new) {
+            let delta = (a - b).abs();
+
+            count += 1;
+            sum += delta;
+            sum_squared += delta * delta;
+            max = max.max(delta);
+        }
+
+        let count = f64::from(count.max(1));
+
+        Drift {
+            mean: sum / count,
+            max,
+            rms: (sum_squared / count).sqrt(),
+        }
+    }
+}
+
+/// Compares two models and reports their differences.
+///
+/// # Description
+///
+/// This is meant to review model updates, for example when a retrained model is checked in. It
+/// reports changed header fields, changed support vector counts per class, and how much `rho`
+/// and the support vector coefficients drifted.
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let old = ModelFile::try_from(SAMPLE_MODEL)?;
+///     let new = ModelFile::try_from(SAMPLE_MODEL)?;
+///
+///     assert!(diff(&old, &new).is_empty());
+///
+///     Ok(())
+/// }
+/// ```
+pub fn diff(old: &ModelFile<'_>, new: &ModelFile<'_>) -> ModelDiff {
+    let (a, b) = (&old.header, &new.header);
+    let mut rval = ModelDiff::default();
+
+    {
+        let mut compare = |field: &'static str, old: String, new: String| {
+            if old != new {
+                rval.header.push(HeaderChange { field, old, new });
+            }
+        };
+
+        compare("svm_type", a.svm_type.to_owned(), b.svm_type.to_owned());
+        compare("kernel_type", a.kernel_type.to_owned(), b.kernel_type.to_owned());
+        compare("gamma", format!("{:?}", a.gamma), format!("{:?}", b.gamma));
+        compare("coef0", format!("{:?}", a.coef0), format!("{:?}", b.coef0));
+        compare("degree", format!("{:?}", a.degree), format!("{:?}", b.degree));
+        compare("nr_class", a.nr_class.to_string(), b.nr_class.to_string());
+        compare("total_sv", a.total_sv.to_string(), b.total_sv.to_string());
+        compare("label", format!("{:?}", a.label), format!("{:?}", b.label));
+        compare("probA", a.prob_a.is_some().to_string(), b.prob_a.is_some().to_string());
+        compare("attributes", attributes(old).to_string(), attributes(new).to_string());
+    }
+
+    for (label, num_sv) in a.label.iter().zip(&a.nr_sv) {
+        let other = b.label.iter().position(|l| l == label).and_then(|i| b.nr_sv.get(i)).cloned();
+
+        if other != Some(*num_sv) {
+            rval.classes.push(ClassChange {
+                label: *label,
+                old: Some(*num_sv),
+                new: other,
+            });
+        }
+    }
+
+    for (label, num_sv) in b.label.iter().zip(&b.nr_sv) {
+        if !a.label.contains(label) {
+            rval.classes.push(ClassChange {
+                label: *label,
+                old: None,
+                new: Some(*num_sv),
+            });
+        }
+    }
+
+    if a.rho.len() == b.rho.len() {
+        rval.rho = Some(Drift::between(a.rho.iter().cloned(), b.rho.iter().cloned()));
+    }
+
+    if a.label == b.label && a.nr_sv == b.nr_sv && old.vectors.len() == new.vectors.len() {
+        let coefficients = |model: &ModelFile<'_>| model.vectors.iter().flat_map(|v| v.coefs.iter().map(|c| f64::from(*c))).collect::<Vec<_>>();
+        let (old_coefficients, new_coefficients) = (coefficients(old), coefficients(new));
+
+        if old_coefficients.len() == new_coefficients.len() {
+            rval.coefficients = Some(Drift::between(old_coefficients.into_iter(), new_coefficients.into_iter()));
+        }
+    }
+
+    rval
+}
+
+/// Number of attributes of the first support vector.
+fn attributes(model: &ModelFile<'_>) -> usize { model.vectors.first().map_or(0, |v| v.features.len()) }
+
+impl fmt::Display for ModelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.header {
+            writeln!(f, "{}: {} -> {}", change.field, change.old, change.new)?;
+        }
+
+        for change in &self.classes {
+            let count = |n: Option<u32>| n.map_or_else(|| "-".to_owned(), |n| n.to_string());
+            writeln!(f, "class {}: {} -> {} support vectors", change.label, count(change.old), count(change.new))?;
+        }
+
+        if let Some(drift) = self.rho {
+            writeln!(f, "rho drift: {}", drift)?;
+        }
+
+        if let Some(drift) = self.coefficients {
+            writeln!(f, "coefficient drift: {}", drift)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "mean {:.6}, max {:.6}, rms {:.6}", self.mean, self.max, self.rms) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn detects_changes() -> Result<(), Error> {
+        let changed = SAMPLE_MODEL.replace("rho -0.25527", "rho -0.5").replace("label 21 42", "label 21 43");
+
+        let old = ModelFile::try_from(SAMPLE_MODEL)?;
+        let new = ModelFile::try_from(changed.as_str())?;
+        let diff = diff(&old, &new);
+
+        assert!(!diff.is_empty());
+        assert_eq!(diff.header.len(), 1);
+        assert_eq!(diff.classes.len(), 2);
+        assert_eq!(diff.coefficients, None);
+        assert!((diff.rho.unwrap().max - 0.24473).abs() < 1e-9);
+
+        Ok(())
+    }
+}
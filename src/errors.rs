@@ -1,10 +1,14 @@
 use std::{
+    fmt, io,
     num::{ParseFloatError, ParseIntError},
     option::NoneError,
 };
 
-/// Possible error types when classifying with a [SVMCore].
+/// Possible error types when loading models or classifying with a [SVMCore].
+///
+/// New variants may be added in minor releases, so matches must have a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// This can be emitted when creating a [SVM] from a [ModelFile]. For models generated by
     /// libSVM's `svm-train`, the most common reason this occurs is skipping attributes.
@@ -28,8 +32,12 @@ pub enum Error {
     NoProbabilities,
 
     /// Can be emitted by [Predict::predict_probability()] when predicting probabilities
-    /// and the internal iteration limit was exceeded.
-    IterationsExceeded,
+    /// and the iteration limit was exceeded, see [SVMCore::set_coupling_max_iterations].
+    IterationsExceeded {
+        /// The largest remaining error after the last iteration. Convergence requires it to
+        /// drop below the epsilon, see [SVMCore::set_coupling_epsilon].
+        residual: f64,
+    },
 
     /// If the model does not have a `gamma` set this error may be raised.
     NoGamma,
@@ -40,10 +48,231 @@ pub enum Error {
     /// If the model does not have a `degree` set this error may be raised.
     NoDegree,
 
+    /// Emitted when converting between [DenseSVM] and [SparseSVM] if the kernel used by
+    /// the model does not support the target representation.
+    KernelNotConvertible,
+
+    /// Emitted when converting between [DenseSVM] and [SparseSVM] if the support vectors
+    /// are too sparse (or too dense) for the requested conversion.
+    DensityOutOfBounds {
+        /// The fraction of non-zero attributes over all support vectors.
+        density: f32,
+
+        /// The threshold that was requested for the conversion.
+        threshold: f32,
+    },
+
+    /// Emitted when combining a SVM into an [Ensemble], [Cascade] or [Hierarchy] it does not fit,
+    /// for example because it is no classifier or has a different number of classes. Also
+    /// emitted by [ReloadableSvm] if a problem does not fit the reloaded model.
+    IncompatibleModel,
+
+    /// Emitted when parsing a model written by a newer version of FFSVM, see [FORMAT_VERSION].
+    UnsupportedFormatVersion {
+        /// The format version of the model.
+        version: u32,
+
+        /// The newest format version this crate supports.
+        supported: u32,
+    },
+
+    /// A label was given that is not part of the model.
+    UnknownLabel(i64),
+
+    /// Emitted by [SVMCore::set_temperature] if the temperature is not positive and finite.
+    InvalidTemperature(f64),
+
+    /// Emitted by [SparseProblem::set_features] if there is not exactly one value per index, and
+    /// by [PredictIter] for items with more values than the model has attributes.
+    FeatureCountMismatch {
+        /// The number of indices given, or of attributes available.
+        indices: usize,
+
+        /// The number of values given.
+        values: usize,
+    },
+
+    /// Emitted when parsing a model that lacks a required section, like `svm_type` or the support vectors.
+    MissingSection(&'static str),
+
+    /// Emitted when loading a model with a section FFSVM does not support, for example an unknown
+    /// `kernel_type`.
+    UnsupportedSection {
+        /// The section, for example `kernel_type`.
+        section: &'static str,
+
+        /// The unsupported value found in the model.
+        value: String,
+    },
+
+    /// Emitted when loading a model whose sections don't agree, for example if `nr_class` is `3`
+    /// but only two labels are given. Use [ModelFile::validate] to list all such problems at once.
+    CountMismatch {
+        /// The section with the wrong number of entries, for example `label`.
+        section: &'static str,
+
+        /// The number of entries other sections require.
+        expected: usize,
+
+        /// The number of entries found.
+        found: usize,
+    },
+
+    /// Emitted when parsing a model that exceeds one of its [Limits], before anything of that size
+    /// is allocated.
+    LimitExceeded {
+        /// The limit, for example `max_support_vectors`.
+        limit: &'static str,
+
+        /// The value the model requires.
+        value: usize,
+
+        /// The largest allowed value.
+        max: usize,
+    },
+
+    /// Emitted by a [Decrypt] implementation if a model could not be decrypted, or if the decrypted
+    /// model is no text.
+    Decryption(String),
+
+    /// Emitted when loading a signed model that no trusted key signed, or whose signature is
+    /// malformed, see [TrustedKeys].
+    SignatureMismatch,
+
+    /// Emitted by [TrustedKeys::new] if a key is no valid ed25519 public key.
+    InvalidPublicKey,
+
+    /// Emitted when a model file could not be read, see [SVMCore::from_file].
+    Io(io::Error),
+
+    /// Wraps an error with where in which model it occurred. Parsing errors carry the byte
+    /// offset of the offending value, [SVMCore::from_file] and [SVMCore::from_reader] add the
+    /// origin of the model. Use [Error::root] to match on the underlying error.
+    Context {
+        /// Where the model was loaded from, usually its path.
+        origin: Option<String>,
+
+        /// Byte offset into the model at which the error occurred, if known.
+        offset: Option<usize>,
+
+        /// The underlying error.
+        error: Box<Error>,
+    },
+
+    /// Emitted by [SVMCore::set_threads] if the thread pool could not be created.
+    ThreadPool(rayon::ThreadPoolBuildError),
+
     /// Wrapper for internal parsing error when unifiying error handling.
     ParsingError(String),
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AttributesUnordered { index, value, last_index } => write!(
+                f,
+                "Attributes of support vector not in order, got index {} (value {}) after index {}.",
+                index, value, last_index
+            ),
+            Error::NoProbabilities => write!(f, "Model was not trained with probability estimates (`svm-train -b 1`)."),
+            Error::IterationsExceeded { residual } => write!(f, "Probability coupling did not converge, residual {}.", residual),
+            Error::NoGamma => write!(f, "Model has no `gamma`."),
+            Error::NoCoef0 => write!(f, "Model has no `coef0`."),
+            Error::NoDegree => write!(f, "Model has no `degree`."),
+            Error::KernelNotConvertible => write!(f, "Kernel can't be converted between dense and sparse."),
+            Error::DensityOutOfBounds { density, threshold } => write!(f, "Model density {} is out of bounds of threshold {}.", density, threshold),
+            Error::IncompatibleModel => write!(f, "Model is not compatible with the other models or the problem."),
+            Error::UnsupportedFormatVersion { version, supported } => {
+                write!(f, "Model format version {} is not supported, the newest supported version is {}.", version, supported)
+            }
+            Error::UnknownLabel(label) => write!(f, "Label {} is not part of the model.", label),
+            Error::InvalidTemperature(temperature) => write!(f, "Temperature {} is not positive and finite.", temperature),
+            Error::FeatureCountMismatch { indices, values } => write!(f, "Got {} feature values for {} indices.", values, indices),
+            Error::MissingSection(section) => write!(f, "Model has no `{}`.", section),
+            Error::UnsupportedSection { section, value } => write!(f, "Model has an unsupported `{}` of `{}`.", section, value),
+            Error::CountMismatch { section, expected, found } => write!(f, "Model has {} entries in `{}`, expected {}.", found, section, expected),
+            Error::LimitExceeded { limit, value, max } => write!(f, "Model exceeds `{}` with {}, the maximum is {}.", limit, value, max),
+            Error::ThreadPool(e) => write!(f, "Error creating thread pool: {}", e),
+            Error::Decryption(reason) => write!(f, "Error decrypting model: {}", reason),
+            Error::SignatureMismatch => write!(f, "Model signature is not valid for any trusted key."),
+            Error::InvalidPublicKey => write!(f, "Public key is no valid ed25519 key."),
+            Error::Io(e) => write!(f, "Error reading model: {}", e),
+            Error::Context { origin, offset, error } => {
+                write!(f, "In model")?;
+
+                if let Some(origin) = origin {
+                    write!(f, " `{}`", origin)?;
+                }
+
+                if let Some(offset) = offset {
+                    write!(f, " at byte {}", offset)?;
+                }
+
+                write!(f, ": {}", error)
+            }
+            Error::ParsingError(message) => write!(f, "Error parsing model: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ThreadPool(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Context { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Returns the underlying error if this is an [Error::Context], or the error itself otherwise.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { error, .. } => error.root(),
+            _ => self,
+        }
+    }
+
+    /// Records the byte `offset` at which this error occurred.
+    crate fn at_offset(self, offset: usize) -> Error {
+        Error::Context {
+            origin: None,
+            offset: Some(offset),
+            error: Box::new(self),
+        }
+    }
+
+    /// Moves the offset of this error by `start`, for errors in a part of the model beginning there.
+    crate fn offset_by(self, start: usize) -> Error {
+        match self {
+            Error::Context { origin, offset: Some(offset), error } => Error::Context {
+                origin,
+                offset: Some(start + offset),
+                error,
+            },
+            error => error,
+        }
+    }
+
+    /// Records the `origin` of the model in which this error occurred.
+    crate fn with_origin(self, origin: &str) -> Error {
+        match self {
+            Error::Context { origin: None, offset, error } => Error::Context {
+                origin: Some(origin.to_owned()),
+                offset,
+                error,
+            },
+            _ => Error::Context {
+                origin: Some(origin.to_owned()),
+                offset: None,
+                error: Box::new(self),
+            },
+        }
+    }
+}
+
 // impl<'a, T> From<Error<'a, T>> for Error {
 //     fn from(_: Error<'a, T>) -> Self {
 //         Error::ParsingError
@@ -54,14 +283,22 @@ impl<'a> From<pest::Error<'a, crate::parser::Rule>> for Error {
     fn from(e: pest::Error<'a, crate::parser::Rule>) -> Self { Error::ParsingError(format!("{}", e)) }
 }
 
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self { Error::Io(e) }
+}
+
+impl From<rayon::ThreadPoolBuildError> for Error {
+    fn from(e: rayon::ThreadPoolBuildError) -> Self { Error::ThreadPool(e) }
+}
+
 impl From<NoneError> for Error {
     fn from(_: NoneError) -> Self { Error::ParsingError("NoneError".to_owned()) }
 }
 
 impl From<ParseFloatError> for Error {
-    fn from(_e: ParseFloatError) -> Self { Error::ParsingError("ParseFloatError".to_owned()) }
+    fn from(e: ParseFloatError) -> Self { Error::ParsingError(format!("{}", e)) }
 }
 
 impl From<ParseIntError> for Error {
-    fn from(_: ParseIntError) -> Self { Error::ParsingError("ParseIntError".to_owned()) }
+    fn from(e: ParseIntError) -> Self { Error::ParsingError(format!("{}", e)) }
 }
@@ -28,7 +28,7 @@
 //! * **2.5x - 14x faster than libSVM for dense SVMs**
 //! * extremely low classification times for small models (e.g., 128 SV, 16 dense attributes, linear ~ 500ns)
 //! * successfully used in **Unity and VR** projects (Windows & Android)
-//! * free of `unsafe` code ;)
+//! * free of `unsafe` code ;) (unless you opt into the `prefetch`, `mlock` or `encryption` features)
 //!
 //! FFSVM is not, however, a full libSVM replacement. Instead, it assumes you use `svm-train`
 //! *at home* (see [Usage](#usage) below), and ship a working model with your library or application.
@@ -67,9 +67,28 @@
 //! ```
 
 // Opt in to unstable features expected for Rust 2018
-#![feature(try_from, stdsimd, try_trait, crate_visibility_modifier)]
+#![feature(try_from, stdsimd, try_trait, crate_visibility_modifier, non_exhaustive)]
 #![warn(rust_2018_idioms)]
 
+/// Enters a `tracing` span at `$level` until the end of the current block, if the `tracing`
+/// feature is enabled. Otherwise this expands to nothing, so spans cost nothing by default.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($level:ident, $($span:tt)*) => {
+        let _span = tracing::span!(tracing::Level::$level, $($span)*).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($level:ident, $($span:tt)*) => {};
+}
+
+mod check;
+#[cfg(feature = "encryption")]
+mod decrypt;
+mod dense;
+mod diff;
 mod errors;
 mod parser;
 mod sparse;
@@ -77,17 +96,59 @@ mod svm;
 mod util;
 mod vectors;
 
+pub mod samples;
+
+#[cfg(feature = "signatures")]
+mod signature;
+
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+
+/// Version of this crate, for example to log what an application is running against.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The SIMD types used by [KernelDense], re-exported so kernels use the same version as FFSVM.
+pub use simd_aligned;
+
+#[cfg(feature = "encryption")]
+pub use crate::decrypt::Decrypt;
+
+#[cfg(feature = "signatures")]
+pub use crate::signature::TrustedKeys;
+
 #[doc(hidden)]
 pub static SAMPLE_MODEL: &str = include_str!("sample.model");
 
+/// Features of a problem `SAMPLE_MODEL` classifies as `42`.
+#[doc(hidden)]
+pub static SAMPLE_FEATURES: [f32; 4] = [0.55838, -0.157895, 0.581292, -0.221184];
+
 pub use crate::{
+    check::{check_bundled_models, check_model_file},
+    dense::Layout,
+    diff::{diff, ClassChange, Drift, HeaderChange, ModelDiff},
     errors::Error,
-    parser::ModelFile,
+    parser::{Allocation, Limits, ModelFile, FORMAT_VERSION},
+    sparse::{SparseMatrix, SparseVector},
     svm::{
+        batch::ProbabilityMatrix,
+        cascade::Cascade,
         core::SVMCore,
-        kernel::{KernelDense, KernelSparse, Linear, Poly, Rbf, Sigmoid},
-        predict::Predict,
-        problem::{DenseProblem, Problem, Solution, SparseProblem},
+        ensemble::{Ensemble, Voting},
+        explain::Contribution,
+        hierarchy::Hierarchy,
+        introspect::Introspect,
+        kernel::{Combination, Composite, KernelDense, KernelParameters, KernelSparse, Linear, Normalized, Poly, Rbf, Sigmoid},
+        mock::{MockSvm, Script},
+        observer::{Observation, PredictObserver},
+        pipeline::{Pipeline, Scaler},
+        predict::{DensePredict, Predict, SparsePredict},
+        problem::{Cache, DenseProblem, Outcome, ProbabilityKind, Problem, Solution, SparseProblem},
+        reload::ReloadableSvm,
+        sensitivity::Sensitivity,
+        settings::{Reject, TieBreak},
+        stats::{MemoryUsage, PredictStats, Stats},
+        stream::PredictIter,
         DenseSVM, SVMType, SparseSVM,
     },
 };
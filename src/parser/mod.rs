@@ -1,10 +1,11 @@
 mod raw;
+mod write;
 
 use std::{convert::TryFrom, str};
 
 pub use self::raw::*;
 
-use pest::Parser;
+use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 
 use crate::errors::Error;
@@ -32,39 +33,241 @@ macro_rules! next {
         $p.next()?.as_str()
     };
     ($p:expr, $t:ty) => {
-        JUST_FUCKING_DEBUG_IT($p.next()?.as_str()).parse::<$t>()?
+        convert!($p.next()?, $t)
     };
 }
 
 macro_rules! convert {
-    ($p:expr, $t:ty) => {
-        JUST_FUCKING_DEBUG_IT($p.as_str()).parse::<$t>()?
-    };
+    ($p:expr, $t:ty) => {{
+        let pair = $p;
+        let offset = pair.as_span().start();
+
+        JUST_FUCKING_DEBUG_IT(pair.as_str()).parse::<$t>().map_err(|e| Error::from(e).at_offset(offset))?
+    }};
+}
+
+/// Parses an integer label, which may also be written as float like `1.0`.
+fn parse_label(text: &str) -> Option<i64> {
+    if let Ok(label) = text.parse::<i64>() {
+        return Some(label);
+    }
+
+    // Floats beyond 2^53 can't tell integers apart anymore.
+    match text.parse::<f64>() {
+        Ok(label) if label.fract() == 0.0 && label.abs() < 9_007_199_254_740_992.0 => Some(label as i64),
+        _ => None,
+    }
+}
+
+/// Limits enforced while parsing a model, see [ModelFile::parse_with_limits].
+///
+/// Loading a model allocates memory according to the numbers in its header, so a corrupt or
+/// malicious model could request gigabytes. Services that load models from untrusted sources
+/// should parse them with limits fitting their largest legitimate model. All limits default to
+/// `usize::max_value()`, i.e., no limit:
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let limits = Limits {
+///         max_bytes: 16 << 20,
+///         max_support_vectors: 10_000,
+///         max_attributes: 1_000,
+///         ..Limits::default()
+///     };
+///
+///     let model = ModelFile::parse_with_limits(SAMPLE_MODEL, &limits)?;
+///     let svm = DenseSVM::try_from(&model)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Limits only bound sizes. Sections whose number of entries doesn't fit `nr_class`, like a
+/// truncated `probA`, are refused with [Error::CountMismatch] when the SVM is created, so they
+/// can't make predictions fail later on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Limits {
+    /// Most bytes of the whole model.
+    pub max_bytes: usize,
+
+    /// Most bytes of the header, i.e., everything up to and including the `SV` line.
+    pub max_header_bytes: usize,
+
+    /// Most support vectors, as given by `total_sv` and as found.
+    pub max_support_vectors: usize,
+
+    /// Most attributes per support vector, i.e., the highest attribute index plus one.
+    pub max_attributes: usize,
+
+    /// Most classes, as given by `nr_class`.
+    pub max_classes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_bytes: usize::max_value(),
+            max_header_bytes: usize::max_value(),
+            max_support_vectors: usize::max_value(),
+            max_attributes: usize::max_value(),
+            max_classes: usize::max_value(),
+        }
+    }
+}
+
+/// How memory is allocated while loading a model, see [SVMCore::from_str_with].
+///
+/// Loading a model eagerly parses all of it before converting the support vectors, so memory
+/// peaks at several times the size of the loaded SVM for a moment. Integrators with tight
+/// memory budgets can trade some load time for a lower peak by loading in chunks:
+///
+/// ```rust
+/// use ffsvm::*;
+///
+/// fn main() -> Result<(), Error> {
+///     let svm = DenseSVM::from_str_with(SAMPLE_MODEL, &Limits::default(), Allocation::Chunked(1024))?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Allocation {
+    /// Parses the whole model, then converts all support vectors at once. This is the fastest
+    /// and what `try_from` does.
+    Eager,
+
+    /// Parses and converts this many lines of the model at a time, reusing the buffers of one
+    /// chunk for the next. Besides the model text and the SVM, only one chunk is held in memory.
+    Chunked(usize),
+}
+
+impl Default for Allocation {
+    fn default() -> Self { Allocation::Eager }
+}
+
+/// Returns an [Error::LimitExceeded] if `value` is above `max`.
+fn check_limit(limit: &'static str, value: usize, max: usize) -> Result<(), Error> {
+    if value > max {
+        return Err(Error::LimitExceeded { limit, value, max });
+    }
+
+    Ok(())
 }
 
 impl<'a> TryFrom<&'a str> for ModelFile<'a> {
     type Error = Error;
 
     /// Parses a string into a SVM model
-    fn try_from(input: &str) -> Result<ModelFile<'_>, Error> {
-        let parsed = LibSVMModel::parse(Rule::file, input)?.next()?;
-
-        let mut svm_type = Option::None;
-        let mut kernel_type = Option::None;
-        let mut gamma = Option::None;
-        let mut coef0 = Option::None;
-        let mut degree = Option::None;
-        let mut nr_class = Option::None;
-        let mut total_sv = Option::None;
-        let mut rho = Vec::new();
-        let mut label = Vec::new();
-        let mut prob_a = Option::None;
-        let mut prob_b = Option::None;
-        let mut nr_sv = Vec::new();
-
-        let mut vectors = Vec::new();
-
-        for line in parsed.into_inner() {
+    fn try_from(input: &str) -> Result<ModelFile<'_>, Error> { ModelFile::parse_with_limits(input, &Limits::default()) }
+}
+
+impl<'a> ModelFile<'a> {
+    /// Parses a string into a SVM model like `ModelFile::try_from`, but returns an
+    /// [Error::LimitExceeded] as soon as the model exceeds one of the `limits`.
+    pub fn parse_with_limits(input: &'a str, limits: &Limits) -> Result<ModelFile<'a>, Error> {
+        trace_span!(DEBUG, "ffsvm::parse", bytes = input.len());
+
+        check_limit("max_bytes", input.len(), limits.max_bytes)?;
+
+        let mut sections = Sections::default();
+        sections.read(input, 0, limits)?;
+
+        Ok(ModelFile {
+            header: sections.header()?,
+            vectors: sections.vectors,
+        })
+    }
+
+    /// Parses `input` in chunks of `lines` lines, calling `f` with the support vectors of each chunk.
+    ///
+    /// Only the support vectors of one chunk are held at a time, in buffers reused for the next
+    /// chunk. The header passed to `f` lacks the sections following the support vectors, like
+    /// metadata, the complete header is returned once all chunks were parsed.
+    crate fn parse_chunked<F>(input: &'a str, limits: &Limits, lines: usize, mut f: F) -> Result<Header<'a>, Error>
+    where
+        F: FnMut(&ModelFile<'a>) -> Result<(), Error>,
+    {
+        trace_span!(DEBUG, "ffsvm::parse", bytes = input.len(), lines);
+
+        check_limit("max_bytes", input.len(), limits.max_bytes)?;
+
+        let mut sections = Sections::default();
+        let mut chunk = ModelFile::default();
+        let mut start = 0;
+
+        while start < input.len() {
+            let end = input[start ..].match_indices('\n').nth(lines.max(1) - 1).map_or(input.len(), |(i, _)| start + i + 1);
+
+            // Trailing empty lines would not parse on their own.
+            if !input[start .. end].trim().is_empty() {
+                sections.read(&input[start .. end], start, limits)?;
+            }
+
+            if !sections.vectors.is_empty() {
+                if sections.num_vectors == sections.vectors.len() {
+                    chunk.header = sections.header()?;
+                }
+
+                chunk.vectors.clear();
+                chunk.vectors.append(&mut sections.vectors);
+
+                f(&chunk)?;
+            }
+
+            start = end;
+        }
+
+        sections.header()
+    }
+}
+
+/// Sections of a model read so far.
+#[derive(Default)]
+struct Sections<'a> {
+    svm_type: Option<&'a str>,
+    kernel_type: Option<&'a str>,
+    gamma: Option<f32>,
+    coef0: Option<f32>,
+    degree: Option<u32>,
+    nr_class: Option<u32>,
+    total_sv: Option<u32>,
+    rho: Vec<f64>,
+    label_texts: Vec<&'a str>,
+    prob_a: Option<Vec<f64>>,
+    prob_b: Option<Vec<f64>>,
+    nr_sv: Vec<u32>,
+    metadata: Vec<(&'a str, &'a str)>,
+    format_version: Option<u32>,
+
+    /// Support vectors read, unless taken by [ModelFile::parse_chunked].
+    vectors: Vec<SupportVector>,
+
+    /// Number of support vectors read in total.
+    num_vectors: usize,
+}
+
+impl<'a> Sections<'a> {
+    /// Reads the lines of `input`, which starts at byte `start` of the model.
+    fn read(&mut self, input: &'a str, start: usize, limits: &Limits) -> Result<(), Error> {
+        let parsed = match LibSVMModel::parse(Rule::file, input) {
+            Ok(mut pairs) => pairs.next()?,
+            // Positions in the message are relative to `input`.
+            Err(e) if start > 0 => return Err(Error::from(e).at_offset(start)),
+            Err(e) => return Err(Error::from(e)),
+        };
+
+        self.read_lines(parsed, start, limits).map_err(|e| e.offset_by(start))
+    }
+
+    /// Reads the parsed `lines` of an input starting at byte `start` of the model. Offsets of
+    /// errors are relative to that input.
+    fn read_lines(&mut self, lines: Pair<'a, Rule>, start: usize, limits: &Limits) -> Result<(), Error> {
+        for line in lines.into_inner() {
             match line.as_rule() {
                 // svm_type c_svc
                 // kernel_type rbf
@@ -78,47 +281,80 @@ impl<'a> TryFrom<&'a str> for ModelFile<'a> {
                 // nr_sv 50 56 17 11 7 12
                 // SV
                 Rule::line_multiple => {
+                    let span = line.as_span();
+
+                    // Lines after the support vectors, like `ffsvm_format`, are no part of the header.
+                    if self.num_vectors == 0 {
+                        check_limit("max_header_bytes", start + span.end(), limits.max_header_bytes).map_err(|e| e.at_offset(span.start()))?;
+                    }
+
                     let mut line_pairs = line.into_inner();
                     match next!(line_pairs, str) {
-                        "svm_type" => svm_type = Some(next!(line_pairs, str)),
-                        "kernel_type" => kernel_type = Some(next!(line_pairs, str)),
-                        "gamma" => gamma = Some(next!(line_pairs, f32)),
-                        "coef0" => coef0 = Some(next!(line_pairs, f32)),
-                        "degree" => degree = Some(next!(line_pairs, u32)),
-                        "nr_class" => nr_class = Some(next!(line_pairs, u32)),
-                        "total_sv" => total_sv = Some(next!(line_pairs, u32)),
+                        "svm_type" => self.svm_type = Some(next!(line_pairs, str)),
+                        "kernel_type" => self.kernel_type = Some(next!(line_pairs, str)),
+                        "gamma" => self.gamma = Some(next!(line_pairs, f32)),
+                        "coef0" => self.coef0 = Some(next!(line_pairs, f32)),
+                        "degree" => self.degree = Some(next!(line_pairs, u32)),
+                        "nr_class" => {
+                            let value = next!(line_pairs, u32);
+                            check_limit("max_classes", value as usize, limits.max_classes).map_err(|e| e.at_offset(span.start()))?;
+                            self.nr_class = Some(value);
+                        }
+                        "total_sv" => {
+                            let value = next!(line_pairs, u32);
+                            check_limit("max_support_vectors", value as usize, limits.max_support_vectors).map_err(|e| e.at_offset(span.start()))?;
+                            self.total_sv = Some(value);
+                        }
                         "rho" => while let Some(x) = line_pairs.next() {
-                            rho.push(convert!(x, f64))
+                            self.rho.push(convert!(x, f64))
                         },
                         "label" => while let Some(x) = line_pairs.next() {
-                            label.push(convert!(x, u32))
+                            self.label_texts.push(x.as_str())
                         },
                         "nr_sv" => while let Some(x) = line_pairs.next() {
-                            nr_sv.push(convert!(x, u32))
+                            self.nr_sv.push(convert!(x, u32))
                         },
                         "probA" => {
                             let mut v = Vec::<f64>::new();
                             while let Some(x) = line_pairs.next() {
                                 v.push(convert!(x, f64))
                             }
-                            prob_a = Option::Some(v);
+                            self.prob_a = Option::Some(v);
                         }
                         "probB" => {
                             let mut v = Vec::<f64>::new();
                             while let Some(x) = line_pairs.next() {
                                 v.push(convert!(x, f64))
                             }
-                            prob_b = Option::Some(v);
+                            self.prob_b = Option::Some(v);
                         }
+                        "ffsvm_format" => self.format_version = Some(next!(line_pairs, u32)),
                         "SV" => (),
-                        unknown => panic!("Unknown header `{}`!", unknown),
+                        unknown => {
+                            return Err(Error::UnsupportedSection {
+                                section: "header",
+                                value: unknown.to_owned(),
+                            })
+                        }
                     };
                 }
 
+                // meta git_hash 4f2a9c1
+                Rule::line_meta => {
+                    let mut line_pairs = line.into_inner();
+                    let key = next!(line_pairs, str);
+                    let value = next!(line_pairs, str).trim();
+
+                    self.metadata.push((key, value));
+                }
+
                 // 0.0625 0:0.6619648 1:0.8464851 2:0.4801146 3:0 4:0 5:0.02131653 6:0 7:0 8:0 9:0 10:0 11:0 12:0 13:0 14:0 15:0.5579834 16:0.1106567 17:0 18:0 19:0 20:0
                 // 0.0625 0:0.5861949 1:0.5556895 2:0.619291 3:0 4:0 5:0 6:0 7:0 8:0 9:0 10:0 11:0.5977631 12:0 13:0 14:0 15:0.6203156 16:0 17:0 18:0 19:0.1964417 20:0
                 // 0.0625 0:0.44675 1:0.4914977 2:0.4227562 3:0.2904663 4:0.2904663 5:0.268158 6:0 7:0 8:0 9:0 10:0 11:0.6202393 12:0.0224762 13:0 14:0 15:0.6427917 16:0.0224762 17:0 18:0 19:0.1739655 20:0
                 Rule::line_sv => {
+                    let offset = line.as_span().start();
+                    check_limit("max_support_vectors", self.num_vectors + 1, limits.max_support_vectors).map_err(|e| e.at_offset(offset))?;
+
                     let line_pairs = line.into_inner();
 
                     let mut sv = SupportVector {
@@ -133,6 +369,8 @@ impl<'a> TryFrom<&'a str> for ModelFile<'a> {
                                 let index = next!(sv_pairs, u32);
                                 let value = next!(sv_pairs, f32);
 
+                                check_limit("max_attributes", index as usize + 1, limits.max_attributes).map_err(|e| e.at_offset(offset))?;
+
                                 sv.features.push(Attribute { index, value })
                             }
                             Rule::number => sv.coefs.push(convert!(element, f32)),
@@ -140,28 +378,49 @@ impl<'a> TryFrom<&'a str> for ModelFile<'a> {
                         }
                     }
 
-                    vectors.push(sv);
+                    self.vectors.push(sv);
+                    self.num_vectors += 1;
                 }
                 _ => unreachable!(),
             };
         }
 
-        Ok(ModelFile {
-            header: Header {
-                svm_type: svm_type?,
-                kernel_type: kernel_type?,
-                gamma,
-                coef0,
-                degree,
-                nr_class: nr_class?,
-                total_sv: total_sv?,
-                rho,
-                label,
-                prob_a,
-                prob_b,
-                nr_sv,
-            },
-            vectors,
+        Ok(())
+    }
+
+    /// Returns the header read so far.
+    fn header(&self) -> Result<Header<'a>, Error> {
+        // Labels that are no integers are numbered by class, and keep their text as name.
+        let (label, label_names) = match self.label_texts.iter().map(|text| parse_label(text)).collect::<Option<Vec<_>>>() {
+            Some(label) => (label, Vec::new()),
+            None => ((0 .. self.label_texts.len() as i64).collect(), self.label_texts.clone()),
+        };
+
+        if let Some(version) = self.format_version {
+            if version > FORMAT_VERSION {
+                return Err(Error::UnsupportedFormatVersion {
+                    version,
+                    supported: FORMAT_VERSION,
+                });
+            }
+        }
+
+        Ok(Header {
+            svm_type: self.svm_type.ok_or(Error::MissingSection("svm_type"))?,
+            kernel_type: self.kernel_type.ok_or(Error::MissingSection("kernel_type"))?,
+            gamma: self.gamma,
+            coef0: self.coef0,
+            degree: self.degree,
+            nr_class: self.nr_class.ok_or(Error::MissingSection("nr_class"))?,
+            total_sv: self.total_sv.ok_or(Error::MissingSection("total_sv"))?,
+            rho: self.rho.clone(),
+            label,
+            label_names,
+            prob_a: self.prob_a.clone(),
+            prob_b: self.prob_b.clone(),
+            nr_sv: self.nr_sv.clone(),
+            metadata: self.metadata.clone(),
+            format_version: self.format_version,
         })
     }
 }
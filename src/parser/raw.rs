@@ -1,5 +1,10 @@
 use rand::Rng;
 
+/// Version of FFSVM's extensions to the libSVM model format this crate writes and reads.
+///
+/// * `1` - Adds `meta` lines with custom metadata.
+pub const FORMAT_VERSION: u32 = 1;
+
 /// Parsing result of a model file used to instantiate a [SVM].
 ///
 /// # Obtaining a model
@@ -48,6 +53,27 @@ use rand::Rng;
 /// * All support vectors (past the `SV` line) must have **strictly** increasing attribute
 /// identifiers, without skipping an attribute.
 ///
+/// # Labels
+///
+/// Labels are integers, which may also be written as floats like `1.0`. If any label is not an
+/// integer, for example `0.5` or a string like `cat`, the classes are labeled `0, 1, ...` in
+/// the order of the `label` line instead, and the original texts become their names, see
+/// [SVMCore::label_name]. Models with integer labels take the same fast path as before.
+///
+/// # Metadata
+///
+/// Models can carry arbitrary metadata, for example the training date or a git hash, as lines
+/// `meta <key> <value>`. Keys must be identifiers, values extend to the end of the line. FFSVM
+/// writes them after the support vectors, where libSVM ignores them. The metadata can be queried
+/// at runtime via [SVMCore::metadata].
+///
+/// # Format version
+///
+/// Models written by FFSVM end with a line `ffsvm_format <version>` naming the version of the
+/// extensions above they use, see [FORMAT_VERSION]. Files without this line are plain libSVM
+/// models. Files of older versions can always be read, files of newer versions are refused
+/// with [Error::UnsupportedFormatVersion] instead of being misread.
+///
 #[doc(hidden)]
 #[derive(Clone, Debug, Default)]
 pub struct ModelFile<'a> {
@@ -65,10 +91,15 @@ pub struct Header<'a> {
     crate nr_class: u32,
     crate total_sv: u32,
     crate rho: Vec<f64>,
-    crate label: Vec<u32>,
+    crate label: Vec<i64>,
+
+    /// Original text of each label, if any label is not an integer.
+    crate label_names: Vec<&'a str>,
     crate prob_a: Option<Vec<f64>>,
     crate prob_b: Option<Vec<f64>>,
     crate nr_sv: Vec<u32>,
+    crate metadata: Vec<(&'a str, &'a str)>,
+    crate format_version: Option<u32>,
 }
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -84,6 +115,107 @@ crate struct SupportVector {
 }
 
 impl<'a> ModelFile<'a> {
+    /// Returns the value of the metadata `key`, if present.
+    pub fn metadata(&self, key: &str) -> Option<&'a str> { self.header.metadata.iter().find(|(k, _)| *k == key).map(|(_, v)| *v) }
+
+    /// Returns the `rho` of each decision function, in the order of the model.
+    pub fn rho(&self) -> &[f64] { &self.header.rho }
+
+    /// Sets the metadata `key` to `value`, replacing any previous value.
+    pub fn set_metadata(&mut self, key: &'a str, value: &'a str) {
+        match self.header.metadata.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.header.metadata.push((key, value)),
+        }
+    }
+
+    /// Checks the model for internal inconsistencies, and returns a description of each one found.
+    ///
+    /// Loading an inconsistent model into a [SVM] may fail or even panic, so this is meant to
+    /// diagnose broken or hand-edited model files before loading them.
+    pub fn validate(&self) -> Vec<String> {
+        let header = &self.header;
+        let mut problems = Vec::new();
+
+        let classification = match header.svm_type {
+            "c_svc" | "nu_svc" => true,
+            "epsilon_svr" | "nu_svr" => false,
+            other => {
+                problems.push(format!("Unknown svm_type `{}`.", other));
+                true
+            }
+        };
+
+        let needs_gamma = match header.kernel_type {
+            "linear" => false,
+            "rbf" | "polynomial" | "sigmoid" => true,
+            other => {
+                problems.push(format!("Unknown kernel_type `{}`.", other));
+                false
+            }
+        };
+
+        if needs_gamma && header.gamma.is_none() {
+            problems.push(format!("Kernel `{}` needs a gamma.", header.kernel_type));
+        }
+
+        if header.total_sv as usize != self.vectors.len() {
+            problems.push(format!("total_sv is {}, but there are {} support vectors.", header.total_sv, self.vectors.len()));
+        }
+
+        let num_classes = header.nr_class as usize;
+        let num_pairs = if classification { num_classes * num_classes.saturating_sub(1) / 2 } else { 1 };
+
+        if header.rho.len() != num_pairs {
+            problems.push(format!("Expected {} rho values, got {}.", num_pairs, header.rho.len()));
+        }
+
+        if classification {
+            if header.label.len() != num_classes {
+                problems.push(format!("nr_class is {}, but there are {} labels.", num_classes, header.label.len()));
+            }
+
+            if !header.label_names.is_empty() && header.label_names.len() != header.label.len() {
+                problems.push(format!("There are {} labels, but {} label names.", header.label.len(), header.label_names.len()));
+            }
+
+            if header.nr_sv.len() != num_classes {
+                problems.push(format!("nr_class is {}, but nr_sv has {} entries.", num_classes, header.nr_sv.len()));
+            }
+
+            let sum_sv = header.nr_sv.iter().sum::<u32>();
+
+            if sum_sv != header.total_sv {
+                problems.push(format!("nr_sv adds up to {}, but total_sv is {}.", sum_sv, header.total_sv));
+            }
+
+            for (name, values) in &[("probA", &header.prob_a), ("probB", &header.prob_b)] {
+                if let Some(values) = values {
+                    if values.len() != num_pairs {
+                        problems.push(format!("Expected {} {} values, got {}.", num_pairs, name, values.len()));
+                    }
+                }
+            }
+        }
+
+        let num_coefficients = if classification { num_classes.saturating_sub(1) } else { 1 };
+
+        for (i, vector) in self.vectors.iter().enumerate() {
+            if vector.coefs.len() != num_coefficients {
+                problems.push(format!("Support vector {} has {} coefficients, expected {}.", i, vector.coefs.len(), num_coefficients));
+            }
+
+            for pair in vector.features.windows(2) {
+                if pair[1].index <= pair[0].index {
+                    problems.push(format!("Support vector {} has unordered attribute {} after {}.", i, pair[1].index, pair[0].index));
+                    break;
+                }
+            }
+        }
+
+        problems
+    }
+
     pub fn random_dense<'b>(svm_type: &'b str, kernel_type: &'b str, total_sv: u32, attr: u32) -> ModelFile<'b> {
         let mut rng = rand::thread_rng();
 
@@ -98,9 +230,12 @@ impl<'a> ModelFile<'a> {
                 nr_class: 2,
                 rho: vec![rng.gen::<f64>()],
                 label: vec![0, 1],
+                label_names: Vec::new(),
                 prob_a: Some(vec![rng.gen::<f64>(), rng.gen::<f64>()]),
                 prob_b: Some(vec![rng.gen::<f64>(), rng.gen::<f64>()]),
                 nr_sv: vec![total_sv / 2, total_sv / 2],
+                metadata: Vec::new(),
+                format_version: None,
             },
             vectors: (0 .. total_sv)
                 .map(|_| SupportVector {
@@ -0,0 +1,386 @@
+use rand::Rng;
+use std::fmt;
+
+use super::{Header, ModelFile, SupportVector, FORMAT_VERSION};
+
+impl<'a> ModelFile<'a> {
+    /// Returns an equivalent model without anything that is not needed for predictions.
+    ///
+    /// # Description
+    ///
+    /// This is meant to prepare models before shipping them to third parties. Kernel parameters
+    /// not used by the kernel and all metadata are removed. If `shuffle` is `true`, the support
+    /// vectors are shuffled within each class, so that their order no longer reflects the order
+    /// of the training data. Predictions of the stripped model are identical to the original one.
+    ///
+    /// Use `to_string()` to obtain the stripped model in libSVM's format.
+    pub fn stripped(&self, shuffle: bool) -> ModelFile<'a> {
+        let header = &self.header;
+        let (gamma, coef0, degree) = match header.kernel_type {
+            "linear" => (None, None, None),
+            "rbf" => (header.gamma, None, None),
+            "sigmoid" => (header.gamma, header.coef0, None),
+            _ => (header.gamma, header.coef0, header.degree),
+        };
+
+        let mut vectors = self.vectors.clone();
+
+        if shuffle {
+            let mut rng = rand::thread_rng();
+            let mut start = 0;
+
+            for num_sv in &header.nr_sv {
+                let stop = (start + *num_sv as usize).min(vectors.len());
+                rng.shuffle(&mut vectors[start .. stop]);
+                start = stop;
+            }
+        }
+
+        ModelFile {
+            header: Header {
+                gamma,
+                coef0,
+                degree,
+                metadata: Vec::new(),
+                ..header.clone()
+            },
+            vectors,
+        }
+    }
+
+    /// Returns a copy of the model without support vectors whose coefficients are all below `threshold`.
+    ///
+    /// # Description
+    ///
+    /// Support vectors with tiny coefficients barely contribute to any decision value, so pruning
+    /// them makes the model smaller and faster at the cost of slightly different decision values.
+    /// A `threshold` of `0.0` only removes support vectors that don't contribute at all, and
+    /// keeps predictions identical. Check the pruned model against your test data before shipping it.
+    pub fn pruned(&self, threshold: f32) -> ModelFile<'a> {
+        let header = &self.header;
+        let keep = |v: &SupportVector| v.coefs.iter().any(|c| c.abs() > threshold);
+
+        let mut vectors = Vec::with_capacity(self.vectors.len());
+        let mut nr_sv = Vec::with_capacity(header.nr_sv.len());
+        let mut start = 0;
+
+        for num_sv in &header.nr_sv {
+            let stop = (start + *num_sv as usize).min(self.vectors.len());
+            let before = vectors.len();
+
+            vectors.extend(self.vectors[start .. stop].iter().filter(|v| keep(v)).cloned());
+            nr_sv.push((vectors.len() - before) as u32);
+            start = stop;
+        }
+
+        // Regression models have no `nr_sv`.
+        vectors.extend(self.vectors[start ..].iter().filter(|v| keep(v)).cloned());
+
+        ModelFile {
+            header: Header {
+                total_sv: vectors.len() as u32,
+                nr_sv,
+                ..header.clone()
+            },
+            vectors,
+        }
+    }
+
+    /// Returns a copy of a classification model with only the classes in `labels`.
+    ///
+    /// # Description
+    ///
+    /// Deployments that only distinguish a few classes of a model with hundreds of them can load
+    /// the restricted model instead, which only holds the support vectors, decision functions and
+    /// probability parameters among the kept classes. Loading time and memory shrink accordingly.
+    /// Predictions of the restricted model are those of the original one, limited to the kept classes.
+    ///
+    /// Labels not in the model are ignored, the order of the kept classes is that of the model.
+    /// At least two classes must remain for the result to be a valid classifier. Regression
+    /// models have no classes and are returned unchanged.
+    ///
+    /// This is an eager filter, not lazy loading: the whole model must be parsed and held in memory
+    /// first, and all support vectors of the kept classes are copied. Run it offline, for example
+    /// with `ffsvm-convert -c`, to ship models that only contain what a deployment needs.
+    pub fn restrict_classes(&self, labels: &[i64]) -> ModelFile<'a> {
+        let header = &self.header;
+
+        if header.label.is_empty() {
+            return self.clone();
+        }
+
+        let num_classes = header.label.len();
+        let kept = (0 .. num_classes).filter(|i| labels.contains(&header.label[*i])).collect::<Vec<_>>();
+
+        // Decision functions, and their probability parameters, are stored for all pairs `(i, j)`
+        // with `i < j` in order.
+        let pairs = (0 .. num_classes).flat_map(|i| (i + 1 .. num_classes).map(move |j| (i, j))).collect::<Vec<_>>();
+        let keep_pairs = |values: &[f64]| -> Vec<f64> {
+            pairs
+                .iter()
+                .zip(values)
+                .filter(|((i, j), _)| kept.contains(i) && kept.contains(j))
+                .map(|(_, v)| *v)
+                .collect()
+        };
+
+        let mut vectors = Vec::new();
+        let mut nr_sv = Vec::with_capacity(kept.len());
+        let mut start = 0;
+
+        for (i, num_sv) in header.nr_sv.iter().enumerate() {
+            let stop = (start + *num_sv as usize).min(self.vectors.len());
+
+            if kept.contains(&i) {
+                // The coefficients of class `i` skip `i` itself.
+                let others = (0 .. num_classes).filter(|j| *j != i).collect::<Vec<_>>();
+
+                vectors.extend(self.vectors[start .. stop].iter().map(|v| SupportVector {
+                    coefs: v.coefs.iter().zip(&others).filter(|(_, j)| kept.contains(j)).map(|(c, _)| *c).collect(),
+                    features: v.features.clone(),
+                }));
+
+                nr_sv.push(*num_sv);
+            }
+
+            start = stop;
+        }
+
+        ModelFile {
+            header: Header {
+                nr_class: kept.len() as u32,
+                total_sv: vectors.len() as u32,
+                rho: keep_pairs(&header.rho),
+                label: kept.iter().map(|i| header.label[*i]).collect(),
+                label_names: kept.iter().filter_map(|i| header.label_names.get(*i).cloned()).collect(),
+                prob_a: header.prob_a.as_ref().map(|p| keep_pairs(p)),
+                prob_b: header.prob_b.as_ref().map(|p| keep_pairs(p)),
+                nr_sv,
+                ..header.clone()
+            },
+            vectors,
+        }
+    }
+}
+
+/// Writes all `values` separated by spaces.
+fn write_values<T>(f: &mut fmt::Formatter<'_>, name: &str, values: &[T]) -> fmt::Result
+where
+    T: fmt::Display,
+{
+    write!(f, "{}", name)?;
+
+    for value in values {
+        write!(f, " {}", value)?;
+    }
+
+    writeln!(f)
+}
+
+/// Writes the model in libSVM's format.
+impl<'a> fmt::Display for ModelFile<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+
+        writeln!(f, "svm_type {}", header.svm_type)?;
+        writeln!(f, "kernel_type {}", header.kernel_type)?;
+
+        if let Some(degree) = header.degree {
+            writeln!(f, "degree {}", degree)?;
+        }
+
+        if let Some(gamma) = header.gamma {
+            writeln!(f, "gamma {}", gamma)?;
+        }
+
+        if let Some(coef0) = header.coef0 {
+            writeln!(f, "coef0 {}", coef0)?;
+        }
+
+        writeln!(f, "nr_class {}", header.nr_class)?;
+        writeln!(f, "total_sv {}", header.total_sv)?;
+        write_values(f, "rho", &header.rho)?;
+
+        if !header.label_names.is_empty() {
+            write_values(f, "label", &header.label_names)?;
+        } else if !header.label.is_empty() {
+            write_values(f, "label", &header.label)?;
+        }
+
+        if let Some(prob_a) = &header.prob_a {
+            write_values(f, "probA", prob_a)?;
+        }
+
+        if let Some(prob_b) = &header.prob_b {
+            write_values(f, "probB", prob_b)?;
+        }
+
+        if !header.nr_sv.is_empty() {
+            write_values(f, "nr_sv", &header.nr_sv)?;
+        }
+
+        writeln!(f, "SV")?;
+
+        for vector in &self.vectors {
+            let coefficients = vector.coefs.iter().map(|c| c.to_string());
+            let features = vector.features.iter().map(|a| format!("{}:{}", a.index, a.value));
+
+            writeln!(f, "{}", coefficients.chain(features).collect::<Vec<_>>().join(" "))?;
+        }
+
+        // libSVM stops reading after `total_sv` support vectors, so it ignores our extensions.
+        for (key, value) in &header.metadata {
+            writeln!(f, "meta {} {}", key, value)?;
+        }
+
+        writeln!(f, "ffsvm_format {}", FORMAT_VERSION)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SupportVector;
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn stripped_is_equivalent() -> Result<(), Error> {
+        let model = ModelFile::try_from(SAMPLE_MODEL)?;
+        let text = model.stripped(true).to_string();
+        let stripped = ModelFile::try_from(text.as_str())?;
+
+        assert_eq!(diff(&model, &stripped).header, vec![]);
+        assert_eq!(diff(&model, &stripped).classes, vec![]);
+
+        let svm = DenseSVM::try_from(&stripped)?;
+        let original = DenseSVM::try_from(&model)?;
+        let mut problem = Problem::from(&svm);
+        let mut expected = Problem::from(&original);
+        set_sample_features(&mut problem);
+        set_sample_features(&mut expected);
+
+        svm.predict_value(&mut problem)?;
+        original.predict_value(&mut expected)?;
+
+        assert_eq!(problem.solution(), Solution::Label(42));
+        assert_eq!(problem.margin(), expected.margin());
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_survives() -> Result<(), Error> {
+        let mut model = ModelFile::try_from(SAMPLE_MODEL)?;
+        model.set_metadata("git_hash", "4f2a9c1");
+        model.set_metadata("trained", "2018-10-01 by ci/nightly");
+
+        let text = model.to_string();
+        let svm = DenseSVM::try_from(text.as_str())?;
+
+        assert_eq!(svm.metadata("git_hash"), Some("4f2a9c1"));
+        assert_eq!(svm.metadata("trained"), Some("2018-10-01 by ci/nightly"));
+        assert_eq!(svm.metadata("unknown"), None);
+        assert_eq!(model.stripped(false).metadata("git_hash"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pruned_is_equivalent() -> Result<(), Error> {
+        let model = ModelFile::try_from(SAMPLE_MODEL)?;
+        let pruned = model.pruned(0.0);
+
+        assert!(pruned.validate().is_empty());
+
+        // The remaining support vector has fewer attributes, so only a sparse model can hold it.
+        let svm = SparseSVM::try_from(&pruned)?;
+        assert_eq!(svm.stats().total_support_vectors, 1);
+
+        let original = SparseSVM::try_from(&model)?;
+        let mut problem = Problem::from(&svm);
+        let mut expected = Problem::from(&original);
+        set_sample_features(&mut problem);
+        set_sample_features(&mut expected);
+
+        svm.predict_value(&mut problem)?;
+        original.predict_value(&mut expected)?;
+
+        assert_eq!(problem.solution(), Solution::Label(42));
+        assert!((problem.margin().unwrap() - expected.margin().unwrap()).abs() < 1e-5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn restrict_classes() -> Result<(), Error> {
+        let model = ModelFile::random_dense("c_svc", "rbf", 6, 4);
+        let mut header = model.header.clone();
+
+        header.nr_class = 3;
+        header.rho = vec![0.1, 0.2, 0.3];
+        header.label = vec![1, 2, 3];
+        header.nr_sv = vec![2, 2, 2];
+        header.prob_a = None;
+        header.prob_b = None;
+
+        let vectors = model.vectors.iter().map(|v| SupportVector { coefs: vec![0.5, -0.5], ..v.clone() }).collect();
+        let model = ModelFile { header, vectors };
+        assert!(model.validate().is_empty());
+
+        let restricted = model.restrict_classes(&[1, 3, 5]);
+
+        assert!(restricted.validate().is_empty());
+        assert_eq!(restricted.header.label, vec![1, 3]);
+        assert_eq!(restricted.header.rho, vec![0.2]);
+        assert_eq!(restricted.header.total_sv, 4);
+        assert!(restricted.vectors.iter().all(|v| v.coefs.len() == 1));
+
+        let svm = DenseSVM::try_from(&restricted)?;
+        assert_eq!(svm.labels().collect::<Vec<_>>(), vec![1, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_integer_labels() -> Result<(), Error> {
+        let floats = SAMPLE_MODEL.replace("label 21 42", "label -1.0 1.0");
+        let svm = DenseSVM::try_from(floats.as_str())?;
+
+        assert_eq!(svm.labels().collect::<Vec<_>>(), vec![-1, 1]);
+        assert_eq!(svm.label_name(1), None);
+
+        let strings = SAMPLE_MODEL.replace("label 21 42", "label cat dog");
+        let model = ModelFile::try_from(strings.as_str())?;
+        let svm = DenseSVM::try_from(&model)?;
+
+        assert_eq!(svm.labels().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(svm.label_name(1), Some("dog"));
+        assert!(model.to_string().contains("label cat dog"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<(), Error> {
+        let model = ModelFile::try_from(SAMPLE_MODEL)?;
+        assert!(model.validate().is_empty());
+
+        let broken = SAMPLE_MODEL.replace("nr_sv 1 1", "nr_sv 1 2");
+        let model = ModelFile::try_from(broken.as_str())?;
+        assert_eq!(model.validate().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn newer_format_refused() {
+        let text = format!("{}ffsvm_format {}\n", SAMPLE_MODEL, FORMAT_VERSION + 1);
+
+        match ModelFile::try_from(text.as_str()) {
+            Err(Error::UnsupportedFormatVersion { version, .. }) => assert_eq!(version, FORMAT_VERSION + 1),
+            _ => panic!("Newer format must be refused."),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Small models covering every configuration FFSVM supports, for examples and tests.
+//!
+//! The models were trained by libSVM on a synthetic problem with 8 classes of 4 samples each,
+//! see `tests/gen-models.py`, binary models are the first two classes of the multi-class
+//! models. Each model comes with a [Sample] describing it, and [ALL] lists all of them, so
+//! tests can run every code path:
+//!
+//! ```rust
+//! #![feature(try_from)]
+//!
+//! use ffsvm::*;
+//! use std::convert::TryFrom;
+//!
+//! fn main() -> Result<(), Error> {
+//!     for sample in samples::ALL.iter().filter(|s| !s.sparse) {
+//!         let svm = DenseSVM::try_from(sample.model)?;
+//!         let mut problem = Problem::from(&svm);
+//!
+//!         svm.predict_value(&mut problem)?;
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::svm::SVMType;
+
+/// A bundled model and the configuration it covers.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Name of the model, the same as the name of its constant in lower case.
+    pub name: &'static str,
+
+    /// The model in libSVM's format.
+    pub model: &'static str,
+
+    /// Type of the SVM.
+    pub svm_type: SVMType,
+
+    /// Type of the kernel, as in the model file.
+    pub kernel_type: &'static str,
+
+    /// Number of classes, `1` for regression.
+    pub classes: usize,
+
+    /// Number of attributes a problem has.
+    pub attributes: usize,
+
+    /// If the model was trained with probability estimates.
+    pub probabilities: bool,
+
+    /// If the support vectors are sparse, so the model must be loaded as [SparseSVM].
+    pub sparse: bool,
+}
+
+macro_rules! samples {
+    ($($constant:ident: $name:expr, $svm_type:ident, $kernel_type:expr, $classes:expr, $attributes:expr, $probabilities:expr, $sparse:expr;)*) => {
+        $(
+            pub const $constant: Sample = Sample {
+                name: $name,
+                model: include_str!(concat!($name, ".model")),
+                svm_type: SVMType::$svm_type,
+                kernel_type: $kernel_type,
+                classes: $classes,
+                attributes: $attributes,
+                probabilities: $probabilities,
+                sparse: $sparse,
+            };
+        )*
+
+        /// All bundled models.
+        pub const ALL: &[Sample] = &[$($constant),*];
+    };
+}
+
+samples! {
+    DENSE_LINEAR_BINARY: "dense_linear_binary", CSvc, "linear", 2, 8, false, false;
+    DENSE_LINEAR_BINARY_PROB: "dense_linear_binary_prob", CSvc, "linear", 2, 8, true, false;
+    DENSE_LINEAR_MULTICLASS: "dense_linear_multiclass", CSvc, "linear", 8, 8, false, false;
+    DENSE_LINEAR_MULTICLASS_PROB: "dense_linear_multiclass_prob", CSvc, "linear", 8, 8, true, false;
+    DENSE_LINEAR_REGRESSION: "dense_linear_regression", ESvr, "linear", 1, 8, false, false;
+    DENSE_POLY_BINARY: "dense_poly_binary", CSvc, "polynomial", 2, 8, false, false;
+    DENSE_POLY_BINARY_PROB: "dense_poly_binary_prob", CSvc, "polynomial", 2, 8, true, false;
+    DENSE_POLY_MULTICLASS: "dense_poly_multiclass", CSvc, "polynomial", 8, 8, false, false;
+    DENSE_POLY_MULTICLASS_PROB: "dense_poly_multiclass_prob", CSvc, "polynomial", 8, 8, true, false;
+    DENSE_POLY_REGRESSION: "dense_poly_regression", ESvr, "polynomial", 1, 8, false, false;
+    DENSE_RBF_BINARY: "dense_rbf_binary", CSvc, "rbf", 2, 8, false, false;
+    DENSE_RBF_BINARY_PROB: "dense_rbf_binary_prob", CSvc, "rbf", 2, 8, true, false;
+    DENSE_RBF_MULTICLASS: "dense_rbf_multiclass", CSvc, "rbf", 8, 8, false, false;
+    DENSE_RBF_MULTICLASS_PROB: "dense_rbf_multiclass_prob", CSvc, "rbf", 8, 8, true, false;
+    DENSE_RBF_REGRESSION: "dense_rbf_regression", ESvr, "rbf", 1, 8, false, false;
+    DENSE_SIGMOID_BINARY: "dense_sigmoid_binary", CSvc, "sigmoid", 2, 8, false, false;
+    DENSE_SIGMOID_BINARY_PROB: "dense_sigmoid_binary_prob", CSvc, "sigmoid", 2, 8, true, false;
+    DENSE_SIGMOID_MULTICLASS: "dense_sigmoid_multiclass", CSvc, "sigmoid", 8, 8, false, false;
+    DENSE_SIGMOID_MULTICLASS_PROB: "dense_sigmoid_multiclass_prob", CSvc, "sigmoid", 8, 8, true, false;
+    DENSE_SIGMOID_REGRESSION: "dense_sigmoid_regression", ESvr, "sigmoid", 1, 8, false, false;
+    SPARSE_LINEAR_BINARY: "sparse_linear_binary", CSvc, "linear", 2, 128, false, true;
+    SPARSE_LINEAR_BINARY_PROB: "sparse_linear_binary_prob", CSvc, "linear", 2, 128, true, true;
+    SPARSE_LINEAR_MULTICLASS: "sparse_linear_multiclass", CSvc, "linear", 8, 128, false, true;
+    SPARSE_LINEAR_MULTICLASS_PROB: "sparse_linear_multiclass_prob", CSvc, "linear", 8, 128, true, true;
+    SPARSE_RBF_BINARY: "sparse_rbf_binary", CSvc, "rbf", 2, 128, false, true;
+    SPARSE_RBF_BINARY_PROB: "sparse_rbf_binary_prob", CSvc, "rbf", 2, 128, true, true;
+    SPARSE_RBF_MULTICLASS: "sparse_rbf_multiclass", CSvc, "rbf", 8, 128, false, true;
+    SPARSE_RBF_MULTICLASS_PROB: "sparse_rbf_multiclass_prob", CSvc, "rbf", 8, 128, true, true;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn all_samples_load() -> Result<(), Error> {
+        for sample in samples::ALL {
+            if sample.sparse {
+                let svm = SparseSVM::try_from(sample.model)?;
+                let mut problem = Problem::from(&svm);
+
+                assert_eq!(svm.classes(), sample.classes, "{}", sample.name);
+
+                svm.predict_value(&mut problem)?;
+
+                if sample.probabilities {
+                    svm.predict_probability(&mut problem)?;
+                }
+            } else {
+                let svm = DenseSVM::try_from(sample.model)?;
+                let mut problem = Problem::from(&svm);
+
+                assert_eq!(svm.classes(), sample.classes, "{}", sample.name);
+                assert_eq!(svm.attributes(), sample.attributes, "{}", sample.name);
+
+                svm.predict_value(&mut problem)?;
+
+                if sample.probabilities {
+                    svm.predict_probability(&mut problem)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use ed25519_dalek::{PublicKey, Signature};
+use std::convert::TryFrom;
+
+use crate::errors::Error;
+
+/// Public ed25519 keys trusted to sign models, see [SVMCore::from_signed_file].
+///
+/// # Description
+///
+/// Models signed with the secret key of any trusted key are accepted, so keys can be rotated
+/// by trusting the old and the new key for a while. The signature covers the model bytes exactly
+/// as stored, and is checked before the model is parsed, so tampered models never reach the
+/// parser:
+///
+/// ```rust,no_run
+/// use ffsvm::*;
+///
+/// fn main() -> Result<(), Error> {
+///     let keys = TrustedKeys::new(&[std::fs::read("keys/models.pub")?])?;
+///
+///     // Expects the signature in `models/gaze.model.sig`.
+///     let svm = DenseSVM::from_signed_file("models/gaze.model", &keys)?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrustedKeys {
+    keys: Vec<PublicKey>,
+}
+
+impl TrustedKeys {
+    /// Trusts the given 32 byte public `keys`, fails with [Error::InvalidPublicKey] if any is malformed.
+    pub fn new<K: AsRef<[u8]>>(keys: &[K]) -> Result<Self, Error> {
+        let keys = keys.iter().map(|key| PublicKey::from_bytes(key.as_ref()).map_err(|_| Error::InvalidPublicKey)).collect::<Result<_, _>>()?;
+
+        Ok(TrustedKeys { keys })
+    }
+
+    /// Checks that one of the keys signed `model` with `signature`, else returns [Error::SignatureMismatch].
+    pub fn verify(&self, model: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let signature = Signature::try_from(signature).map_err(|_| Error::SignatureMismatch)?;
+
+        if self.keys.iter().any(|key| key.verify_strict(model, &signature).is_ok()) {
+            Ok(())
+        } else {
+            Err(Error::SignatureMismatch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey};
+
+    #[test]
+    fn signed_models() -> Result<(), Error> {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let signature = ExpandedSecretKey::from(&secret).sign(SAMPLE_MODEL.as_bytes(), &public).to_bytes();
+
+        let keys = TrustedKeys::new(&[public.as_bytes()])?;
+        let path = std::env::temp_dir().join(format!("ffsvm_signed_{}.model", std::process::id()));
+        let signature_path = std::env::temp_dir().join(format!("ffsvm_signed_{}.model.sig", std::process::id()));
+
+        std::fs::write(&path, SAMPLE_MODEL)?;
+        std::fs::write(&signature_path, &signature[..])?;
+
+        DenseSVM::from_signed_file(&path, &keys)?;
+        SparseSVM::from_signed_reader(SAMPLE_MODEL.as_bytes(), "bundled/sample.model", &signature, &keys)?;
+
+        let tampered = SAMPLE_MODEL.replace("rho -0.25527", "rho 0.25527");
+
+        match DenseSVM::from_signed_reader(tampered.as_bytes(), "tampered", &signature, &keys) {
+            Err(e) => assert!(match e.root() {
+                Error::SignatureMismatch => true,
+                _ => false,
+            }),
+            Ok(_) => panic!("Tampered model was accepted."),
+        }
+
+        let other = PublicKey::from(&SecretKey::from_bytes(&[8; 32]).unwrap());
+        assert!(DenseSVM::from_signed_file(&path, &TrustedKeys::new(&[other.as_bytes()])?).is_err());
+
+        std::fs::remove_file(&path)?;
+        std::fs::remove_file(&signature_path)?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,12 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    mem::size_of,
+    ops::{Index, IndexMut},
+};
+
+use crate::util::{touch, Pages};
+
+#[cfg(feature = "mlock")]
+use crate::{errors::Error, util::lock};
 
 #[derive(Clone, Debug)]
 struct Entry<T>
@@ -9,6 +17,7 @@ where
     value: T,
 }
 
+/// A vector storing only its non-zero entries, in ascending order of their index.
 #[derive(Clone, Debug, Default)]
 pub struct SparseVector<T>
 where
@@ -21,10 +30,13 @@ impl<T> SparseVector<T>
 where
     T: Clone + Copy + Default,
 {
+    /// Creates an empty vector.
     pub fn new() -> Self { SparseVector { entries: Vec::new() } }
 
+    /// Removes all entries.
     pub fn clear(&mut self) { self.entries.clear(); }
 
+    /// Iterates over all `(index, value)` entries in ascending order of `index`.
     pub fn iter(&self) -> SparseVectorIter<'_, T> { SparseVectorIter { vector: self, index: 0 } }
 }
 
@@ -105,6 +117,7 @@ where
     }
 }
 
+/// A matrix of [SparseVector] rows.
 #[derive(Clone, Debug)]
 pub struct SparseMatrix<T>
 where
@@ -117,14 +130,24 @@ impl<T> SparseMatrix<T>
 where
     T: Clone + Copy + Default,
 {
+    /// Creates a matrix with `rows` empty rows.
     pub fn with(rows: usize) -> Self {
         SparseMatrix {
             vectors: vec![SparseVector::new(); rows],
         }
     }
 
+    /// Returns the row `row`.
     pub fn row(&self, row: usize) -> &SparseVector<T> { &self.vectors[row] }
 
+    /// Returns the heap bytes used by this matrix.
+    crate fn heap_bytes(&self) -> usize {
+        let entries = self.vectors.iter().map(|v| v.entries.capacity() * size_of::<Entry<T>>()).sum::<usize>();
+
+        self.vectors.capacity() * size_of::<SparseVector<T>>() + entries
+    }
+
+    /// Iterates over all rows.
     #[inline]
     pub fn row_iter(&self) -> SparseMatrixIter<'_, T> { SparseMatrixIter { matrix: &self, index: 0 } }
 }
@@ -174,3 +197,25 @@ where
         }
     }
 }
+
+impl Pages for SparseVector<f32> {
+    fn touch(&self) { touch(Some(self.entries.as_slice()), |e| e.value.to_bits() as usize) }
+
+    #[cfg(feature = "mlock")]
+    fn lock(&self) -> Result<(), Error> { lock(Some(self.entries.as_slice())) }
+}
+
+impl Pages for SparseMatrix<f32> {
+    fn touch(&self) {
+        for vector in &self.vectors {
+            vector.touch();
+        }
+    }
+
+    /// Each row has its own allocation, so rows are locked one by one.
+    #[cfg(feature = "mlock")]
+    fn lock(&self) -> Result<(), Error> {
+        lock(Some(self.vectors.as_slice()))?;
+        self.vectors.iter().map(Pages::lock).collect()
+    }
+}
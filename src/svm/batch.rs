@@ -0,0 +1,56 @@
+use std::ops::Index;
+
+use crate::svm::problem::Problem;
+
+/// Probabilities of many problems, see [DenseSVM::predict_probabilities_batch].
+///
+/// Each row holds the [Problem::probabilities] of one problem, in the order the problems were
+/// given. Each column belongs to one class, in the order of [ProbabilityMatrix::labels], which is
+/// the order of the model's `label` line. The matrix is stored row-major and can be indexed
+/// with `matrix[(row, column)]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbabilityMatrix {
+    labels: Vec<i64>,
+    data: Vec<f64>,
+}
+
+impl ProbabilityMatrix {
+    /// Collects the probabilities of all `problems` for the classes with the given `labels`.
+    crate fn from_problems<T>(labels: Vec<i64>, problems: &[Problem<T>]) -> Self {
+        let columns = labels.len();
+        let mut data = Vec::with_capacity(problems.len() * columns);
+
+        for problem in problems {
+            data.extend_from_slice(&problem.probabilities()[.. columns]);
+        }
+
+        ProbabilityMatrix { labels, data }
+    }
+
+    /// Returns the labels of all columns.
+    pub fn labels(&self) -> &[i64] { &self.labels }
+
+    /// Returns the number of rows, i.e., problems.
+    pub fn rows(&self) -> usize { self.data.len() / self.labels.len().max(1) }
+
+    /// Returns the number of columns, i.e., classes.
+    pub fn columns(&self) -> usize { self.labels.len() }
+
+    /// Returns the probabilities of the problem `row`.
+    pub fn row(&self, row: usize) -> &[f64] { &self.data[row * self.labels.len() .. (row + 1) * self.labels.len()] }
+
+    /// Returns all probabilities, row by row.
+    pub fn as_slice(&self) -> &[f64] { &self.data }
+
+    /// Returns the probability of the class `label` for the problem `row`, or `None` if the label is unknown.
+    pub fn get(&self, row: usize, label: i64) -> Option<f64> {
+        let column = self.labels.iter().position(|l| *l == label)?;
+        Some(self[(row, column)])
+    }
+}
+
+impl Index<(usize, usize)> for ProbabilityMatrix {
+    type Output = f64;
+
+    fn index(&self, (row, column): (usize, usize)) -> &f64 { &self.data[row * self.labels.len() + column] }
+}
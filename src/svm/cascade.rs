@@ -0,0 +1,235 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    errors::Error,
+    svm::{
+        ensemble::{load_features, map_classes},
+        introspect::Introspect,
+        predict::Predict,
+        problem::{DenseProblem, Problem, Solution},
+        settings::Reject,
+        DenseSVM, SVMType,
+    },
+};
+
+/// A single SVM of a [Cascade].
+struct Stage {
+    svm: DenseSVM,
+
+    /// Index of the cascade's class for each class of the stage.
+    classes: Vec<usize>,
+
+    /// Number of problems this stage decided since the last reset.
+    hits: AtomicUsize,
+}
+
+/// Chains several [DenseSVM]s so that cheap models decide easy problems early.
+///
+/// # Description
+///
+/// Stages are evaluated in the order they were added. Each stage uses its [Reject] policy as
+/// threshold: if its result is [Solution::Rejected] the problem is passed on to the next stage,
+/// otherwise the result is taken as is. The last stage always decides.
+///
+/// A typical cascade has a linear (or otherwise cheap) first stage that confidently handles the
+/// majority of problems, and a full RBF model as second stage for the ambiguous rest. How often each
+/// stage decided is recorded, see [Cascade::stage_hits].
+///
+/// All stages must be classifiers with the same set of labels, and use the first
+/// `stage.attributes()` features of the problem. Problems must be created via
+/// `Problem::from(&cascade)` **after** all stages were added.
+pub struct Cascade {
+    stages: Vec<Stage>,
+    labels: Vec<i64>,
+    num_attributes: usize,
+}
+
+impl Cascade {
+    /// Creates an empty cascade.
+    pub fn new() -> Self {
+        Cascade {
+            stages: Vec::new(),
+            labels: Vec::new(),
+            num_attributes: 0,
+        }
+    }
+
+    /// Adds a stage to the end of the cascade, passing on problems according to `threshold`.
+    ///
+    /// This sets the [Reject] policy of `svm` to `threshold`, which has no effect on the last stage
+    /// unless another stage is added later. Use [Reject::BelowMargin] if you only predict values,
+    /// [Reject::BelowProbability] if you predict probabilities.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::IncompatibleModel] if `svm` is no classifier or the number of classes differs,
+    /// an [Error::UnknownLabel] if `svm` has a label the other stages don't have.
+    pub fn add_stage(&mut self, mut svm: DenseSVM, threshold: Reject) -> Result<(), Error> {
+        match svm.svm_type {
+            SVMType::CSvc | SVMType::NuSvc => {}
+            SVMType::ESvr | SVMType::NuSvr => return Err(Error::IncompatibleModel),
+        }
+
+        if self.stages.is_empty() {
+            self.labels = svm.classes.iter().map(|c| c.label).collect();
+        } else if self.labels.len() != svm.classes.len() {
+            return Err(Error::IncompatibleModel);
+        }
+
+        let classes = map_classes(&self.labels, &svm)?;
+
+        svm.set_reject(threshold);
+
+        self.num_attributes = self.num_attributes.max(svm.num_attributes);
+        self.stages.push(Stage {
+            svm,
+            classes,
+            hits: AtomicUsize::new(0),
+        });
+
+        Ok(())
+    }
+
+    /// Returns the number of stages.
+    pub fn stages(&self) -> usize { self.stages.len() }
+
+    /// Returns the number of features a [Problem] of this cascade has.
+    pub fn attributes(&self) -> usize { self.num_attributes }
+
+    /// Returns the number of classes.
+    pub fn classes(&self) -> usize { self.labels.len() }
+
+    /// Returns the name of `label` given by the first stage naming it, see [SVMCore::label_name].
+    pub fn label_name(&self, label: i64) -> Option<&str> { self.stages.iter().filter_map(|s| s.svm.label_name(label)).next() }
+
+    /// Returns how many problems each stage decided since the cascade was created or
+    /// [Cascade::reset_statistics] was called.
+    pub fn stage_hits(&self) -> Vec<usize> { self.stages.iter().map(|s| s.hits.load(Ordering::Relaxed)).collect() }
+
+    /// Returns the fraction of problems each stage decided, see [Cascade::stage_hits].
+    pub fn stage_hit_rates(&self) -> Vec<f64> {
+        let hits = self.stage_hits();
+        let total = hits.iter().sum::<usize>().max(1) as f64;
+
+        hits.iter().map(|h| *h as f64 / total).collect()
+    }
+
+    /// Resets the hit statistics of all stages.
+    pub fn reset_statistics(&self) {
+        for stage in &self.stages {
+            stage.hits.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Predicts `problem` with as many stages as needed.
+    fn predict(&self, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        // Problems created before stages were added, or for another cascade, would keep a stale result.
+        if problem.members.len() != self.stages.len() {
+            return Err(Error::IncompatibleModel);
+        }
+
+        let last = self.stages.len().saturating_sub(1);
+
+        for (i, (stage, stage_problem)) in self.stages.iter().zip(problem.members.iter_mut()).enumerate() {
+            load_features(problem.features.as_raw().flat(), stage_problem, None);
+
+            if with_probabilities {
+                stage.svm.predict_probability(stage_problem)?;
+            } else {
+                stage.svm.predict_value(stage_problem)?;
+            }
+
+            let solution = stage_problem.solution();
+
+            if solution == Solution::Rejected && i < last {
+                continue;
+            }
+
+            stage.hits.fetch_add(1, Ordering::Relaxed);
+
+            for (index, (vote, p)) in stage.classes.iter().zip(stage_problem.vote.iter().zip(stage_problem.probabilities())) {
+                problem.vote[*index] = *vote;
+                problem.probabilities.flat_mut()[*index] = *p;
+            }
+
+            problem.probability_kind = stage_problem.probability_kind;
+            problem.result = solution;
+
+            return Ok(());
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Cascade {
+    fn default() -> Self { Cascade::new() }
+}
+
+impl Predict<SimdVector<f32s>, SimdVector<f64s>> for Cascade {
+    fn predict_value(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, false) }
+
+    fn predict_probability(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, true) }
+
+    fn problem(&self) -> DenseProblem { DenseProblem::from(self) }
+}
+
+impl Introspect for Cascade {
+    fn attributes(&self) -> usize { self.num_attributes }
+
+    fn classes(&self) -> usize { self.labels.len() }
+
+    fn labels(&self) -> Vec<i64> { self.labels.clone() }
+}
+
+impl<'a> From<&'a Cascade> for DenseProblem {
+    fn from(cascade: &Cascade) -> Self {
+        let mut problem = Problem::<SimdVector<f32s>>::with_dimension(0, cascade.labels.len(), cascade.num_attributes, cascade.labels.clone());
+        problem.members = cascade.stages.iter().map(|s| Problem::from(&s.svm)).collect();
+        problem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn stage_hits() -> Result<(), Error> {
+        let mut cascade = Cascade::new();
+
+        cascade.add_stage(DenseSVM::try_from(SAMPLE_MODEL)?, Reject::BelowMargin(std::f64::INFINITY))?;
+        cascade.add_stage(DenseSVM::try_from(SAMPLE_MODEL)?, Reject::Never)?;
+
+        let mut problem = Problem::from(&cascade);
+        set_sample_features(&mut problem);
+
+        cascade.predict_value(&mut problem)?;
+
+        assert_eq!(problem.solution(), Solution::Label(42));
+        assert_eq!(cascade.stage_hits(), vec![0, 1]);
+
+        cascade.reset_statistics();
+        assert_eq!(cascade.stage_hits(), vec![0, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stale_problem() -> Result<(), Error> {
+        let mut cascade = Cascade::new();
+
+        cascade.add_stage(DenseSVM::try_from(SAMPLE_MODEL)?, Reject::Never)?;
+
+        let mut problem = Problem::from(&cascade);
+
+        cascade.add_stage(DenseSVM::try_from(SAMPLE_MODEL)?, Reject::Never)?;
+
+        match cascade.predict_value(&mut problem) {
+            Err(Error::IncompatibleModel) => Ok(()),
+            other => panic!("Expected an incompatible model, got {:?}.", other),
+        }
+    }
+}
@@ -1,12 +1,15 @@
-use crate::sparse::SparseMatrix;
-use simd_aligned::{f32s, f64s, RowOptimized, SimdMatrix};
+use crate::{
+    dense::{DenseMatrix, Layout},
+    sparse::SparseMatrix,
+};
+use simd_aligned::{f64s, RowOptimized, SimdMatrix};
 
 /// Represents one class of the SVM model.
 #[derive(Clone, Debug)]
 #[doc(hidden)]
 crate struct Class<M32> {
     /// The label of this class
-    crate label: u32,
+    crate label: i64,
 
     /// The number of support vectors in this class
     crate num_support_vectors: usize,
@@ -16,28 +19,33 @@ crate struct Class<M32> {
 
     /// All support vectors in this class.
     crate support_vectors: M32,
+
+    /// Values the kernel computed for each support vector, see [KernelDense::prepare].
+    crate prepared: Vec<f64>,
 }
 
-impl Class<SimdMatrix<f32s, RowOptimized>> {
+impl Class<DenseMatrix> {
     /// Creates a new class with the given parameters.
-    pub fn with_parameters(classes: usize, support_vectors: usize, attributes: usize, label: u32) -> Class<SimdMatrix<f32s, RowOptimized>> {
+    pub fn with_parameters(classes: usize, support_vectors: usize, attributes: usize, label: i64) -> Class<DenseMatrix> {
         Class {
             label,
             num_support_vectors: support_vectors,
             coefficients: SimdMatrix::with_dimension(classes - 1, support_vectors),
-            support_vectors: SimdMatrix::with_dimension(support_vectors, attributes),
+            support_vectors: DenseMatrix::with_dimension(support_vectors, attributes, Layout::Rows),
+            prepared: Vec::new(),
         }
     }
 }
 
 impl Class<SparseMatrix<f32>> {
     /// Creates a new class with the given parameters.
-    pub fn with_parameters(classes: usize, support_vectors: usize, _attributes: usize, label: u32) -> Class<SparseMatrix<f32>> {
+    pub fn with_parameters(classes: usize, support_vectors: usize, _attributes: usize, label: i64) -> Class<SparseMatrix<f32>> {
         Class {
             label,
             num_support_vectors: support_vectors,
             coefficients: SimdMatrix::with_dimension(classes - 1, support_vectors),
             support_vectors: SparseMatrix::with(support_vectors),
+            prepared: Vec::new(),
         }
     }
 }
@@ -0,0 +1,234 @@
+use std::{convert::TryFrom, marker::PhantomData};
+
+use crate::{
+    dense::{DenseMatrix, Layout},
+    errors::Error,
+    sparse::SparseMatrix,
+    svm::{class::Class, core::SVMCore, stats::density, DenseSVM, SparseSVM},
+};
+
+impl DenseSVM {
+    /// Creates a [DenseSVM] from a [SparseSVM].
+    ///
+    /// # Description
+    ///
+    /// All support vectors are expanded so that attribute `i` of the sparse model is stored
+    /// at position `i` of the dense model. This is useful if a model was trained on sparse data,
+    /// but the data to classify is actually (mostly) dense.
+    ///
+    /// The ratio of non-zero attributes over all support vectors is the model's density. If
+    /// it is below `min_density` the conversion is refused with [Error::DensityOutOfBounds],
+    /// as densifying a very sparse model mostly wastes memory.
+    pub fn from_sparse(svm: &SparseSVM, min_density: f32) -> Result<DenseSVM, Error> {
+        let kernel = svm.kernel.to_dense().ok_or(Error::KernelNotConvertible)?;
+
+        let mut num_attributes = 0;
+        let mut num_non_zero = 0;
+
+        for class in &svm.classes {
+            for sv in class.support_vectors.row_iter() {
+                for (index, _) in sv.iter() {
+                    num_attributes = num_attributes.max(index as usize + 1);
+                    num_non_zero += 1;
+                }
+            }
+        }
+
+        let density = density(num_non_zero, svm.num_total_sv, num_attributes);
+
+        if density < min_density {
+            return Err(Error::DensityOutOfBounds { density, threshold: min_density });
+        }
+
+        let classes = svm
+            .classes
+            .iter()
+            .map(|class| {
+                let layout = Layout::for_shape(svm.num_total_sv, num_attributes);
+                let mut support_vectors = DenseMatrix::with_dimension(class.num_support_vectors, num_attributes, layout);
+
+                for (i_vector, sv) in class.support_vectors.row_iter().enumerate() {
+                    for (index, value) in sv.iter() {
+                        support_vectors.set(i_vector, index as usize, value);
+                    }
+                }
+
+                Class {
+                    label: class.label,
+                    num_support_vectors: class.num_support_vectors,
+                    coefficients: class.coefficients.clone(),
+                    support_vectors,
+                    prepared: Vec::new(),
+                }
+            }).collect::<Vec<Class<DenseMatrix>>>();
+
+        let mut rval = SVMCore {
+            num_total_sv: svm.num_total_sv,
+            num_attributes,
+            rho: svm.rho.clone(),
+            probabilities: svm.probabilities.clone(),
+            svm_type: svm.svm_type,
+            kernel,
+            classes,
+            settings: svm.settings.clone(),
+            linear_weights: None,
+            pairs: svm.pairs.clone(),
+            metadata: svm.metadata.clone(),
+            phantom_v32: PhantomData,
+            phantom_v64: PhantomData,
+        };
+
+        rval.prepare_kernel();
+        rval.set_fold_linear(true);
+
+        Ok(rval)
+    }
+}
+
+impl SparseSVM {
+    /// Creates a [SparseSVM] from a [DenseSVM].
+    ///
+    /// # Description
+    ///
+    /// All attributes of the support vectors which are exactly `0.0` will be dropped.
+    ///
+    /// The ratio of non-zero attributes over all support vectors is the model's density. If
+    /// it is above `max_density` the conversion is refused with [Error::DensityOutOfBounds],
+    /// as sparse models are considerably slower for mostly dense data.
+    pub fn from_dense(svm: &DenseSVM, max_density: f32) -> Result<SparseSVM, Error> {
+        let kernel = svm.kernel.to_sparse().ok_or(Error::KernelNotConvertible)?;
+
+        let mut num_non_zero = 0;
+
+        for class in &svm.classes {
+            for i_vector in 0 .. class.num_support_vectors {
+                for i_attribute in 0 .. svm.num_attributes {
+                    if class.support_vectors.get(i_vector, i_attribute) != 0.0 {
+                        num_non_zero += 1;
+                    }
+                }
+            }
+        }
+
+        let density = density(num_non_zero, svm.num_total_sv, svm.num_attributes);
+
+        if density > max_density {
+            return Err(Error::DensityOutOfBounds { density, threshold: max_density });
+        }
+
+        let classes = svm
+            .classes
+            .iter()
+            .map(|class| {
+                let mut support_vectors = SparseMatrix::with(class.num_support_vectors);
+
+                for i_vector in 0 .. class.num_support_vectors {
+                    for i_attribute in 0 .. svm.num_attributes {
+                        let value = class.support_vectors.get(i_vector, i_attribute);
+
+                        if value != 0.0 {
+                            support_vectors[(i_vector, i_attribute)] = value;
+                        }
+                    }
+                }
+
+                Class {
+                    label: class.label,
+                    num_support_vectors: class.num_support_vectors,
+                    coefficients: class.coefficients.clone(),
+                    support_vectors,
+                    prepared: Vec::new(),
+                }
+            }).collect::<Vec<Class<SparseMatrix<f32>>>>();
+
+        Ok(SVMCore {
+            num_total_sv: svm.num_total_sv,
+            num_attributes: svm.num_attributes,
+            rho: svm.rho.clone(),
+            probabilities: svm.probabilities.clone(),
+            svm_type: svm.svm_type,
+            kernel,
+            classes,
+            settings: svm.settings.clone(),
+            linear_weights: None,
+            pairs: svm.pairs.clone(),
+            metadata: svm.metadata.clone(),
+            phantom_v32: PhantomData,
+            phantom_v64: PhantomData,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a SparseSVM> for DenseSVM {
+    type Error = Error;
+
+    /// Densifies the [SparseSVM] regardless of its density, see [DenseSVM::from_sparse].
+    fn try_from(svm: &'a SparseSVM) -> Result<DenseSVM, Error> { DenseSVM::from_sparse(svm, 0.0) }
+}
+
+impl<'a> TryFrom<&'a DenseSVM> for SparseSVM {
+    type Error = Error;
+
+    /// Sparsifies the [DenseSVM] regardless of its density, see [SparseSVM::from_dense].
+    fn try_from(svm: &'a DenseSVM) -> Result<SparseSVM, Error> { SparseSVM::from_dense(svm, 1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn dense_to_sparse_and_back() -> Result<(), Error> {
+        let dense = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let sparse = SparseSVM::try_from(&dense)?;
+        let dense = DenseSVM::try_from(&sparse)?;
+
+        let mut problem_sparse = Problem::from(&sparse);
+        let mut problem_dense = Problem::from(&dense);
+
+        set_sample_features(&mut problem_sparse);
+        set_sample_features(&mut problem_dense);
+
+        sparse.predict_value(&mut problem_sparse)?;
+        dense.predict_value(&mut problem_dense)?;
+
+        assert_eq!(problem_sparse.solution(), problem_dense.solution());
+        assert!((problem_sparse.margin().unwrap() - problem_dense.margin().unwrap()).abs() < 1e-5);
+        assert_eq!(sparse.stats().support_vectors, dense.stats().support_vectors);
+
+        Ok(())
+    }
+
+    #[test]
+    fn density_threshold() -> Result<(), Error> {
+        let dense = DenseSVM::try_from(SAMPLE_MODEL)?;
+
+        assert!(SparseSVM::from_dense(&dense, 0.1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rbf_round_trip() -> Result<(), Error> {
+        let dense = DenseSVM::try_from(samples::DENSE_RBF_BINARY.model)?;
+        let sparse = SparseSVM::from_dense(&dense, 1.0)?;
+
+        let mut problem_sparse = Problem::from(&sparse);
+        let mut problem_dense = Problem::from(&dense);
+
+        // Zeros are dropped from the sparse model, so most attributes are only present in one vector.
+        for (i, value) in [0.55838, 0.0, -0.581292, 0.0].iter().enumerate() {
+            problem_sparse.features()[i] = *value;
+            problem_dense.features()[i] = *value;
+        }
+
+        sparse.predict_value(&mut problem_sparse)?;
+        dense.predict_value(&mut problem_dense)?;
+
+        assert_eq!(problem_sparse.solution(), problem_dense.solution());
+        assert!((problem_sparse.margin().unwrap() - problem_dense.margin().unwrap()).abs() < 1e-5);
+
+        Ok(())
+    }
+}
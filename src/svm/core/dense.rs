@@ -2,26 +2,464 @@ use simd_aligned::{f32s, f64s, RowOptimized, SimdMatrix, SimdVector};
 use std::{convert::TryFrom, marker::PhantomData};
 
 use crate::{
+    dense::{DenseMatrix, Layout},
     errors::Error,
-    parser::ModelFile,
+    parser::{Allocation, Limits, ModelFile, SupportVector},
     svm::{
+        batch::ProbabilityMatrix,
         class::Class,
         core::SVMCore,
-        kernel::{KernelDense, Linear, Poly, Rbf, Sigmoid},
+        kernel::{KernelDense, Linear, Normalized, Poly, Rbf, Sigmoid},
         predict::Predict,
-        problem::{Problem, Solution},
+        problem::{DenseProblem, ProbabilityKind, Problem, Solution},
+        settings::Reject,
+        stats::{density, simd_bytes, MemoryUsage, PredictStats, Stats, Stopwatch},
         DenseSVM, Probabilities, SVMType,
     },
     util::{find_max_index, set_all, sigmoid_predict},
     vectors::Triangular,
 };
 
+/// Models with at most this many attributes pack problems into SIMD lanes, see [SVMCore::packs_problems].
+const PACKED_MAX_ATTRIBUTES: usize = 16;
+
+/// Most SIMD lanes problems are packed into.
+const PACKED_MAX_LANES: usize = 16;
+
 impl DenseSVM {
     impl_common_svm!(SimdVector<f32s>);
+
+    /// Returns the [Layout] of the support vectors.
+    pub fn layout(&self) -> Layout { self.classes.first().map_or(Layout::Rows, |c| c.support_vectors.layout()) }
+
+    /// Changes the [Layout] of the support vectors.
+    ///
+    /// When a model is loaded the layout is picked by [Layout::for_shape]. Use this method
+    /// to override that choice, for example after benchmarking both layouts for your model.
+    pub fn set_layout(&mut self, layout: Layout) {
+        let num_attributes = self.num_attributes;
+
+        for class in self.classes.iter_mut() {
+            if class.support_vectors.layout() != layout {
+                class.support_vectors = class.support_vectors.to_layout(class.num_support_vectors, num_attributes, layout);
+            }
+        }
+    }
+
+    /// Predicts a [Problem] of which only the features in `changed` were modified since it was last
+    /// predicted with this method.
+    ///
+    /// # Description
+    ///
+    /// For linear and RBF kernels in [Layout::Rows] only the changed attributes of each support
+    /// vector are visited, instead of all of them. The first call for a problem, calls
+    /// for other kernels and calls after [Problem::invalidate_cache] compute all kernel
+    /// values like [Predict::predict_value].
+    ///
+    /// Each incremental update accumulates a tiny floating point error. If you predict
+    /// incrementally for a long time you might want to call [Problem::invalidate_cache]
+    /// every now and then. Passing indices beyond [SVMCore::attributes] will panic.
+    pub fn predict_value_incremental(&self, problem: &mut DenseProblem, changed: &[usize]) -> Result<(), Error> {
+        // Regression models ignore the class mask.
+        if self.is_regression() && problem.class_mask.is_some() {
+            let mask = problem.class_mask.take();
+            let result = self.predict_value_incremental(problem, changed);
+            problem.class_mask = mask;
+            return result;
+        }
+
+        let start = self.observation_start();
+        let mut watch = self.stats_start(problem);
+
+        // Kernel values of excluded classes are skipped, so later updates could not build on them.
+        if problem.class_mask.is_some() {
+            problem.invalidate_cache();
+            self.recompute_kernel_values(problem);
+            watch.lap(&mut problem.stats, |s| &mut s.kernel);
+            self.compute_solution(problem);
+            watch.lap(&mut problem.stats, |s| &mut s.decision);
+            self.observe(problem, start, false);
+            return Ok(());
+        }
+
+        let updated = match problem.previous_features.take() {
+            Some(previous) => {
+                let updated = self.update_kernel_values(problem, &previous, changed);
+                problem.previous_features = Some(previous);
+                updated
+            }
+            None => false,
+        };
+
+        if updated {
+            self.count_support_vectors(problem);
+        } else {
+            self.recompute_kernel_values(problem);
+        }
+
+        match &mut problem.previous_features {
+            Some(previous) => previous.flat_mut().copy_from_slice(problem.features.as_raw().flat()),
+            None => problem.previous_features = Some(problem.features.as_raw().clone()),
+        }
+
+        // Regular predictions must not reuse these kernel values.
+        problem.cached_features = None;
+        problem.cached_token = None;
+
+        watch.lap(&mut problem.stats, |s| &mut s.kernel);
+        self.compute_solution(problem);
+        watch.lap(&mut problem.stats, |s| &mut s.decision);
+
+        self.observe(problem, start, false);
+
+        Ok(())
+    }
+
+    /// Updates the kernel values for the `changed` features, returns `false` if not supported.
+    fn update_kernel_values(&self, problem: &mut DenseProblem, previous: &SimdVector<f32s>, changed: &[usize]) -> bool {
+        // Updated kernel values depend on the order of all previous updates.
+        if self.settings.deterministic {
+            return false;
+        }
+
+        let features = &problem.features;
+        let kernel_values = &mut problem.kernel_values;
+
+        for (i, class) in self.classes.iter().enumerate() {
+            let kvalues = kernel_values.row_as_flat_mut(i);
+
+            let updated = match &class.support_vectors {
+                DenseMatrix::Rows(m) => self.kernel.compute_incremental(m, features.as_raw(), previous, changed, kvalues),
+                DenseMatrix::Columns(_) => false,
+            };
+
+            if !updated {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Sets the features of `problem` to the support vector `index` of the class `class`.
+    crate fn load_support_vector(&self, problem: &mut DenseProblem, class: usize, index: usize) {
+        let support_vectors = &self.classes[class].support_vectors;
+        let features = problem.features.as_slice_mut();
+
+        for (i, feature) in features.iter_mut().enumerate().take(self.num_attributes) {
+            *feature = support_vectors.get(index, i);
+        }
+    }
+
+    /// Replaces the kernel, for example by a kernel implemented outside of FFSVM.
+    ///
+    /// Like when loading a model, linear kernels are folded (see [SVMCore::set_fold_linear]).
+    /// Kernel values cached in problems predicted before are stale, use [Problem::invalidate_cache].
+    pub fn set_kernel(&mut self, kernel: Box<dyn KernelDense>) {
+        self.kernel = kernel;
+        self.prepare_kernel();
+        self.set_fold_linear(true);
+    }
+
+    /// Replaces the kernel `k` by its [Normalized] version `k(x, y) / sqrt(k(x, x) * k(y, y))`,
+    /// see [SVMCore::set_kernel].
+    pub fn normalize_kernel(&mut self) {
+        let base = std::mem::replace(&mut self.kernel, Box::new(Linear {}));
+        self.set_kernel(Box::new(Normalized::new(base)));
+    }
+
+    /// Computes the values the kernel needs per support vector, see [KernelDense::prepare].
+    crate fn prepare_kernel(&mut self) {
+        let num_attributes = self.num_attributes;
+
+        for class in self.classes.iter_mut() {
+            class.prepared = match &class.support_vectors {
+                DenseMatrix::Rows(m) => self.kernel.prepare(m),
+                DenseMatrix::Columns(_) => match class.support_vectors.to_layout(class.num_support_vectors, num_attributes, Layout::Rows) {
+                    DenseMatrix::Rows(m) => self.kernel.prepare(&m),
+                    DenseMatrix::Columns(_) => unreachable!(),
+                },
+            };
+        }
+    }
+
+    /// Enables or disables folding of linear support vectors into weight vectors.
+    ///
+    /// # Description
+    ///
+    /// For linear kernels the decision value between two classes is `<w, x> - rho`, where `w` is
+    /// the sum of the support vectors of both classes weighted by their coefficients. With
+    /// folding enabled `w` is computed once, and predicting costs `O(attributes)` per pair of
+    /// classes instead of `O(support vectors * attributes)`.
+    ///
+    /// Folding is enabled by default when loading linear models and has no effect for other
+    /// kernels. Since kernel values are not computed when folding, you need to disable it
+    /// before using [SVMCore::explain].
+    pub fn set_fold_linear(&mut self, fold: bool) {
+        self.linear_weights = if fold && self.kernel.is_linear() { Some(self.compute_linear_weights()) } else { None };
+    }
+
+    /// Computes the weight vector `w` for each pair of classes, stored in the order of `rho`.
+    fn compute_linear_weights(&self) -> SimdMatrix<f32s, RowOptimized> {
+        let mut weights = SimdMatrix::with_dimension(self.rho.data.len(), self.num_attributes);
+
+        // Each entry is (class, row of coefficients to use) for all classes contributing to a pair.
+        let pairs = match self.svm_type {
+            SVMType::CSvc | SVMType::NuSvc => (0 .. self.classes.len())
+                .flat_map(|i| (i + 1 .. self.classes.len()).map(move |j| (i, j)))
+                .map(|(i, j)| (self.rho.offset(i, j), vec![(i, j - 1), (j, i)]))
+                .collect::<Vec<_>>(),
+            SVMType::ESvr | SVMType::NuSvr => vec![(0, vec![(0, 0)])],
+        };
+
+        for (row, contributors) in pairs {
+            let mut weight = vec![0.0f64; self.num_attributes];
+
+            for (c, coefficient_row) in contributors {
+                let class = &self.classes[c];
+                let coefficients = class.coefficients.flat();
+
+                for k in 0 .. class.num_support_vectors {
+                    let alpha = coefficients[(coefficient_row, k)];
+
+                    for (a, w) in weight.iter_mut().enumerate() {
+                        *w += alpha * f64::from(class.support_vectors.get(k, a));
+                    }
+                }
+            }
+
+            let mut flat = weights.flat_mut();
+
+            for (a, w) in weight.iter().enumerate() {
+                flat[(row, a)] = *w as f32;
+            }
+        }
+
+        weights
+    }
+
+    /// Returns the heap bytes used by all support vectors.
+    crate fn support_vector_bytes(&self) -> usize {
+        self.classes
+            .iter()
+            .map(|c| c.support_vectors.heap_bytes(c.num_support_vectors, self.num_attributes) + c.prepared.capacity() * std::mem::size_of::<f64>())
+            .sum()
+    }
+
+    /// Returns the ratio of non-zero attributes over all support vectors.
+    crate fn support_vector_density(&self) -> f32 {
+        let mut num_non_zero = 0;
+
+        for class in &self.classes {
+            for i_vector in 0 .. class.num_support_vectors {
+                for i_attribute in 0 .. self.num_attributes {
+                    if class.support_vectors.get(i_vector, i_attribute) != 0.0 {
+                        num_non_zero += 1;
+                    }
+                }
+            }
+        }
+
+        density(num_non_zero, self.num_total_sv, self.num_attributes)
+    }
+
+    /// Returns the heap bytes used by the features of a problem.
+    crate fn feature_bytes(&self) -> usize { simd_bytes::<f32s>(1, self.num_attributes, f32s::lanes()) }
+
+    /// If linear weights are folded, computes the decision value between class `i` and `j`.
+    crate fn folded_decision_value(&self, problem: &DenseProblem, i: usize, j: usize) -> Option<f64> {
+        let weights = self.linear_weights.as_ref()?;
+        let row = self.rho.offset(i, j);
+
+        if self.settings.deterministic {
+            let sum = weights.row_as_flat(row).iter().zip(problem.features.as_raw().flat()).fold(0.0, |sum, (a, b)| sum + a * b);
+            return Some(f64::from(sum) - self.rho[(i, j)]);
+        }
+
+        let feature: &[f32s] = problem.features.as_raw();
+        let mut sum = f32s::splat(0.0);
+
+        for (a, b) in weights.row(row).iter().zip(feature) {
+            sum += *a * *b;
+        }
+
+        Some(f64::from(sum.sum()) - self.rho[(i, j)])
+    }
+
+    /// Computes the kernel values for this problem
+    crate fn compute_kernel_values(&self, problem: &mut DenseProblem) {
+        // Folded linear models compute their decision values straight from the features.
+        if self.linear_weights.is_some() {
+            return;
+        }
+
+        // Kernel values of excluded classes are skipped, so they must neither be reused nor cached.
+        if problem.class_mask.is_some() {
+            problem.invalidate_cache();
+        } else if problem.kernel_values_cached() {
+            return;
+        }
+
+        // Incremental predictions can't build on these kernel values.
+        problem.previous_features = None;
+
+        trace_span!(TRACE, "ffsvm::kernel", total_sv = self.num_total_sv, attributes = self.num_attributes);
+        self.recompute_kernel_values(problem);
+    }
+
+    /// Returns `true` if [Predict::predict_values] packs problems into SIMD lanes.
+    ///
+    /// # Description
+    ///
+    /// SIMD vectors are usually filled with consecutive attributes of a support vector. For models
+    /// with only a handful of attributes most lanes stay empty, so instead the same attribute of
+    /// `f32s::lanes()` problems is packed into one vector, and their kernel values are computed
+    /// at once. This is done for models with at most 16 attributes and a built-in kernel, unless
+    /// predictions are deterministic (see [SVMCore::set_deterministic]) or linear support vectors
+    /// are folded (see [SVMCore::set_fold_linear]).
+    ///
+    /// Packed problems are timed together, so [Observation::duration] is the time of all problems
+    /// predicted at once.
+    pub fn packs_problems(&self) -> bool {
+        self.num_attributes <= PACKED_MAX_ATTRIBUTES
+            && f32s::lanes() <= PACKED_MAX_LANES
+            && self.kernel.parameters().is_builtin()
+            && !self.settings.deterministic
+            && self.linear_weights.is_none()
+    }
+
+    /// Predicts up to `f32s::lanes()` problems at once, see [SVMCore::packs_problems].
+    fn predict_values_packed(&self, problems: &mut [DenseProblem]) -> Result<(), Error> {
+        let start = self.observation_start();
+        let mut pending = [false; PACKED_MAX_LANES];
+
+        // Problems with excluded classes take the regular path below.
+        for (problem, pending) in problems.iter_mut().zip(pending.iter_mut()) {
+            if problem.class_mask.is_none() && !problem.kernel_values_cached() {
+                problem.previous_features = None;
+                *pending = true;
+            }
+        }
+
+        let mut shared = if self.settings.predict_stats { Some(PredictStats::default()) } else { None };
+        let mut watch = Stopwatch::start(self.settings.predict_stats);
+
+        self.compute_kernel_values_packed(problems, &pending);
+        watch.lap(&mut shared, |s| &mut s.kernel);
+
+        for (problem, pending) in problems.iter_mut().zip(&pending) {
+            if problem.class_mask.is_some() {
+                self.predict_value(problem)?;
+            } else {
+                let mut watch = self.stats_start(problem);
+
+                if let (Some(stats), Some(shared)) = (&mut problem.stats, shared) {
+                    stats.kernel = shared.kernel;
+                }
+
+                if *pending {
+                    self.count_support_vectors(problem);
+                }
+
+                self.compute_solution(problem);
+                watch.lap(&mut problem.stats, |s| &mut s.decision);
+                self.observe(problem, start, false);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes the kernel values of all `pending` problems, one problem per SIMD lane.
+    fn compute_kernel_values_packed(&self, problems: &mut [DenseProblem], pending: &[bool]) {
+        let parameters = self.kernel.parameters();
+        let uses_distance = parameters.uses_distance();
+        let mut packed = [f32s::splat(0.0); PACKED_MAX_ATTRIBUTES];
+
+        for (lane, problem) in problems.iter().enumerate() {
+            for (p, value) in packed.iter_mut().zip(&problem.features.as_raw().flat()[.. self.num_attributes]) {
+                *p = p.replace(lane, *value);
+            }
+        }
+
+        trace_span!(TRACE, "ffsvm::kernel_packed", total_sv = self.num_total_sv, problems = problems.len());
+
+        for (i, class) in self.classes.iter().enumerate() {
+            for sv in 0 .. class.num_support_vectors {
+                let mut sum = f32s::splat(0.0);
+
+                for (a, p) in packed[.. self.num_attributes].iter().enumerate() {
+                    let x = f32s::splat(class.support_vectors.get(sv, a));
+
+                    if uses_distance {
+                        sum += (x - *p) * (x - *p);
+                    } else {
+                        sum += x * *p;
+                    }
+                }
+
+                for (lane, problem) in problems.iter_mut().enumerate().filter(|(lane, _)| pending[*lane]) {
+                    let value = sum.extract(lane);
+                    let (dot, distance) = if uses_distance { (0.0, value) } else { (value, 0.0) };
+
+                    problem.kernel_values.row_as_flat_mut(i)[sv] = parameters.evaluate(dot, distance);
+                }
+            }
+        }
+    }
+
+    /// Computes the kernel values for this problem, ignoring any cache.
+    crate fn recompute_kernel_values(&self, problem: &mut DenseProblem) {
+        // Get current problem and decision values array
+        let features = &problem.features;
+        let kernel_values = &mut problem.kernel_values;
+        let class_mask = &problem.class_mask;
+        let enabled = |i: usize| class_mask.as_ref().map_or(true, |mask| mask[i]);
+
+        let parameters = self.kernel.parameters();
+
+        if self.settings.deterministic && parameters.is_builtin() {
+            let feature = features.as_raw().flat();
+
+            for (i, class) in self.classes.iter().enumerate().filter(|(i, _)| enabled(*i)) {
+                let kvalues = kernel_values.row_as_flat_mut(i);
+
+                for (sv, kvalue) in kvalues.iter_mut().enumerate().take(class.num_support_vectors) {
+                    let attributes = (0 .. self.num_attributes).map(|a| class.support_vectors.get(sv, a));
+                    *kvalue = parameters.compute_sequential(attributes, feature);
+                }
+            }
+
+            self.count_support_vectors(problem);
+            return;
+        }
+
+        // Compute kernel values per class
+        for (i, class) in self.classes.iter().enumerate().filter(|(i, _)| enabled(*i)) {
+            let kvalues = kernel_values.row_as_flat_mut(i);
+
+            match &class.support_vectors {
+                DenseMatrix::Rows(m) => self.kernel.compute_prepared(m, &class.prepared, features.as_raw(), kvalues),
+                DenseMatrix::Columns(m) => self.kernel.compute_columns_prepared(m, &class.prepared, features.as_raw(), kvalues),
+            }
+        }
+
+        self.count_support_vectors(problem);
+    }
 }
 
 impl Predict<SimdVector<f32s>, SimdVector<f64s>> for DenseSVM {
     impl_common_predict!(SimdVector<f32s>);
+
+    /// Predicts all `problems`. For models with few attributes, `f32s::lanes()` problems are
+    /// predicted at once, see [SVMCore::packs_problems].
+    fn predict_values(&self, problems: &mut [DenseProblem]) -> Result<(), Error> {
+        if self.packs_problems() {
+            self.settings.threads.run_chunks(problems, f32s::lanes(), self.min_parallel_batch(), |chunk| self.predict_values_packed(chunk))
+        } else {
+            self.settings.threads.run(problems, self.min_parallel_batch(), |problem| self.predict_value(problem))
+        }
+    }
 }
 
 impl<'a, 'b> TryFrom<&'a str> for DenseSVM {
@@ -36,63 +474,202 @@ impl<'a, 'b> TryFrom<&'a str> for DenseSVM {
 impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for DenseSVM {
     type Error = Error;
 
-    fn try_from(raw_model: &'a ModelFile<'_>) -> Result<DenseSVM, Error> {
-        let (mut svm, nr_sv) = prepare_svm!(raw_model, dyn KernelDense, SimdMatrix<f32s, RowOptimized>);
+    fn try_from(raw_model: &'a ModelFile<'_>) -> Result<DenseSVM, Error> { DenseSVM::load(raw_model, None) }
+}
 
-        let vectors = &raw_model.vectors;
+impl DenseSVM {
+    /// Creates a SVM from `raw_model`, using the kernel `K` if the model's `kernel_type` is `kernel_type`.
+    ///
+    /// # Description
+    ///
+    /// This allows deploying kernels implemented outside of FFSVM (see [KernelDense]) through regular
+    /// model files. The kernel is created from the model via `K::try_from`, so it can read its
+    /// parameters (e.g., `gamma`) from the header. Models with any other `kernel_type` are
+    /// loaded as usual.
+    pub fn from_custom_kernel<K>(raw_model: &ModelFile<'_>, kernel_type: &str) -> Result<DenseSVM, Error>
+    where
+        K: KernelDense + for<'a, 'b> TryFrom<&'a ModelFile<'b>, Error = Error> + 'static,
+    {
+        let kernel = if raw_model.header.kernel_type == kernel_type {
+            Some(Box::new(K::try_from(raw_model)?) as Box<dyn KernelDense>)
+        } else {
+            None
+        };
 
-        // Things down here are a bit ugly as the file format is a bit ugly ...
-        // Now read all vectors and decode stored information
-        let mut start_offset = 0;
+        DenseSVM::load(raw_model, kernel)
+    }
 
-        // In the raw file, support vectors are grouped by class
-        for (i, num_sv_per_class) in nr_sv.iter().enumerate() {
-            let stop_offset = start_offset + *num_sv_per_class as usize;
-
-            // Set support vector and coefficients
-            for (i_vector, vector) in vectors[start_offset .. stop_offset].iter().enumerate() {
-                let mut last_attribute = None;
-
-                // Set support vectors
-                for (i_attribute, attribute) in vector.features.iter().enumerate() {
-                    if let Some(last) = last_attribute {
-                        // In case we have seen an attribute already, this one must be strictly
-                        // the successor attribute
-                        if attribute.index != last + 1 {
-                            return Result::Err(Error::AttributesUnordered {
-                                index: attribute.index,
-                                value: attribute.value,
-                                last_index: last,
-                            });
-                        }
-                    };
-
-                    let mut support_vectors = svm.classes[i].support_vectors.flat_mut();
-                    support_vectors[(i_vector, i_attribute)] = attribute.value;
-
-                    last_attribute = Some(attribute.index);
-                }
+    /// Creates a SVM from the model text `input`, enforcing `limits` and allocating as `allocation` says.
+    ///
+    /// # Description
+    ///
+    /// With [Allocation::Eager] this is `ModelFile::parse_with_limits` followed by `try_from`.
+    /// With [Allocation::Chunked] support vectors are converted as they are parsed, so the
+    /// parsed model never exists in memory as a whole, see [Allocation].
+    pub fn from_str_with(input: &str, limits: &Limits, allocation: Allocation) -> Result<DenseSVM, Error> {
+        let lines = match allocation {
+            Allocation::Eager => return DenseSVM::try_from(&ModelFile::parse_with_limits(input, limits)?),
+            Allocation::Chunked(lines) => lines,
+        };
 
-                // Set coefficients
-                for (i_coefficient, coefficient) in vector.coefs.iter().enumerate() {
-                    let mut coefficients = svm.classes[i].coefficients.flat_mut();
-                    coefficients[(i_coefficient, i_vector)] = f64::from(*coefficient);
-                }
+        trace_span!(DEBUG, "ffsvm::load", kind = "dense", lines);
+
+        let mut loaded = None;
+        let mut num_loaded = 0;
+
+        let header = ModelFile::parse_chunked(input, limits, lines, |chunk| {
+            if loaded.is_none() {
+                loaded = Some(prepare_svm!(chunk, chunk.header.total_sv as usize, None::<Box<dyn KernelDense>>, dyn KernelDense, DenseMatrix));
+            }
+
+            if let Some((svm, nr_sv)) = &mut loaded {
+                svm.set_vectors(nr_sv, num_loaded, &chunk.vectors)?;
             }
 
-            // Update last offset.
-            start_offset = stop_offset;
+            num_loaded += chunk.vectors.len();
+            Ok(())
+        })?;
+
+        let (mut svm, _) = loaded.ok_or(Error::MissingSection("SV"))?;
+
+        if num_loaded != svm.num_total_sv {
+            return Err(Error::CountMismatch {
+                section: "SV",
+                expected: svm.num_total_sv,
+                found: num_loaded,
+            });
         }
 
+        // Metadata follow the support vectors, so chunks didn't have them.
+        svm.metadata = header.metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        svm.finish_load();
+
+        Ok(svm)
+    }
+
+    /// Creates a SVM from `raw_model`, using `kernel` instead of the model's `kernel_type` if given.
+    fn load(raw_model: &ModelFile<'_>, kernel: Option<Box<dyn KernelDense>>) -> Result<DenseSVM, Error> {
+        trace_span!(DEBUG, "ffsvm::load", kind = "dense", total_sv = raw_model.header.total_sv, classes = raw_model.header.nr_class);
+
+        let (mut svm, nr_sv) = prepare_svm!(raw_model, raw_model.vectors.len(), kernel, dyn KernelDense, DenseMatrix);
+
+        svm.set_vectors(&nr_sv, 0, &raw_model.vectors)?;
+        svm.finish_load();
+
         // Return what we have
         Result::Ok(svm)
     }
+
+    /// Sets the support vectors and coefficients of `vectors`, the first of which is support vector
+    /// `first` of the model file, which has `nr_sv` support vectors per class.
+    fn set_vectors(&mut self, nr_sv: &[u32], first: usize, vectors: &[SupportVector]) -> Result<(), Error> {
+        let num_coefficients = self.classes.len().max(2) - 1;
+
+        if first + vectors.len() > self.num_total_sv {
+            return Err(Error::CountMismatch {
+                section: "SV",
+                expected: self.num_total_sv,
+                found: first + vectors.len(),
+            });
+        }
+
+        // Things down here are a bit ugly as the file format is a bit ugly ...
+        // In the raw file, support vectors are grouped by class
+        let mut class = 0;
+        let mut class_offset = 0;
+
+        for (i, vector) in vectors.iter().enumerate() {
+            while first + i >= class_offset + nr_sv[class] as usize {
+                class_offset += nr_sv[class] as usize;
+                class += 1;
+            }
+
+            let i_vector = first + i - class_offset;
+            let mut last_attribute = None;
+
+            if vector.coefs.len() != num_coefficients {
+                return Err(Error::CountMismatch {
+                    section: "coefficients",
+                    expected: num_coefficients,
+                    found: vector.coefs.len(),
+                });
+            }
+
+            // Set support vectors
+            for (i_attribute, attribute) in vector.features.iter().enumerate() {
+                if let Some(last) = last_attribute {
+                    // In case we have seen an attribute already, this one must be strictly
+                    // the successor attribute
+                    if attribute.index != last + 1 {
+                        return Result::Err(Error::AttributesUnordered {
+                            index: attribute.index,
+                            value: attribute.value,
+                            last_index: last,
+                        });
+                    }
+                };
+
+                self.classes[class].support_vectors.set(i_vector, i_attribute, attribute.value);
+
+                last_attribute = Some(attribute.index);
+            }
+
+            // Set coefficients
+            for (i_coefficient, coefficient) in vector.coefs.iter().enumerate() {
+                let mut coefficients = self.classes[class].coefficients.flat_mut();
+                coefficients[(i_coefficient, i_vector)] = f64::from(*coefficient);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prepares the kernel and layout once all support vectors are set.
+    fn finish_load(&mut self) {
+        let layout = Layout::for_shape(self.num_total_sv, self.num_attributes);
+        self.prepare_kernel();
+        self.set_layout(layout);
+        self.set_fold_linear(true);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::*;
-    use std::convert::TryFrom;
+    use crate::{test_utils::set_sample_features, *};
+    use std::{convert::TryFrom, time::Duration};
+
+    #[test]
+    fn from_file() -> Result<(), Error> {
+        // Test binaries of other builds may run at the same time.
+        let path = std::env::temp_dir().join(format!("ffsvm_from_file_{}.model", std::process::id()));
+        std::fs::write(&path, SAMPLE_MODEL)?;
+
+        let svm = DenseSVM::from_file(&path)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(svm.attributes(), 4);
+        assert!(DenseSVM::from_file(&path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_context() {
+        let model = SAMPLE_MODEL.replace("nr_sv 1 1", "nr_sv 1 -1");
+        let offset = model.find("nr_sv 1 -1").unwrap() + "nr_sv 1 ".len();
+
+        match DenseSVM::from_reader(model.as_bytes(), "bundled/sample.model") {
+            Err(Error::Context { origin, offset: found, error }) => {
+                assert_eq!(origin.as_ref().map(String::as_str), Some("bundled/sample.model"));
+                assert_eq!(found, Some(offset));
+                assert!(match *error {
+                    Error::ParsingError(_) => true,
+                    _ => false,
+                });
+            }
+            _ => panic!("Expected an error with context."),
+        }
+    }
 
     #[test]
     fn class_operations() -> Result<(), Error> {
@@ -100,6 +677,527 @@ mod tests {
 
         assert_eq!(None, svm.class_index_for_label(0));
         assert_eq!(Some(1), svm.class_index_for_label(42));
+        assert_eq!(vec![21, 42], svm.labels().collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn negative_labels() -> Result<(), Error> {
+        let model = SAMPLE_MODEL.replace("label 21 42", "label -1 5000000000");
+        let svm = DenseSVM::try_from(model.as_str())?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(svm.labels().collect::<Vec<_>>(), vec![-1, 5_000_000_000]);
+        assert_eq!(problem.solution(), Solution::Label(5_000_000_000));
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_kernel() -> Result<(), Error> {
+        use crate::simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
+
+        struct Custom;
+
+        impl KernelDense for Custom {
+            fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) { Linear {}.compute(vectors, feature, output) }
+
+            fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+                Linear {}.compute_columns(vectors, feature, output)
+            }
+
+            fn parameters(&self) -> KernelParameters { KernelParameters::Custom { name: "custom" } }
+        }
+
+        impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for Custom {
+            type Error = Error;
+
+            fn try_from(_: &'a ModelFile<'b>) -> Result<Custom, Error> { Ok(Custom) }
+        }
+
+        let model = SAMPLE_MODEL.replace("kernel_type linear", "kernel_type custom");
+        let raw_model = ModelFile::try_from(model.as_str())?;
+        let svm = DenseSVM::from_custom_kernel::<Custom>(&raw_model, "custom")?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+
+        let mut linear = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut expected = Problem::from(&linear);
+        set_sample_features(&mut expected);
+        linear.set_fold_linear(false);
+        linear.predict_value(&mut expected)?;
+
+        assert!((problem.margin().unwrap() - expected.margin().unwrap()).abs() < 1e-6);
+        assert_eq!(svm.stats().kernel, KernelParameters::Custom { name: "custom" });
+        assert!(DenseSVM::try_from(&raw_model).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretouch() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.pretouch();
+        problem.pretouch();
+
+        #[cfg(feature = "mlock")]
+        {
+            svm.lock_memory()?;
+            problem.lock_memory()?;
+        }
+
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), Solution::Label(42));
+
+        Ok(())
+    }
+
+    #[test]
+    fn predict_stats() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.predict_stats(), None);
+
+        svm.set_predict_stats(true);
+        svm.predict_value(&mut problem)?;
+
+        let stats = problem.predict_stats().unwrap();
+        assert_eq!(stats.support_vectors, 0);
+        assert_eq!(stats.probability, Duration::default());
+
+        svm.set_fold_linear(false);
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(problem.predict_stats().unwrap().support_vectors, 2);
+        assert_eq!(problem.solution(), Solution::Label(42));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tiles_agree() -> Result<(), Error> {
+        // Large enough to span several tiles of support vectors and attributes.
+        let model = ModelFile::random_dense("c_svc", "linear", 40, 700);
+        let mut svm = DenseSVM::try_from(&model)?;
+        svm.set_layout(Layout::Rows);
+        svm.set_fold_linear(false);
+
+        let mut problem = Problem::from(&svm);
+        svm.load_support_vector(&mut problem, 0, 0);
+        svm.predict_value(&mut problem)?;
+
+        let tiled = problem.kernel_values.row_as_flat(1)[.. 20].to_vec();
+
+        svm.set_deterministic(true);
+        problem.invalidate_cache();
+        svm.predict_value(&mut problem)?;
+
+        for (a, b) in tiled.iter().zip(&problem.kernel_values.row_as_flat(1)[.. 20]) {
+            assert!((a - b).abs() <= 1e-4 * b.abs());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn packed_problems() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_kernel(Box::new(Rbf { gamma: 0.5 }));
+
+        assert!(svm.packs_problems());
+
+        let mut problems = (0 .. 19).map(|_| Problem::from(&svm)).collect::<Vec<_>>();
+
+        for (i, problem) in problems.iter_mut().enumerate() {
+            let features = problem.features();
+
+            features[0] = 0.55838 - i as f32 * 0.1;
+            features[1] = -0.157895;
+            features[2] = 0.581292 * i as f32;
+            features[3] = -0.221184;
+        }
+
+        let mut expected = problems.clone();
+
+        svm.predict_values(&mut problems)?;
+
+        for (packed, problem) in problems.iter().zip(expected.iter_mut()) {
+            svm.predict_value(problem)?;
+
+            assert_eq!(packed.solution(), problem.solution());
+            assert!((packed.kernel_values.row_as_flat(1)[0] - problem.kernel_values.row_as_flat(1)[0]).abs() < 1e-5);
+        }
+
+        svm.set_deterministic(true);
+        assert!(!svm.packs_problems());
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_models() {
+        let unsupported = SAMPLE_MODEL.replace("kernel_type linear", "kernel_type foo");
+        let mismatch = SAMPLE_MODEL.replace("nr_sv 1 1", "nr_sv 1");
+
+        match DenseSVM::try_from(unsupported.as_str()) {
+            Err(Error::UnsupportedSection { section, value }) => assert_eq!((section, value.as_str()), ("kernel_type", "foo")),
+            _ => panic!("Expected an unsupported `kernel_type`."),
+        }
+
+        match DenseSVM::try_from(mismatch.as_str()) {
+            Err(Error::CountMismatch { section, expected, found }) => assert_eq!((section, expected, found), ("nr_sv", 2, 1)),
+            _ => panic!("Expected a mismatch in `nr_sv`."),
+        }
+    }
+
+    #[test]
+    fn parse_limits() {
+        let exceeded = |limits: Limits| match ModelFile::parse_with_limits(SAMPLE_MODEL, &limits).map_err(|e| e.root().to_string()) {
+            Err(message) => message,
+            Ok(_) => panic!("Expected a limit to be exceeded."),
+        };
+
+        assert!(ModelFile::parse_with_limits(SAMPLE_MODEL, &Limits::default()).is_ok());
+        assert!(exceeded(Limits { max_support_vectors: 1, ..Limits::default() }).contains("max_support_vectors"));
+        assert!(exceeded(Limits { max_attributes: 3, ..Limits::default() }).contains("max_attributes"));
+        assert!(exceeded(Limits { max_classes: 1, ..Limits::default() }).contains("max_classes"));
+        assert!(exceeded(Limits { max_header_bytes: 16, ..Limits::default() }).contains("max_header_bytes"));
+        assert!(exceeded(Limits { max_bytes: 16, ..Limits::default() }).contains("max_bytes"));
+
+        let corrupt = SAMPLE_MODEL.replace("nr_sv 1 1", "nr_sv 1 1\nprobA 1 2\nprobB 3");
+        let model = ModelFile::parse_with_limits(&corrupt, &Limits::default()).unwrap();
+
+        match DenseSVM::try_from(&model) {
+            Err(Error::CountMismatch { section, expected, found }) => assert_eq!((section, expected, found), ("probA", 1, 2)),
+            _ => panic!("Expected a mismatch in `probA`."),
+        }
+    }
+
+    #[test]
+    fn chunked_allocation() -> Result<(), Error> {
+        let limits = Limits::default();
+
+        for sample in samples::ALL.iter() {
+            for lines in &[1, 5, 1000] {
+                let (eager, chunked) = if sample.sparse {
+                    let eager = SparseSVM::from_str_with(sample.model, &limits, Allocation::Eager)?;
+                    let chunked = SparseSVM::from_str_with(sample.model, &limits, Allocation::Chunked(*lines))?;
+                    let mut problems = (Problem::from(&eager), Problem::from(&chunked));
+
+                    problems.0.features()[1] = 0.5;
+                    problems.1.features()[1] = 0.5;
+                    eager.predict_value(&mut problems.0)?;
+                    chunked.predict_value(&mut problems.1)?;
+
+                    (problems.0.solution(), problems.1.solution())
+                } else {
+                    let eager = DenseSVM::from_str_with(sample.model, &limits, Allocation::Eager)?;
+                    let chunked = DenseSVM::from_str_with(sample.model, &limits, Allocation::Chunked(*lines))?;
+                    let mut problems = (Problem::from(&eager), Problem::from(&chunked));
+
+                    problems.0.features()[1] = 0.5;
+                    problems.1.features()[1] = 0.5;
+                    eager.predict_value(&mut problems.0)?;
+                    chunked.predict_value(&mut problems.1)?;
+
+                    (problems.0.solution(), problems.1.solution())
+                };
+
+                assert_eq!(eager, chunked, "{} in chunks of {} lines", sample.name, lines);
+            }
+        }
+
+        let mut model = ModelFile::try_from(SAMPLE_MODEL)?;
+        model.set_metadata("git_hash", "4f2a9c1");
+
+        let text = model.to_string();
+        let svm = DenseSVM::from_str_with(&text, &limits, Allocation::Chunked(2))?;
+        assert_eq!(svm.metadata("git_hash"), Some("4f2a9c1"));
+
+        let missing = SAMPLE_MODEL.replace("total_sv 2", "total_sv 3").replace("nr_sv 1 1", "nr_sv 1 2");
+        assert!(DenseSVM::from_str_with(&missing, &limits, Allocation::Chunked(1)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn label_names() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+
+        svm.set_label_names(Some(&[(42, "blink")]))?;
+
+        assert_eq!(svm.label_name(42), Some("blink"));
+        assert_eq!(svm.label_name(21), None);
+        assert_eq!(svm.solution_name(Solution::Label(42)), Some("blink"));
+        assert_eq!(svm.label_for_name("blink"), Some(42));
+        assert!(svm.set_label_names(Some(&[(7, "wink")])).is_err());
+
+        svm.set_label_names(None)?;
+        assert_eq!(svm.label_name(42), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn predict_restricted() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_restricted(&mut problem, &[21])?;
+        assert_eq!(problem.solution(), Solution::Label(21));
+
+        svm.predict_restricted(&mut problem, &[21, 42])?;
+        assert_eq!(problem.solution(), Solution::Label(42));
+
+        assert!(svm.predict_restricted(&mut problem, &[7]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn regression_ignores_labels() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(samples::DENSE_RBF_REGRESSION.model)?;
+        let mut problem = Problem::from(&svm);
+        let mut expected = Problem::from(&svm);
+
+        for value in &[0.25, -0.5] {
+            problem.features()[0] = *value;
+            expected.features()[0] = *value;
+            svm.predict_value(&mut expected)?;
+
+            svm.predict_restricted(&mut problem, &[])?;
+            assert_eq!(problem.solution(), expected.solution());
+        }
+
+        problem.set_enabled_labels(Some(&[]))?;
+        problem.features()[0] = 0.75;
+        expected.features()[0] = 0.75;
+
+        svm.predict_value(&mut problem)?;
+        svm.predict_value(&mut expected)?;
+        assert_eq!(problem.solution(), expected.solution());
+
+        Ok(())
+    }
+
+    #[test]
+    fn class_mask() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_pseudo_probabilities(true);
+
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        problem.set_label_enabled(42, false)?;
+        assert!(problem.label_enabled(21));
+        assert!(!problem.label_enabled(42));
+
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), Solution::Label(21));
+
+        svm.predict_probability(&mut problem)?;
+        assert_eq!(problem.probabilities()[.. 2], [1.0, 0.0]);
+
+        problem.set_enabled_labels(None)?;
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), Solution::Label(42));
+
+        assert!(problem.set_enabled_labels(Some(&[7])).is_err());
+        assert!(problem.set_label_enabled(7, true).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_by_token() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_fold_linear(false);
+
+        let mut problem = Problem::from(&svm);
+
+        problem.set_cache(Cache::Token(1));
+        problem.features()[0] = 0.55838;
+        svm.predict_value(&mut problem)?;
+        let first = problem.solution();
+
+        // Same token, so the changed features must be ignored.
+        problem.features()[0] = -100.0;
+        svm.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), first);
+
+        problem.set_cache(Cache::Token(2));
+        svm.predict_value(&mut problem)?;
+        assert_ne!(problem.solution(), first);
+
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_matches_full() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut incremental = Problem::from(&svm);
+        let mut full = Problem::from(&svm);
+
+        svm.predict_value_incremental(&mut incremental, &[])?;
+
+        for (i, value) in SAMPLE_FEATURES.iter().enumerate() {
+            incremental.features()[i] = *value;
+            full.features()[i] = *value;
+
+            svm.predict_value_incremental(&mut incremental, &[i])?;
+            svm.predict_value(&mut full)?;
+
+            assert_eq!(incremental.solution(), full.solution());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn folded_linear_agrees() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+
+        problem.features()[0] = 0.55838;
+        problem.features()[2] = 0.581292;
+
+        svm.predict_value(&mut problem)?;
+        let folded = problem.margin();
+
+        svm.set_fold_linear(false);
+        svm.predict_value(&mut problem)?;
+        let unfolded = problem.margin();
+
+        assert!((folded.unwrap() - unfolded.unwrap()).abs() < 1e-5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn layouts_agree() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_fold_linear(false);
+
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.set_layout(Layout::Rows);
+        svm.predict_value(&mut problem)?;
+        let rows = problem.kernel_values.clone();
+        let margin = problem.margin().unwrap();
+
+        svm.set_layout(Layout::Columns);
+        problem.invalidate_cache();
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(svm.layout(), Layout::Columns);
+        assert!((problem.margin().unwrap() - margin).abs() < 1e-5);
+
+        for class in 0 .. 2 {
+            assert!((problem.kernel_values.row_as_flat(class)[0] - rows.row_as_flat(class)[0]).abs() < 1e-5);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let stats = svm.stats();
+
+        assert_eq!(stats.total_support_vectors, 2);
+        assert_eq!(stats.support_vectors, vec![(21, 1), (42, 1)]);
+        assert_eq!(stats.svm_type, SVMType::CSvc);
+        assert_eq!(stats.kernel, KernelParameters::Linear);
+        assert!(stats.density > 0.0 && stats.density <= 1.0);
+        assert!(!stats.probabilities);
+        assert!(stats.heap_bytes >= 2 * stats.attributes * 4);
+
+        let usage = svm.memory_usage();
+
+        assert_eq!(usage.model(), stats.heap_bytes);
+        assert!(usage.support_vectors >= 2 * stats.attributes * 4);
+        assert_eq!(usage.probabilities, 0);
+        assert!(usage.problem >= stats.attributes * 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn observer() -> Result<(), Error> {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let observed = Arc::new(AtomicUsize::new(0));
+        let counter = observed.clone();
+
+        svm.set_observer(Some(Arc::new(move |o: &Observation| {
+            assert_eq!(o.solution, Solution::Label(42));
+            assert!(o.margin.is_some());
+            counter.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+        svm.predict_value(&mut problem)?;
+        svm.predict_value_incremental(&mut problem, &[])?;
+
+        assert_eq!(observed.load(Ordering::SeqCst), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pseudo_probabilities() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        assert!(svm.predict_probability(&mut problem).is_err());
+
+        svm.set_pseudo_probabilities(true);
+        svm.predict_probability(&mut problem)?;
+        let pseudo = problem.solution();
+
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(problem.probability_kind(), ProbabilityKind::Pseudo);
+        assert_eq!(problem.solution(), pseudo);
+        assert!((problem.probabilities().iter().sum::<f64>() - 1.0).abs() < 1e-6);
+
+        let outcome = problem.outcome();
+
+        assert_eq!(outcome.label, Some(42));
+        assert_eq!(outcome.vote_margin, Some(1));
+        assert!(outcome.probability.unwrap() > 0.5);
+        assert_eq!(outcome.probability, problem.probability_of(42));
+        assert_eq!(problem.probability_of(7), None);
+
+        let confidence = outcome.confidence.unwrap();
+        let entropy = problem.entropy().unwrap();
+
+        assert!(confidence > 0.0 && confidence <= 1.0);
+        assert!(entropy > 0.0 && entropy < 2f64.ln());
+        assert_eq!(problem.label_probabilities().map(|(l, _)| l).collect::<Vec<_>>(), vec![21, 42]);
+        assert!(!outcome.rejected && !outcome.ambiguous);
 
         Ok(())
     }
@@ -0,0 +1,141 @@
+use simd_aligned::{f64s, RowOptimized, SimdMatrix};
+
+use crate::{
+    errors::Error,
+    svm::{class::Class, core::SVMCore, settings::Settings, Probabilities, SVMType},
+    vectors::Triangular,
+};
+
+impl<K, M32, V32, V64> SVMCore<K, M32, V32, V64>
+where
+    K: ?Sized,
+{
+    /// Merges the classes of `other` into `self`, see [SVMCore::merge]. Kernels are not compared.
+    crate fn merge_classes(self, other: Self) -> Result<Self, Error> {
+        for svm in &[&self, &other] {
+            match svm.svm_type {
+                SVMType::CSvc | SVMType::NuSvc => {}
+                SVMType::ESvr | SVMType::NuSvr => return Err(Error::IncompatibleModel),
+            }
+        }
+
+        if self.num_attributes != other.num_attributes || self.classes.iter().any(|a| other.classes.iter().any(|b| a.label == b.label)) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        let offset = self.classes.len();
+        let num_classes = offset + other.classes.len();
+
+        let mut pairs = Triangular::with_dimension(num_classes, false);
+        let mut rho = Triangular::with_dimension(num_classes, 0.0);
+
+        let mut probabilities = match (&self.probabilities, &other.probabilities) {
+            (Some(_), Some(_)) => Some(Probabilities {
+                a: Triangular::with_dimension(num_classes, 0.0),
+                b: Triangular::with_dimension(num_classes, 0.0),
+            }),
+            _ => None,
+        };
+
+        for i in 0 .. num_classes {
+            for j in i + 1 .. num_classes {
+                let (svm, i_old, j_old) = if j < offset {
+                    (&self, i, j)
+                } else if i >= offset {
+                    (&other, i - offset, j - offset)
+                } else {
+                    continue;
+                };
+
+                pairs[(i, j)] = svm.pairs.as_ref().map_or(true, |pairs| pairs[(i_old, j_old)]);
+                rho[(i, j)] = svm.rho[(i_old, j_old)];
+
+                if let (Some(merged), Some(old)) = (&mut probabilities, &svm.probabilities) {
+                    merged.a[(i, j)] = old.a[(i_old, j_old)];
+                    merged.b[(i, j)] = old.b[(i_old, j_old)];
+                }
+            }
+        }
+
+        let rows_self = self.classes.len() - 1;
+        let rows_other = other.classes.len() - 1;
+
+        let classes = self
+            .classes
+            .into_iter()
+            .map(|class| merge_class(class, rows_self, num_classes, 0))
+            .chain(other.classes.into_iter().map(|class| merge_class(class, rows_other, num_classes, offset)))
+            .collect();
+
+        Ok(SVMCore {
+            num_total_sv: self.num_total_sv + other.num_total_sv,
+            num_attributes: self.num_attributes,
+            rho,
+            probabilities,
+            svm_type: self.svm_type,
+            kernel: self.kernel,
+            classes,
+            settings: Settings {
+                multi_label: None,
+                label_names: None,
+                ..self.settings
+            },
+            linear_weights: None,
+            pairs: Some(pairs),
+            metadata: self.metadata,
+            phantom_v32: self.phantom_v32,
+            phantom_v64: self.phantom_v64,
+        })
+    }
+}
+
+/// Moves the `rows` coefficients of `class` to rows `offset ..` of coefficients for `num_classes` classes.
+fn merge_class<M32>(class: Class<M32>, rows: usize, num_classes: usize, offset: usize) -> Class<M32> {
+    let mut coefficients = SimdMatrix::<f64s, RowOptimized>::with_dimension(num_classes - 1, class.num_support_vectors);
+
+    {
+        let old = class.coefficients.flat();
+        let mut new = coefficients.flat_mut();
+
+        for row in 0 .. rows {
+            for column in 0 .. class.num_support_vectors {
+                new[(offset + row, column)] = old[(row, column)];
+            }
+        }
+    }
+
+    Class { coefficients, ..class }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn merge_disjoint_labels() -> Result<(), Error> {
+        let a = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let b = DenseSVM::try_from(SAMPLE_MODEL.replace("label 21 42", "label 1 2").as_str())?;
+
+        assert!(a.merge(DenseSVM::try_from(SAMPLE_MODEL)?).is_err());
+
+        let rbf = samples::DENSE_RBF_BINARY.model;
+        let other_gamma = rbf.replace("gamma 0.142857", "gamma 2").replace("label 0 1", "label 5 6");
+        let other_labels = rbf.replace("label 0 1", "label 5 6");
+
+        assert!(DenseSVM::try_from(rbf)?.merge(DenseSVM::try_from(other_gamma.as_str())?).is_err());
+        assert!(DenseSVM::try_from(rbf)?.merge(DenseSVM::try_from(other_labels.as_str())?).is_ok());
+
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?.merge(b)?;
+        let mut problem = Problem::from(&svm);
+        set_sample_features(&mut problem);
+
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(svm.classes(), 4);
+        assert_eq!(problem.votes().map(|(_, v)| v).sum::<u32>(), 2);
+        assert_eq!(problem.decision_value(21, 1), Some(0.0));
+
+        Ok(())
+    }
+}
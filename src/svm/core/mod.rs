@@ -1,10 +1,28 @@
-use std::marker::PhantomData;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use simd_aligned::{f32s, f64s, RowOptimized, SimdMatrix};
+use std::{cmp::Ordering, marker::PhantomData, sync::Arc, time::Instant};
 
 use crate::{
-    svm::{class::Class, Probabilities, SVMType},
+    errors::Error,
+    svm::{
+        class::Class,
+        observer::{Observation, Observer, PredictObserver},
+        problem::{Problem, Solution},
+        settings::{Reject, Settings, Threads, TieBreak},
+        stats::{PredictStats, Stopwatch},
+        Probabilities, SVMType,
+    },
+    util::find_max_index,
     vectors::Triangular,
 };
 
+/// Multiply-adds a batch should need before it is worth distributing over threads.
+const PARALLEL_MIN_WORK: usize = 1 << 16;
+
+/// Bounds of the derived [SVMCore::min_parallel_batch].
+const PARALLEL_MIN_BATCH: usize = 2;
+const PARALLEL_MAX_BATCH: usize = 64;
+
 /// Generic support vector machine core, used by [DenseSVM] and [SparseSVM].
 ///
 /// The SVM holds a kernel, class information and all other numerical data read from
@@ -23,6 +41,14 @@ use crate::{
 /// let svm = SparseSVM::try_from("...");
 /// ```
 ///
+/// # Sharing between threads
+///
+/// A loaded SVM is immutable while predicting, it has no interior mutability and all state
+/// of a prediction lives in the [Problem]. SVMs are `Send + Sync`, so a single model can be
+/// shared by any number of threads via an [Arc](std::sync::Arc), with one [Problem] per
+/// thread. Settings like [SVMCore::set_tie_break] need `&mut self` and should be applied
+/// before sharing the model; to replace a shared model see [ReloadableSvm].
+///
 pub struct SVMCore<K, M32, V32, V64>
 where
     K: ?Sized,
@@ -45,6 +71,18 @@ where
     /// All classes
     crate classes: Vec<Class<M32>>,
 
+    /// Runtime options not stored in the model.
+    crate settings: Settings,
+
+    /// Weight vectors per pair of classes if the support vectors of a linear dense model were folded.
+    crate linear_weights: Option<SimdMatrix<f32s, RowOptimized>>,
+
+    /// Which pairwise classifiers are available if the model was merged, all are if `None`.
+    crate pairs: Option<Triangular<bool>>,
+
+    /// Metadata of the model file as `(key, value)`.
+    crate metadata: Vec<(String, String)>,
+
     phantom_v32: PhantomData<V32>,
 
     phantom_v64: PhantomData<V64>,
@@ -59,10 +97,515 @@ where
 
     /// Returns number of classes, reflecting the libSVM model.
     pub fn classes(&self) -> usize { self.classes.len() }
+
+    /// Returns the labels of all classes, in the order of [Problem::probabilities].
+    pub fn labels(&self) -> impl Iterator<Item = i64> + '_ { self.classes.iter().map(|class| class.label) }
+
+    /// Returns the value of the metadata `key` of the model file, see [ModelFile].
+    ///
+    /// This can be used to verify the intended model was loaded, for example by checking
+    /// a git hash or feature schema version stored at training time.
+    pub fn metadata(&self, key: &str) -> Option<&str> { self.metadata.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()) }
+
+    /// Returns all metadata of the model file as `(key, value)`.
+    pub fn metadata_entries(&self) -> impl Iterator<Item = (&str, &str)> { self.metadata.iter().map(|(k, v)| (k.as_str(), v.as_str())) }
+
+    /// Returns how ties between classes with the same number of votes are resolved.
+    pub fn tie_break(&self) -> TieBreak { self.settings.tie_break }
+
+    /// Sets how ties between classes with the same number of votes are resolved. By default
+    /// this is [TieBreak::FirstClass], which is what libSVM does.
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) { self.settings.tie_break = tie_break; }
+
+    /// Returns when classifications are rejected.
+    pub fn reject(&self) -> Reject { self.settings.reject }
+
+    /// Sets when classifications should be rejected with [Solution::Rejected] instead of reporting
+    /// the best label. By default this is [Reject::Never].
+    pub fn set_reject(&mut self, reject: Reject) { self.settings.reject = reject; }
+
+    /// Enables or disables multi-label classification.
+    ///
+    /// # Description
+    ///
+    /// Some problems can belong to several classes at the same time. If `thresholds` is `Some`,
+    /// [Predict::predict_probability] reports all labels whose probability is above their
+    /// threshold via [Problem::multi_label], and sets the solution to [Solution::MultiLabel].
+    /// Labels not listed in `thresholds` are never reported. The [Reject] policy is ignored
+    /// in this mode.
+    ///
+    /// Requires a model trained with probability estimates.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::UnknownLabel] if any of the given labels is not part of the model.
+    pub fn set_multi_label(&mut self, thresholds: Option<&[(i64, f64)]>) -> Result<(), Error> {
+        self.settings.multi_label = match thresholds {
+            None => None,
+            Some(thresholds) => {
+                let mut per_class = vec![std::f64::INFINITY; self.classes.len()];
+
+                for (label, threshold) in thresholds {
+                    let index = self.classes.iter().position(|c| c.label == *label).ok_or(Error::UnknownLabel(*label))?;
+                    per_class[index] = *threshold;
+                }
+
+                Some(per_class)
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Attaches application names to the labels of the model, or removes them if `names` is `None`.
+    ///
+    /// # Description
+    ///
+    /// Models only know numeric labels, while applications usually think in terms like `"blink"`.
+    /// Keeping the mapping with the SVM means it ships and is tested together with the model,
+    /// instead of being repeated in every integration. Use [SVMCore::solution_name] to obtain
+    /// the name of a prediction, and [SVMCore::label_for_name] to map back. Labels not listed
+    /// in `names` have no name. To map labels to an application enum, give the enum variants'
+    /// names here or implement `TryFrom<i64>` for the enum.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::UnknownLabel] if any of the given labels is not part of the model.
+    pub fn set_label_names(&mut self, names: Option<&[(i64, &str)]>) -> Result<(), Error> {
+        self.settings.label_names = match names {
+            None => None,
+            Some(names) => {
+                let mut per_class = vec![None; self.classes.len()];
+
+                for (label, name) in names {
+                    let index = self.classes.iter().position(|c| c.label == *label).ok_or(Error::UnknownLabel(*label))?;
+                    per_class[index] = Some((*name).to_owned());
+                }
+
+                Some(per_class)
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Returns the name of `label`, if one was set via [SVMCore::set_label_names].
+    pub fn label_name(&self, label: i64) -> Option<&str> {
+        let index = self.classes.iter().position(|c| c.label == label)?;
+        self.settings.label_names.as_ref()?[index].as_ref().map(|name| name.as_str())
+    }
+
+    /// Returns the name of the label of `solution`, if it is a [Solution::Label] with a name.
+    pub fn solution_name(&self, solution: Solution) -> Option<&str> {
+        match solution {
+            Solution::Label(label) => self.label_name(label),
+            _ => None,
+        }
+    }
+
+    /// Returns the label with the given `name`, if one was set via [SVMCore::set_label_names].
+    pub fn label_for_name(&self, name: &str) -> Option<i64> {
+        let names = self.settings.label_names.as_ref()?;
+        let index = names.iter().position(|n| n.as_ref().map(|n| n.as_str()) == Some(name))?;
+        Some(self.classes[index].label)
+    }
+
+    /// Returns the temperature applied when predicting probabilities.
+    pub fn temperature(&self) -> f64 { self.settings.temperature }
+
+    /// Sets the temperature applied when predicting probabilities.
+    ///
+    /// # Description
+    ///
+    /// The pairwise probabilities are computed as `1 / (1 + exp((A * f + B) / temperature))`, where `A`
+    /// and `B` are the model's calibrated `probA` and `probB`. A temperature above `1.0` softens the
+    /// probabilities towards a uniform distribution, one below `1.0` sharpens them. The default of
+    /// `1.0` reproduces libSVM.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::InvalidTemperature] if `temperature` is not positive and finite, in which case the
+    /// temperature is not changed.
+    pub fn set_temperature(&mut self, temperature: f64) -> Result<(), Error> {
+        if !temperature.is_finite() || temperature <= 0.0 {
+            return Err(Error::InvalidTemperature(temperature));
+        }
+
+        self.settings.temperature = temperature;
+        Ok(())
+    }
+
+    /// Sets the maximum number of iterations when coupling pairwise probabilities of multi-class models.
+    ///
+    /// If the coupling does not converge within this limit [Predict::predict_probability] fails
+    /// with [Error::IterationsExceeded]. With `None` libSVM's limit of `max(100, classes)` is used.
+    pub fn set_coupling_max_iterations(&mut self, max_iterations: Option<usize>) { self.settings.coupling_max_iterations = max_iterations; }
+
+    /// Sets the convergence epsilon when coupling pairwise probabilities of multi-class models.
+    ///
+    /// Larger values converge faster but give less exact probabilities. With `None` libSVM's
+    /// epsilon of `0.005 / classes` is used.
+    pub fn set_coupling_epsilon(&mut self, epsilon: Option<f64>) { self.settings.coupling_epsilon = epsilon; }
+
+    /// Returns `true` if predictions don't depend on the SIMD width.
+    pub fn deterministic(&self) -> bool { self.settings.deterministic }
+
+    /// Enables or disables deterministic predictions.
+    ///
+    /// # Description
+    ///
+    /// Floating point additions are not associative, and SIMD kernels sum in an order that depends
+    /// on the vector width. Builds for SSE, AVX or NEON can therefore produce slightly different
+    /// decision values and probabilities for the same model and problem. If enabled, all sums are
+    /// computed with scalar math in attribute and support vector order, which gives bit-identical
+    /// results regardless of the vector width, at a considerable cost in speed.
+    ///
+    /// Results in deterministic mode can differ slightly from the default mode. Incremental
+    /// predictions (see [DenseSVM::predict_value_incremental]) always recompute all kernel values
+    /// in this mode.
+    pub fn set_deterministic(&mut self, deterministic: bool) { self.settings.deterministic = deterministic; }
+
+    /// Sums the products of `a` and `b`, in element order if deterministic.
+    crate fn sum_products(&self, a: &[f64s], b: &[f64s]) -> f64 {
+        if !self.settings.deterministic {
+            return a.iter().zip(b).map(|(a, b)| (*a * *b).sum()).sum();
+        }
+
+        let mut sum = 0.0;
+
+        // Trailing lanes are zero padding, adding them does not change the sum.
+        for (a, b) in a.iter().zip(b) {
+            for lane in 0 .. f64s::lanes() {
+                sum += a.extract(lane) * b.extract(lane);
+            }
+        }
+
+        sum
+    }
+
+    /// Returns the observer notified after each prediction, if any.
+    pub fn observer(&self) -> Option<&Arc<dyn PredictObserver>> { self.settings.observer.as_ref().map(|o| &o.0) }
+
+    /// Sets an observer notified after each successful prediction, see [PredictObserver].
+    ///
+    /// Without an observer (the default) predictions are not timed, so they cost nothing extra.
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn PredictObserver>>) { self.settings.observer = observer.map(Observer); }
+
+    /// Returns the start time of a prediction if it is observed.
+    crate fn observation_start(&self) -> Option<Instant> { self.settings.observer.as_ref().map(|_| Instant::now()) }
+
+    /// Returns `true` if problems record [PredictStats].
+    pub fn predict_stats(&self) -> bool { self.settings.predict_stats }
+
+    /// Enables or disables [PredictStats] for each prediction.
+    ///
+    /// # Description
+    ///
+    /// If enabled, predictions like [Predict::predict_value] time their phases and count the work
+    /// done, which is then available via [Problem::predict_stats]. This is
+    /// meant to find out why a prediction exceeded its time budget on machines without a profiler.
+    /// Timing costs a few calls to [Instant::now] per prediction, so it is disabled by default.
+    /// Problems predicted together by [SVMCore::packs_problems] share their kernel time.
+    pub fn set_predict_stats(&mut self, enabled: bool) { self.settings.predict_stats = enabled; }
+
+    /// Resets the [PredictStats] of `problem` and starts timing its phases, if enabled.
+    crate fn stats_start(&self, problem: &mut Problem<V32>) -> Stopwatch {
+        problem.stats = if self.settings.predict_stats { Some(PredictStats::default()) } else { None };
+        Stopwatch::start(self.settings.predict_stats)
+    }
+
+    /// Returns `true` for regression models, which have a single class no mask may exclude.
+    crate fn is_regression(&self) -> bool {
+        match self.svm_type {
+            SVMType::CSvc | SVMType::NuSvc => false,
+            SVMType::ESvr | SVMType::NuSvr => true,
+        }
+    }
+
+    /// Counts the support vectors of all classes taking part in the prediction of `problem`.
+    crate fn count_support_vectors(&self, problem: &mut Problem<V32>) {
+        if let Some(stats) = &mut problem.stats {
+            let class_mask = &problem.class_mask;
+            let enabled = |i: usize| class_mask.as_ref().map_or(true, |mask| mask[i]);
+
+            stats.support_vectors += self.classes.iter().enumerate().filter(|(i, _)| enabled(*i)).map(|(_, c)| c.num_support_vectors).sum::<usize>();
+        }
+    }
+
+    /// Notifies the observer about the prediction of `problem` that started at `start`.
+    crate fn observe(&self, problem: &Problem<V32>, start: Option<Instant>, has_probabilities: bool) {
+        if let (Some(observer), Some(start)) = (&self.settings.observer, start) {
+            let probabilities = problem.probabilities.flat();
+
+            observer.0.observe(&Observation {
+                duration: start.elapsed(),
+                solution: problem.result,
+                probability: if has_probabilities { Some(probabilities[find_max_index(probabilities)]) } else { None },
+                margin: problem.margin(),
+            });
+        }
+    }
+
+    /// Returns the number of threads batch predictions use, or `None` for Rayon's global thread pool.
+    pub fn threads(&self) -> Option<usize> {
+        match &self.settings.threads {
+            Threads::Global => None,
+            Threads::Sequential => Some(1),
+            Threads::Pool(pool) => Some(pool.current_num_threads()),
+        }
+    }
+
+    /// Sets how many threads batch predictions like [Predict::predict_values] use.
+    ///
+    /// # Description
+    ///
+    /// By default batch predictions run on Rayon's global thread pool, which uses all cores.
+    /// With `Some(1)` (or `Some(0)`) they run on the calling thread only, with `Some(n)` on a
+    /// dedicated pool of `n` threads owned by this SVM. `None` restores the default.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::ThreadPool] if the threads could not be created.
+    pub fn set_threads(&mut self, threads: Option<usize>) -> Result<(), Error> {
+        self.settings.threads = match threads {
+            None => Threads::Global,
+            Some(0) | Some(1) => Threads::Sequential,
+            Some(n) => Threads::Pool(Arc::new(ThreadPoolBuilder::new().num_threads(n).build()?)),
+        };
+
+        Ok(())
+    }
+
+    /// Runs batch predictions like [Predict::predict_values] on `pool`.
+    ///
+    /// Use this if your application already partitions its cores into pools, so that predictions
+    /// don't oversubscribe them via Rayon's global pool. The pool can be shared by several SVMs,
+    /// see [SVMCore::set_threads] to restore the default.
+    pub fn set_thread_pool(&mut self, pool: Arc<ThreadPool>) { self.settings.threads = Threads::Pool(pool); }
+
+    /// Returns the smallest number of problems a batch prediction distributes over threads.
+    pub fn min_parallel_batch(&self) -> usize {
+        if let Some(min) = self.settings.min_parallel_batch {
+            return min;
+        }
+
+        // Multiply-adds needed per problem, dominated by the kernel values of all support vectors.
+        let work = match &self.linear_weights {
+            Some(weights) => weights.row_iter().count() * self.num_attributes,
+            None => self.num_total_sv * self.num_attributes,
+        };
+
+        (PARALLEL_MIN_WORK / work.max(1)).max(PARALLEL_MIN_BATCH).min(PARALLEL_MAX_BATCH)
+    }
+
+    /// Sets the smallest number of problems a batch prediction distributes over threads.
+    ///
+    /// # Description
+    ///
+    /// Handing problems to other threads takes a few microseconds, so smaller batches are
+    /// predicted on the calling thread. By default the limit is derived from the model size,
+    /// between 2 problems for large models and 64 for tiny ones. `Some(n)` overrides it, `None`
+    /// restores the default.
+    pub fn set_min_parallel_batch(&mut self, min: Option<usize>) { self.settings.min_parallel_batch = min; }
+
+    /// Enables or disables pseudo-probabilities for models trained without probability estimates.
+    ///
+    /// # Description
+    ///
+    /// Usually [Predict::predict_probability] fails with [Error::NoProbabilities] for models
+    /// not trained with `svm-train -b 1`. If enabled, it instead computes a softmax over the
+    /// [Problem::scores] of all classes (scaled by [SVMCore::set_temperature]). These values sum to
+    /// `1.0`, but are **not** calibrated probabilities. Use [Problem::probability_kind] to
+    /// tell them apart. Models with probability estimates are not affected.
+    pub fn set_pseudo_probabilities(&mut self, enabled: bool) { self.settings.pseudo_probabilities = enabled; }
+
+    /// Based on decision values, computes a softmax over the aggregate scores of all classes.
+    crate fn compute_pseudo_probabilities(&self, problem: &mut Problem<V32>) {
+        let num_classes = self.classes.len();
+        let temperature = self.settings.temperature;
+        let max_score = (0 .. num_classes)
+            .filter(|i| problem.class_enabled(*i))
+            .map(|i| problem.aggregate_decision_value(i))
+            .fold(std::f64::NEG_INFINITY, f64::max);
+        let mut sum = 0.0;
+
+        for i in 0 .. num_classes {
+            let exp = if problem.class_enabled(i) { ((problem.aggregate_decision_value(i) - max_score) / temperature).exp() } else { 0.0 };
+            problem.probabilities.flat_mut()[i] = exp;
+            sum += exp;
+        }
+
+        for probability in problem.probabilities.flat_mut()[.. num_classes].iter_mut() {
+            *probability /= sum;
+        }
+    }
+
+    /// Returns `true` if `problem` was created for a model of the same shape as this one.
+    crate fn fits(&self, problem: &Problem<V32>) -> bool {
+        problem.num_attributes == self.num_attributes
+            && problem.num_total_sv >= self.num_total_sv
+            && problem.labels.iter().eq(self.classes.iter().map(|c| &c.label))
+    }
+
+    /// Returns `true` if the class at `index` did not win by the margin required by the reject policy.
+    crate fn rejects_margin(&self, problem: &Problem<V32>, index: usize) -> bool {
+        match self.settings.reject {
+            Reject::BelowMargin(margin) => problem.class_margin(index) < margin,
+            _ => false,
+        }
+    }
+
+    /// Returns the index of the class with the most votes, or `None` if the vote is ambiguous.
+    crate fn resolve_votes(&self, problem: &Problem<V32>) -> Option<usize> {
+        let enabled = (0 .. self.classes.len()).filter(|i| problem.class_enabled(*i));
+
+        // `max_by_key` returns the last maximum, iterating in reverse makes it the first like libSVM.
+        let highest_vote = enabled.clone().rev().max_by_key(|i| problem.vote[*i])?;
+        let max_votes = problem.vote[highest_vote];
+        let mut tied = enabled.filter(|i| problem.vote[*i] == max_votes);
+
+        match self.settings.tie_break {
+            TieBreak::FirstClass => Some(highest_vote),
+            TieBreak::LowestLabel => tied.min_by_key(|i| self.classes[*i].label),
+            TieBreak::DecisionValue => tied.max_by(|a, b| {
+                let a = problem.aggregate_decision_value(*a);
+                let b = problem.aggregate_decision_value(*b);
+
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            }),
+            TieBreak::Ambiguous => match tied.nth(1) {
+                Some(_) => None,
+                None => Some(highest_vote),
+            },
+        }
+    }
 }
 
 macro_rules! impl_common_svm {
     ($v32:ty) => {
+        /// Merges two classification models trained on disjoint sets of labels into one.
+        ///
+        /// # Description
+        ///
+        /// libSVM trains one classifier per pair of classes. If classes are added over time, a model
+        /// for the new classes can be merged with the existing one instead of retraining everything.
+        /// The classes of `other` are appended to the classes of `self`.
+        ///
+        /// The classifiers between a class of `self` and a class of `other` don't exist. They are marked
+        /// unavailable, have a decision value of `0` and don't vote. As a consequence classes of the
+        /// larger model can receive more votes, [Predict::predict_probability] is usually the better
+        /// choice for merged models. If both models have probability estimates, missing pairs
+        /// contribute a neutral pairwise probability of `0.5`.
+        ///
+        /// Both models must use the same kernel with the same parameters, the kernel and metadata of
+        /// `self` are kept. Kernels implemented outside of FFSVM are only compared by name.
+        /// Linear [DenseSVM]s need [DenseSVM::set_fold_linear] to be enabled again after merging, and
+        /// multi-label thresholds and label names need to be set again.
+        ///
+        /// # Returns
+        ///
+        /// An [Error::IncompatibleModel] if either model is no classifier, the kernels or the number of
+        /// attributes differ, or both models share a label.
+        pub fn merge(self, other: Self) -> Result<Self, Error> {
+            if self.kernel.parameters() != other.kernel.parameters() {
+                return Err(Error::IncompatibleModel);
+            }
+
+            self.merge_classes(other)
+        }
+
+        /// Reads and parses the model file at `path`.
+        ///
+        /// This is a shortcut for reading the file into a string and using `try_from`. If the
+        /// file can't be read, [Error::Io] is returned. All errors are wrapped in an
+        /// [Error::Context] naming `path`.
+        pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+            let path = path.as_ref();
+            let origin = path.display().to_string();
+
+            match std::fs::File::open(path) {
+                Ok(file) => Self::from_reader(file, &origin),
+                Err(e) => Err(Error::from(e).with_origin(&origin)),
+            }
+        }
+
+        /// Reads and parses a model from `reader`.
+        ///
+        /// All errors are wrapped in an [Error::Context] naming `origin`, which should say where
+        /// the model came from, for example the name of a bundled resource.
+        pub fn from_reader<R: std::io::Read>(mut reader: R, origin: &str) -> Result<Self, Error> {
+            let mut model = String::new();
+
+            reader.read_to_string(&mut model).map_err(|e| Error::from(e).with_origin(origin))?;
+            Self::try_from(model.as_str()).map_err(|e| e.with_origin(origin))
+        }
+
+        /// Reads the encrypted model file at `path` and decrypts it in memory with `decrypt`.
+        ///
+        /// Like [SVMCore::from_file], but the file holds the model as encrypted by the application,
+        /// see [Decrypt]. All errors are wrapped in an [Error::Context] naming `path`.
+        #[cfg(feature = "encryption")]
+        pub fn from_encrypted_file<P: AsRef<std::path::Path>>(path: P, decrypt: &dyn crate::decrypt::Decrypt) -> Result<Self, Error> {
+            let path = path.as_ref();
+            let origin = path.display().to_string();
+
+            match std::fs::File::open(path) {
+                Ok(file) => Self::from_encrypted_reader(file, &origin, decrypt),
+                Err(e) => Err(Error::from(e).with_origin(&origin)),
+            }
+        }
+
+        /// Reads an encrypted model from `reader` and decrypts it in memory with `decrypt`.
+        ///
+        /// The decrypted model is overwritten as soon as it is parsed, so it does not linger in
+        /// freed memory. All errors are wrapped in an [Error::Context] naming `origin`.
+        #[cfg(feature = "encryption")]
+        pub fn from_encrypted_reader<R: std::io::Read>(mut reader: R, origin: &str, decrypt: &dyn crate::decrypt::Decrypt) -> Result<Self, Error> {
+            let mut data = Vec::new();
+
+            reader.read_to_end(&mut data).map_err(|e| Error::from(e).with_origin(origin))?;
+
+            let model = crate::decrypt::decrypt_model(&data, decrypt).map_err(|e| e.with_origin(origin))?;
+            let svm = Self::try_from(model.as_str()).map_err(|e| e.with_origin(origin));
+
+            crate::decrypt::wipe(model.into_bytes());
+            svm
+        }
+
+        /// Reads the model file at `path` if its signature in `path` plus `.sig` is valid.
+        ///
+        /// The signature file holds the 64 byte ed25519 signature of the model file. If no
+        /// trusted key signed the model, [Error::SignatureMismatch] is returned and the model is
+        /// not parsed. All errors are wrapped in an [Error::Context] naming `path`.
+        #[cfg(feature = "signatures")]
+        pub fn from_signed_file<P: AsRef<std::path::Path>>(path: P, keys: &crate::signature::TrustedKeys) -> Result<Self, Error> {
+            let path = path.as_ref();
+            let origin = path.display().to_string();
+
+            let mut signature_path = path.as_os_str().to_owned();
+            signature_path.push(".sig");
+
+            match (std::fs::File::open(path), std::fs::read(&signature_path)) {
+                (Ok(file), Ok(signature)) => Self::from_signed_reader(file, &origin, &signature, keys),
+                (Err(e), _) | (_, Err(e)) => Err(Error::from(e).with_origin(&origin)),
+            }
+        }
+
+        /// Reads a model from `reader` if `signature` is a valid signature of it.
+        ///
+        /// If no trusted key signed the model, [Error::SignatureMismatch] is returned and the model
+        /// is not parsed. All errors are wrapped in an [Error::Context] naming `origin`.
+        #[cfg(feature = "signatures")]
+        pub fn from_signed_reader<R: std::io::Read>(mut reader: R, origin: &str, signature: &[u8], keys: &crate::signature::TrustedKeys) -> Result<Self, Error> {
+            let mut data = Vec::new();
+
+            reader.read_to_end(&mut data).map_err(|e| Error::from(e).with_origin(origin))?;
+            keys.verify(&data, signature).map_err(|e| e.with_origin(origin))?;
+
+            let model = String::from_utf8(data).map_err(|e| Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e)).with_origin(origin))?;
+            Self::try_from(model.as_str()).map_err(|e| e.with_origin(origin))
+        }
+
         /// Finds the class index for a given label.
         ///
         /// # Description
@@ -77,7 +620,7 @@ macro_rules! impl_common_svm {
         /// If the label was found its index returned in the [Option]. Otherwise `None`
         /// is returned.
         ///
-        pub fn class_index_for_label(&self, label: u32) -> Option<usize> {
+        pub fn class_index_for_label(&self, label: i64) -> Option<usize> {
             for (i, class) in self.classes.iter().enumerate() {
                 if class.label != label {
                     continue;
@@ -101,7 +644,7 @@ macro_rules! impl_common_svm {
         ///
         /// If the index was found it is returned in the [Option]. Otherwise `None`
         /// is returned.
-        pub fn class_label_for_index(&self, index: usize) -> Option<u32> {
+        pub fn class_label_for_index(&self, index: usize) -> Option<i64> {
             if index >= self.classes.len() {
                 None
             } else {
@@ -109,20 +652,207 @@ macro_rules! impl_common_svm {
             }
         }
 
-        /// Computes the kernel values for this problem
-        crate fn compute_kernel_values(&self, problem: &mut Problem<$v32>) {
-            // Get current problem and decision values array
-            let features = &problem.features;
-            let kernel_values = &mut problem.kernel_values;
+        /// Predicts all [Problem]s with probabilities in parallel, and collects the probabilities.
+        ///
+        /// Like [Predict::predict_probabilities], but also returns a [ProbabilityMatrix] with
+        /// one row per problem and one column per class, in the order of [SVMCore::labels].
+        pub fn predict_probabilities_batch(&self, problems: &mut [Problem<$v32>]) -> Result<ProbabilityMatrix, Error> {
+            self.predict_probabilities(problems)?;
 
-            // Compute kernel values per class
-            for (i, class) in self.classes.iter().enumerate() {
-                let kvalues = kernel_values.row_as_flat_mut(i);
+            Ok(ProbabilityMatrix::from_problems(self.labels().collect(), problems))
+        }
+
+        /// Predicts the value of `problem`, only considering the classes with the given `labels`.
+        ///
+        /// # Description
+        ///
+        /// If the context of a prediction rules out most classes, only the kernel values of the
+        /// support vectors of the allowed classes and the pairwise classifiers among them are
+        /// computed. For 3 allowed classes out of 40, this evaluates 3 instead of 780 classifiers.
+        /// The solution is one of the allowed `labels`, decision values of all other pairs are `0`.
+        ///
+        /// This only predicts values like [Predict::predict_value], regression models ignore `labels`.
+        /// Kernel values computed this way are incomplete, so they invalidate the [Cache] of `problem`.
+        /// To keep a restriction for many predictions, and to restrict probabilities, use
+        /// [Problem::set_enabled_labels] instead. Its mask is ignored by this method.
+        ///
+        /// # Returns
+        ///
+        /// An [Error::UnknownLabel] if any of the given labels is not part of the model.
+        pub fn predict_restricted(&self, problem: &mut Problem<$v32>, labels: &[i64]) -> Result<(), Error> {
+            if self.is_regression() {
+                return self.predict_value(problem);
+            }
+
+            let mut mask = vec![false; self.classes.len()];
+
+            for label in labels {
+                let index = self.class_index_for_label(*label).ok_or(Error::UnknownLabel(*label))?;
+                mask[index] = true;
+            }
+
+            let previous = problem.class_mask.replace(mask);
+            let result = self.predict_value(problem);
+            problem.class_mask = previous;
+
+            result
+        }
+
+        /// Computes the norm of the weight vector `||w||` of a binary classification model.
+        ///
+        /// # Description
+        ///
+        /// The decision value [Problem::margin] is `f(x) = <w, x> - rho` in the kernel's feature
+        /// space. Dividing it by `||w||` gives the geometric distance of `x` to the decision boundary,
+        /// which, unlike `f(x)`, is comparable between models.
+        ///
+        /// This evaluates the kernel for all pairs of support vectors, so it is slow for larger
+        /// models. Compute it once and keep the result.
+        ///
+        /// # Returns
+        ///
+        /// The norm, or `None` if this is not a binary classification model.
+        pub fn weight_norm(&self) -> Option<f64> {
+            match self.svm_type {
+                SVMType::CSvc | SVMType::NuSvc if self.classes.len() == 2 => {}
+                _ => return None,
+            }
+
+            let mut problem = Problem::from(self);
+            let mut norm = 0.0;
+
+            for (c, class) in self.classes.iter().enumerate() {
+                for i in 0 .. class.num_support_vectors {
+                    self.load_support_vector(&mut problem, c, i);
+                    self.recompute_kernel_values(&mut problem);
+
+                    let alpha = class.coefficients.flat()[(0, i)];
+
+                    for (other_c, other) in self.classes.iter().enumerate() {
+                        let coef = other.coefficients.flat();
+                        let kvalues = problem.kernel_values.row_as_flat(other_c);
+
+                        for k in 0 .. other.num_support_vectors {
+                            norm += alpha * coef[(0, k)] * kvalues[k];
+                        }
+                    }
+                }
+            }
+
+            Some(norm.sqrt())
+        }
+
+        /// Estimates the heap memory used by this model and by each of its problems.
+        ///
+        /// This is meant to budget memory on embedded devices, [SVMCore::stats] only reports the total.
+        pub fn memory_usage(&self) -> MemoryUsage { self.common_memory_usage(self.support_vector_bytes(), self.feature_bytes()) }
+
+        /// Reads every memory page of the support vectors and coefficients.
+        ///
+        /// # Description
+        ///
+        /// After idle periods the operating system may have swapped out or compressed pages of a
+        /// large model, and the next prediction pays for the page faults. Latency sensitive
+        /// applications can call this after loading, or before predictions resume, and use
+        /// [Problem::pretouch] for the buffers of their problems. With the `mlock` feature
+        /// [SVMCore::lock_memory] also keeps the pages from being swapped out again.
+        pub fn pretouch(&self) {
+            use crate::util::Pages;
+
+            for class in &self.classes {
+                class.support_vectors.touch();
+                class.coefficients.touch();
+                class.prepared.touch();
+            }
 
-                self.kernel.compute(&class.support_vectors, features.as_raw(), kvalues);
+            if let Some(weights) = &self.linear_weights {
+                weights.touch();
             }
         }
 
+        /// Locks the support vectors and coefficients into RAM, so they are never swapped out.
+        ///
+        /// Requires the `mlock` feature and is only supported on Unix. Locked pages count towards
+        /// `RLIMIT_MEMLOCK`, and the allocator may keep them locked after the model was dropped.
+        ///
+        /// # Returns
+        ///
+        /// An [Error::Io] if the memory could not be locked, for example if the limit was reached.
+        #[cfg(feature = "mlock")]
+        pub fn lock_memory(&self) -> Result<(), Error> {
+            use crate::util::Pages;
+
+            for class in &self.classes {
+                class.support_vectors.lock()?;
+                class.coefficients.lock()?;
+                class.prepared.lock()?;
+            }
+
+            if let Some(weights) = &self.linear_weights {
+                weights.lock()?;
+            }
+
+            Ok(())
+        }
+
+        /// Returns statistics about this model, for example for capacity planning or telemetry.
+        ///
+        /// The heap usage is an estimate. It includes the SIMD padding of support vectors and
+        /// coefficients, but not allocator overhead or the [Problem]s created for this model.
+        pub fn stats(&self) -> Stats {
+            Stats {
+                total_support_vectors: self.num_total_sv,
+                support_vectors: self.classes.iter().map(|c| (c.label, c.num_support_vectors)).collect(),
+                attributes: self.num_attributes,
+                svm_type: self.svm_type,
+                kernel: self.kernel.parameters(),
+                probabilities: self.probabilities.is_some(),
+                density: self.support_vector_density(),
+                heap_bytes: self.memory_usage().model(),
+            }
+        }
+
+        /// Based on decision values, computes the calibrated probabilities using the model's `probA` and `probB`.
+        crate fn compute_calibrated_probabilities(&self, problem: &mut Problem<$v32>, probabilities: &Probabilities) -> Result<(), Error> {
+            const MIN_PROB: f64 = 1e-7;
+
+            let num_classes = self.classes.len();
+            let temperature = self.settings.temperature;
+            let mut pairwise = problem.pairwise.flat_mut();
+
+            // Now compute probability values
+            for i in 0 .. num_classes {
+                for j in i + 1 .. num_classes {
+                    let decision_value = problem.decision_values[(i, j)];
+                    let a = probabilities.a[(i, j)] / temperature;
+                    let b = probabilities.b[(i, j)] / temperature;
+
+                    let sigmoid = sigmoid_predict(decision_value, a, b).max(MIN_PROB).min(1f64 - MIN_PROB);
+
+                    pairwise[(i, j)] = sigmoid;
+                    pairwise[(j, i)] = 1f64 - sigmoid;
+                }
+            }
+
+            let class_mask = &problem.class_mask;
+            let mut enabled = (0 .. num_classes).filter(|i| class_mask.as_ref().map_or(true, |mask| mask[*i]));
+            let problem_probabilities = problem.probabilities.flat_mut();
+
+            // Classes excluded from the prediction are impossible, the others are coupled among themselves.
+            set_all(&mut problem_probabilities[.. num_classes], 0.0);
+
+            match (enabled.next(), enabled.next(), enabled.next()) {
+                (Some(i), None, _) => problem_probabilities[i] = 1.0,
+                (Some(i), Some(j), None) => {
+                    problem_probabilities[i] = pairwise[(i, j)];
+                    problem_probabilities[j] = pairwise[(j, i)];
+                }
+                (None, ..) => {}
+                _ => self.compute_multiclass_probabilities(problem)?,
+            }
+
+            Ok(())
+        }
 
         // This is pretty much copy-paste of `multiclass_probability` from libSVM which we need
         // to be compatibly for predicting probability for multiclass SVMs. The method is in turn
@@ -130,27 +860,30 @@ macro_rules! impl_common_svm {
         // Classification by Pairwise Coupling", Journal of Machine Learning Research 5 (2004) 975-1005,
         // by Ting-Fan Wu, Chih-Jen Lin and Ruby C. Weng.
         crate fn compute_multiclass_probabilities(&self, problem: &mut Problem<$v32>) -> Result<(), Error> {
-            let num_classes = self.classes.len();
-            let max_iter = 100.max(num_classes);
+            // Classes excluded from the prediction are left out of the coupling, their probabilities stay `0`.
+            let class_mask = &problem.class_mask;
+            let classes = (0 .. self.classes.len()).filter(|i| class_mask.as_ref().map_or(true, |mask| mask[*i]));
+            let num_classes = classes.clone().count();
+            let max_iter = self.settings.coupling_max_iterations.unwrap_or_else(|| 100.max(num_classes));
             let mut q = problem.q.flat_mut();
             let qp = &mut problem.qp;
-            let eps = 0.005 / num_classes as f64; // Magic number .005 comes from libSVM.
+            let eps = self.settings.coupling_epsilon.unwrap_or(0.005 / num_classes as f64); // Magic number .005 comes from libSVM.
             let pairwise = problem.pairwise.flat();
             let probabilities = problem.probabilities.flat_mut();
 
             // We first build up matrix Q as defined in (14) in the paper above. Q should have
             // the property of being a transition matrix for a Markov Chain.
-            for t in 0 .. num_classes {
+            for t in classes.clone() {
                 probabilities[t] = 1.0 / num_classes as f64;
 
                 q[(t, t)] = 0.0;
 
-                for j in 0 .. t {
+                for j in classes.clone().take_while(|j| *j < t) {
                     q[(t, t)] += pairwise[(j, t)] * pairwise[(j, t)];
                     q[(t, j)] = q[(j, t)];
                 }
 
-                for j in t + 1 .. num_classes {
+                for j in classes.clone().skip_while(|j| *j <= t) {
                     q[(t, t)] += pairwise[(j, t)] * pairwise[(j, t)];
                     q[(t, j)] = -pairwise[(j, t)] * pairwise[(t, j)];
                 }
@@ -160,10 +893,10 @@ macro_rules! impl_common_svm {
             for i in 0 ..= max_iter {
                 let mut pqp = 0.0;
 
-                for t in 0 .. num_classes {
+                for t in classes.clone() {
                     qp[t] = 0.0;
 
-                    for j in 0 .. num_classes {
+                    for j in classes.clone() {
                         qp[t] += q[(t, j)] * probabilities[j];
                     }
 
@@ -174,14 +907,18 @@ macro_rules! impl_common_svm {
                 // to (21).
                 let mut max_error = 0.0;
 
-                for item in qp.iter() {
-                    let error = (*item - pqp).abs();
+                for item in classes.clone().map(|t| qp[t]) {
+                    let error = (item - pqp).abs();
 
                     if error > max_error {
                         max_error = error;
                     }
                 }
 
+                if let Some(stats) = &mut problem.stats {
+                    stats.probability_iterations = i;
+                }
+
                 if max_error < eps {
                     break;
                 }
@@ -189,17 +926,17 @@ macro_rules! impl_common_svm {
                 // In case we are on the last iteration round past the threshold
                 // we know something went wrong. Signal we exceeded the threshold.
                 if i == max_iter {
-                    return Err(Error::IterationsExceeded);
+                    return Err(Error::IterationsExceeded { residual: max_error });
                 }
 
                 // This seems to be the main function performing (23) and (24).
-                for t in 0 .. num_classes {
+                for t in classes.clone() {
                     let diff = (-qp[t] + pqp) / q[(t, t)];
 
                     probabilities[t] += diff;
                     pqp = (pqp + diff * (diff * q[(t, t)] + 2.0 * qp[t])) / (1.0 + diff) / (1.0 + diff);
 
-                    for j in 0 .. num_classes {
+                    for j in classes.clone() {
                         qp[j] = (qp[j] + diff * q[(t, j)]) / (1.0 + diff);
                         probabilities[j] /= 1.0 + diff;
                     }
@@ -235,16 +972,31 @@ macro_rules! impl_common_svm {
 
             for i in 0 .. self.classes.len() {
                 for j in (i + 1) .. self.classes.len() {
-                    let sv_coef0 = self.classes[i].coefficients.row(j - 1);
-                    let sv_coef1 = self.classes[j].coefficients.row(i);
+                    // Classifiers missing in merged models neither have a decision value nor vote,
+                    // and neither do classifiers involving a class excluded from this prediction.
+                    let available = self.pairs.as_ref().map_or(true, |pairs| pairs[(i, j)]);
+
+                    if !available || !problem.class_enabled(i) || !problem.class_enabled(j) {
+                        problem.decision_values[(i, j)] = 0.0;
+                        continue;
+                    }
+
+                    let sum = match self.folded_decision_value(problem, i, j) {
+                        Some(sum) => sum,
+                        None => {
+                            let sv_coef0 = self.classes[i].coefficients.row(j - 1);
+                            let sv_coef1 = self.classes[j].coefficients.row(i);
 
-                    let kvalues0 = problem.kernel_values.row(i);
-                    let kvalues1 = problem.kernel_values.row(j);
+                            let kvalues0 = problem.kernel_values.row(i);
+                            let kvalues1 = problem.kernel_values.row(j);
 
-                    let sum0 = sv_coef0.iter().zip(kvalues0).map(|(a, b)| (*a * *b).sum()).sum::<f64>();
-                    let sum1 = sv_coef1.iter().zip(kvalues1).map(|(a, b)| (*a * *b).sum()).sum::<f64>();
+                            let sum0 = self.sum_products(sv_coef0, kvalues0);
+                            let sum1 = self.sum_products(sv_coef1, kvalues1);
+
+                            sum0 + sum1 - self.rho[(i, j)]
+                        }
+                    };
 
-                    let sum = sum0 + sum1 - self.rho[(i, j)];
                     let index_to_vote = if sum > 0.0 { i } else { j };
 
                     problem.decision_values[(i, j)] = sum;
@@ -255,17 +1007,38 @@ macro_rules! impl_common_svm {
 
         /// Based on kernel values, computes the decision values for this problem.
         crate fn compute_regression_values(&self, problem: &mut Problem<$v32>) {
-            let class = &self.classes[0];
-            let coef = class.coefficients.row(0);
-            let kvalues = problem.kernel_values.row(0);
+            let sum = match self.folded_decision_value(problem, 0, 1) {
+                Some(sum) => sum,
+                None => {
+                    let class = &self.classes[0];
+                    let coef = class.coefficients.row(0);
+                    let kvalues = problem.kernel_values.row(0);
 
-            let mut sum = coef.iter().zip(kvalues).map(|(a, b)| (*a * *b).sum()).sum::<f64>();
+                    let sum = self.sum_products(coef, kvalues);
 
-            sum -= self.rho[0];
+                    sum - self.rho[0]
+                }
+            };
 
             problem.result = Solution::Value(sum as f32);
         }
 
+        /// Based on kernel values, computes the decision values and the solution for this problem.
+        crate fn compute_solution(&self, problem: &mut Problem<$v32>) {
+            match self.svm_type {
+                SVMType::CSvc | SVMType::NuSvc => {
+                    self.compute_classification_values(problem);
+
+                    // Compute highest vote
+                    problem.result = match self.resolve_votes(problem) {
+                        Some(highest_vote) if self.rejects_margin(problem, highest_vote) => Solution::Rejected,
+                        Some(highest_vote) => Solution::Label(self.classes[highest_vote].label),
+                        None => Solution::Ambiguous,
+                    };
+                }
+                SVMType::ESvr | SVMType::NuSvr => self.compute_regression_values(problem),
+            }
+        }
 
     };
 }
@@ -274,84 +1047,118 @@ macro_rules! impl_common_predict {
     ($v32:ty) => {
 
         fn predict_probability(&self, problem: &mut Problem<$v32>) -> Result<(), Error> {
-            match self.svm_type {
-                SVMType::CSvc | SVMType::NuSvc => {
-                    const MIN_PROB: f64 = 1e-7;
+            if self.is_regression() && problem.class_mask.is_some() {
+                let mask = problem.class_mask.take();
+                let result = self.predict_probability(problem);
+                problem.class_mask = mask;
+                return result;
+            }
+
+            let start = self.observation_start();
+            let mut watch = self.stats_start(problem);
 
+            let has_probabilities = match self.svm_type {
+                SVMType::CSvc | SVMType::NuSvc => {
                     // Ensure we have probabilities set. If not, somebody used us the wrong way
-                    if self.probabilities.is_none() {
+                    if self.probabilities.is_none() && !self.settings.pseudo_probabilities {
                         return Err(Error::NoProbabilities);
                     }
 
-                    let num_classes = self.classes.len();
-                    let probabilities = self.probabilities.as_ref().unwrap();
-
                     // First we need to predict the problem for our decision values
-                    self.predict_value(problem)?;
-
-                    let mut pairwise = problem.pairwise.flat_mut();
-
-                    // Now compute probability values
-                    for i in 0 .. num_classes {
-                        for j in i + 1 .. num_classes {
-                            let decision_value = problem.decision_values[(i, j)];
-                            let a = probabilities.a[(i, j)];
-                            let b = probabilities.b[(i, j)];
+                    self.compute_kernel_values(problem);
+                    watch.lap(&mut problem.stats, |s| &mut s.kernel);
+                    self.compute_solution(problem);
+                    watch.lap(&mut problem.stats, |s| &mut s.decision);
 
-                            let sigmoid = sigmoid_predict(decision_value, a, b).max(MIN_PROB).min(1f64 - MIN_PROB);
+                    trace_span!(TRACE, "ffsvm::probabilities", classes = self.classes.len());
 
-                            pairwise[(i, j)] = sigmoid;
-                            pairwise[(j, i)] = 1f64 - sigmoid;
+                    match &self.probabilities {
+                        Some(probabilities) => {
+                            self.compute_calibrated_probabilities(problem, probabilities)?;
+                            problem.probability_kind = ProbabilityKind::Calibrated;
+                        }
+                        None => {
+                            self.compute_pseudo_probabilities(problem);
+                            problem.probability_kind = ProbabilityKind::Pseudo;
                         }
                     }
 
-                    let problem_probabilities = problem.probabilities.flat_mut();
+                    if let Some(thresholds) = &self.settings.multi_label {
+                        problem.multi_label.clear();
+
+                        for (i, threshold) in thresholds.iter().enumerate() {
+                            let probability = problem.probabilities.flat()[i];
+
+                            if probability > *threshold {
+                                problem.multi_label.push((self.classes[i].label, probability));
+                            }
+                        }
 
-                    if num_classes == 2 {
-                        problem_probabilities[0] = pairwise[(0, 1)];
-                        problem_probabilities[1] = pairwise[(1, 0)];
+                        problem.result = Solution::MultiLabel;
                     } else {
-                        self.compute_multiclass_probabilities(problem)?;
-                    }
+                        let max_index = find_max_index(problem.probabilities.flat());
+                        let rejected = match self.settings.reject {
+                            Reject::BelowProbability(probability) => problem.probabilities.flat()[max_index] < probability,
+                            _ => self.rejects_margin(problem, max_index),
+                        };
 
-                    let max_index = find_max_index(problem.probabilities.flat());
-                    problem.result = Solution::Label(self.classes[max_index].label);
+                        problem.result = if rejected { Solution::Rejected } else { Solution::Label(self.classes[max_index].label) };
+                    }
 
-                    Ok(())
+                    watch.lap(&mut problem.stats, |s| &mut s.probability);
+                    true
                 }
                 // This fallback behavior is mandated by `libSVM`.
-                SVMType::ESvr | SVMType::NuSvr => self.predict_value(problem),
-            }
+                SVMType::ESvr | SVMType::NuSvr => {
+                    self.compute_kernel_values(problem);
+                    watch.lap(&mut problem.stats, |s| &mut s.kernel);
+                    self.compute_solution(problem);
+                    watch.lap(&mut problem.stats, |s| &mut s.decision);
+                    false
+                }
+            };
+
+            self.observe(problem, start, has_probabilities);
+
+            Ok(())
         }
 
 
         // Predict the value for one problem.
         fn predict_value(&self, problem: &mut Problem<$v32>) -> Result<(), Error> {
-            match self.svm_type {
-                SVMType::CSvc | SVMType::NuSvc => {
-                    // Compute kernel, decision values and eventually the label
-                    self.compute_kernel_values(problem);
-                    self.compute_classification_values(problem);
+            // Regression models ignore the class mask.
+            if self.is_regression() && problem.class_mask.is_some() {
+                let mask = problem.class_mask.take();
+                let result = self.predict_value(problem);
+                problem.class_mask = mask;
+                return result;
+            }
 
-                    // Compute highest vote
-                    let highest_vote = find_max_index(&problem.vote);
-                    problem.result = Solution::Label(self.classes[highest_vote].label);
+            let start = self.observation_start();
+            let mut watch = self.stats_start(problem);
 
-                    Ok(())
-                }
-                SVMType::ESvr | SVMType::NuSvr => {
-                    self.compute_kernel_values(problem);
-                    self.compute_regression_values(problem);
-                    Ok(())
-                }
-            }
+            // Compute kernel, decision values and eventually the label
+            self.compute_kernel_values(problem);
+            watch.lap(&mut problem.stats, |s| &mut s.kernel);
+            self.compute_solution(problem);
+            watch.lap(&mut problem.stats, |s| &mut s.decision);
+
+            self.observe(problem, start, false);
+
+            Ok(())
+        }
+
+        fn problem(&self) -> Problem<$v32> { Problem::from(self) }
+
+        fn predict_probabilities(&self, problems: &mut [Problem<$v32>]) -> Result<(), Error> {
+            self.settings.threads.run(problems, self.min_parallel_batch(), |problem| self.predict_probability(problem))
         }
 
     }
 }
 
 macro_rules! prepare_svm {
-    ($raw_model:expr, $k:ty, $m32:ty) => {
+    ($raw_model:expr, $num_vectors:expr, $kernel:expr, $k:ty, $m32:ty) => {
         // To quickly check what broke again during parsing ...
         // println!("{:?}", raw_model);
         {
@@ -359,7 +1166,7 @@ macro_rules! prepare_svm {
             let vectors = &$raw_model.vectors;
 
             // Get basic info
-            let num_attributes = vectors[0].features.len();
+            let num_attributes = vectors.first().ok_or(Error::MissingSection("SV"))?.features.len();
             let num_total_sv = header.total_sv as usize;
 
             let svm_type = match $raw_model.header.svm_type {
@@ -367,15 +1174,26 @@ macro_rules! prepare_svm {
                 "nu_svc" => SVMType::NuSvc,
                 "epsilon_svr" => SVMType::ESvr,
                 "nu_svr" => SVMType::NuSvr,
-                _ => unimplemented!(),
+                other => {
+                    return Err(Error::UnsupportedSection {
+                        section: "svm_type",
+                        value: other.to_owned(),
+                    })
+                }
             };
 
-            let kernel: Box<$k> = match $raw_model.header.kernel_type {
-                "rbf" => Box::new(Rbf::try_from($raw_model)?),
-                "linear" => Box::new(Linear::from($raw_model)),
-                "polynomial" => Box::new(Poly::try_from($raw_model)?),
-                "sigmoid" => Box::new(Sigmoid::try_from($raw_model)?),
-                _ => unimplemented!(),
+            let kernel: Box<$k> = match ($kernel, $raw_model.header.kernel_type) {
+                (Some(kernel), _) => kernel,
+                (None, "rbf") => Box::new(Rbf::try_from($raw_model)?),
+                (None, "linear") => Box::new(Linear::from($raw_model)),
+                (None, "polynomial") => Box::new(Poly::try_from($raw_model)?),
+                (None, "sigmoid") => Box::new(Sigmoid::try_from($raw_model)?),
+                (None, other) => {
+                    return Err(Error::UnsupportedSection {
+                        section: "kernel_type",
+                        value: other.to_owned(),
+                    })
+                }
             };
 
             let num_classes = match svm_type {
@@ -392,6 +1210,32 @@ macro_rules! prepare_svm {
                 SVMType::ESvr | SVMType::NuSvr => vec![num_total_sv as u32],
             };
 
+            // Sections that would otherwise be read out of bounds below.
+            let num_pairs = num_classes * num_classes.saturating_sub(1) / 2;
+            let num_labels = match svm_type {
+                SVMType::CSvc | SVMType::NuSvc => header.label.len(),
+                SVMType::ESvr | SVMType::NuSvr => num_classes,
+            };
+
+            // SVRs have a single `probA`, like their single `rho`.
+            let num_probabilities = |values: &Option<Vec<f64>>| values.as_ref().map_or(num_pairs.max(1), |v| v.len());
+
+            let counts = [
+                ("label", num_classes, num_labels),
+                ("nr_sv", num_classes, nr_sv.len()),
+                ("rho", num_pairs.max(1), header.rho.len()),
+                ("probA", num_pairs.max(1), num_probabilities(&header.prob_a)),
+                ("probB", num_pairs.max(1), num_probabilities(&header.prob_b)),
+                ("SV", num_total_sv, $num_vectors),
+                ("total_sv", nr_sv.iter().map(|n| *n as usize).sum(), num_total_sv),
+            ];
+
+            for (section, expected, found) in counts.iter().cloned() {
+                if expected != found {
+                    return Err(Error::CountMismatch { section, expected, found });
+                }
+            }
+
             // Construct vector of classes
             let classes = match svm_type {
                 // TODO: CLEAN THIS UP ... We can probably unify the logic
@@ -429,6 +1273,17 @@ macro_rules! prepare_svm {
                     svm_type,
                     rho: Triangular::from(&header.rho),
                     classes,
+                    settings: Settings {
+                        label_names: if header.label_names.is_empty() {
+                            None
+                        } else {
+                            Some(header.label_names.iter().map(|name| Some(name.to_string())).collect())
+                        },
+                        ..Settings::default()
+                    },
+                    linear_weights: None,
+                    pairs: None,
+                    metadata: header.metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
                     phantom_v32: PhantomData,
                     phantom_v64: PhantomData,
                 },
@@ -439,5 +1294,7 @@ macro_rules! prepare_svm {
 }
 
 // We do late include here to capture our macros above ...
+mod convert;
 mod dense;
+mod merge;
 mod sparse;
@@ -4,13 +4,16 @@ use std::{convert::TryFrom, marker::PhantomData};
 
 use crate::{
     errors::Error,
-    parser::ModelFile,
+    parser::{Allocation, Limits, ModelFile, SupportVector},
     svm::{
+        batch::ProbabilityMatrix,
         class::Class,
         core::SVMCore,
         kernel::{KernelSparse, Linear, Poly, Rbf, Sigmoid},
         predict::Predict,
-        problem::{Problem, Solution},
+        problem::{ProbabilityKind, Problem, Solution, SparseProblem},
+        settings::Reject,
+        stats::{density, MemoryUsage, Stats},
         Probabilities, SVMType, SparseSVM,
     },
     util::{find_max_index, set_all, sigmoid_predict},
@@ -19,10 +22,89 @@ use crate::{
 
 impl SparseSVM {
     impl_common_svm!(SparseVector<f32>);
+
+    /// Replaces the kernel, for example by a [Composite] kernel.
+    ///
+    /// Kernel values cached in problems predicted before are stale, use [Problem::invalidate_cache].
+    pub fn set_kernel(&mut self, kernel: Box<dyn KernelSparse>) { self.kernel = kernel; }
+
+    /// Sets the features of `problem` to the support vector `index` of the class `class`.
+    crate fn load_support_vector(&self, problem: &mut SparseProblem, class: usize, index: usize) {
+        problem.clear();
+
+        for (i, value) in self.classes[class].support_vectors.row(index).iter() {
+            problem.features[i as usize] = value;
+        }
+    }
+
+    /// Returns the heap bytes used by all support vectors.
+    crate fn support_vector_bytes(&self) -> usize { self.classes.iter().map(|c| c.support_vectors.heap_bytes()).sum() }
+
+    /// Returns the ratio of non-zero attributes over all support vectors, where the number of
+    /// attributes is given by the highest attribute index of any support vector.
+    crate fn support_vector_density(&self) -> f32 {
+        let mut num_attributes = 0;
+        let mut num_non_zero = 0;
+
+        for class in &self.classes {
+            for sv in class.support_vectors.row_iter() {
+                for (index, value) in sv.iter() {
+                    num_attributes = num_attributes.max(index as usize + 1);
+
+                    if value != 0.0 {
+                        num_non_zero += 1;
+                    }
+                }
+            }
+        }
+
+        density(num_non_zero, self.num_total_sv, num_attributes)
+    }
+
+    /// Returns the heap bytes used by the features of a new problem, which grow once features are set.
+    crate fn feature_bytes(&self) -> usize { 0 }
+
+    /// Sparse models never fold linear weights, see [DenseSVM::set_fold_linear].
+    crate fn folded_decision_value(&self, _problem: &SparseProblem, _i: usize, _j: usize) -> Option<f64> { None }
+
+    /// Computes the kernel values for this problem
+    crate fn compute_kernel_values(&self, problem: &mut SparseProblem) {
+        // Kernel values of excluded classes are skipped, so they must neither be reused nor cached.
+        if problem.class_mask.is_some() {
+            problem.invalidate_cache();
+        } else if problem.kernel_values_cached() {
+            return;
+        }
+
+        trace_span!(TRACE, "ffsvm::kernel", total_sv = self.num_total_sv, attributes = self.num_attributes);
+        self.recompute_kernel_values(problem);
+    }
+
+    /// Computes the kernel values for this problem, ignoring any cache.
+    crate fn recompute_kernel_values(&self, problem: &mut SparseProblem) {
+        // Get current problem and decision values array
+        let features = &problem.features;
+        let kernel_values = &mut problem.kernel_values;
+        let class_mask = &problem.class_mask;
+        let enabled = |i: usize| class_mask.as_ref().map_or(true, |mask| mask[i]);
+
+        // Compute kernel values per class
+        for (i, class) in self.classes.iter().enumerate().filter(|(i, _)| enabled(*i)) {
+            let kvalues = kernel_values.row_as_flat_mut(i);
+
+            self.kernel.compute(&class.support_vectors, features.as_raw(), kvalues);
+        }
+
+        self.count_support_vectors(problem);
+    }
 }
 
 impl Predict<SparseVector<f32>, SparseVector<f64>> for SparseSVM {
     impl_common_predict!(SparseVector<f32>);
+
+    fn predict_values(&self, problems: &mut [SparseProblem]) -> Result<(), Error> {
+        self.settings.threads.run(problems, self.min_parallel_batch(), |problem| self.predict_value(problem))
+    }
 }
 
 impl<'a, 'b> TryFrom<&'a str> for SparseSVM {
@@ -37,39 +119,251 @@ impl<'a, 'b> TryFrom<&'a str> for SparseSVM {
 impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for SparseSVM {
     type Error = Error;
 
-    fn try_from(raw_model: &'a ModelFile<'_>) -> Result<SparseSVM, Error> {
-        let (mut svm, nr_sv) = prepare_svm!(raw_model, dyn KernelSparse, SparseMatrix<f32>);
+    fn try_from(raw_model: &'a ModelFile<'_>) -> Result<SparseSVM, Error> { SparseSVM::load(raw_model, None) }
+}
 
-        let vectors = &raw_model.vectors;
+impl SparseSVM {
+    /// Creates a SVM from `raw_model`, using the kernel `K` if the model's `kernel_type` is `kernel_type`.
+    ///
+    /// # Description
+    ///
+    /// This allows deploying kernels implemented outside of FFSVM (see [KernelSparse]) through regular
+    /// model files. The kernel is created from the model via `K::try_from`, so it can read its
+    /// parameters (e.g., `gamma`) from the header. Models with any other `kernel_type` are
+    /// loaded as usual.
+    pub fn from_custom_kernel<K>(raw_model: &ModelFile<'_>, kernel_type: &str) -> Result<SparseSVM, Error>
+    where
+        K: KernelSparse + for<'a, 'b> TryFrom<&'a ModelFile<'b>, Error = Error> + 'static,
+    {
+        let kernel = if raw_model.header.kernel_type == kernel_type {
+            Some(Box::new(K::try_from(raw_model)?) as Box<dyn KernelSparse>)
+        } else {
+            None
+        };
 
-        // Things down here are a bit ugly as the file format is a bit ugly ...
-        // Now read all vectors and decode stored information
-        let mut start_offset = 0;
+        SparseSVM::load(raw_model, kernel)
+    }
 
-        // In the raw file, support vectors are grouped by class
-        for (i, num_sv_per_class) in nr_sv.iter().enumerate() {
-            let stop_offset = start_offset + *num_sv_per_class as usize;
-
-            // Set support vector and coefficients
-            for (i_vector, vector) in vectors[start_offset .. stop_offset].iter().enumerate() {
-                // Set support vectors
-                for attribute in &vector.features {
-                    let support_vectors = &mut svm.classes[i].support_vectors;
-                    support_vectors[(i_vector, attribute.index as usize)] = attribute.value;
-                }
+    /// Creates a SVM from the model text `input`, enforcing `limits` and allocating as `allocation` says.
+    ///
+    /// # Description
+    ///
+    /// With [Allocation::Eager] this is `ModelFile::parse_with_limits` followed by `try_from`.
+    /// With [Allocation::Chunked] support vectors are converted as they are parsed, so the
+    /// parsed model never exists in memory as a whole, see [Allocation].
+    pub fn from_str_with(input: &str, limits: &Limits, allocation: Allocation) -> Result<SparseSVM, Error> {
+        let lines = match allocation {
+            Allocation::Eager => return SparseSVM::try_from(&ModelFile::parse_with_limits(input, limits)?),
+            Allocation::Chunked(lines) => lines,
+        };
 
-                // Set coefficients
-                for (i_coefficient, coefficient) in vector.coefs.iter().enumerate() {
-                    let mut coefficients = svm.classes[i].coefficients.flat_mut();
-                    coefficients[(i_coefficient, i_vector)] = f64::from(*coefficient);
-                }
+        trace_span!(DEBUG, "ffsvm::load", kind = "sparse", lines);
+
+        let mut loaded = None;
+        let mut num_loaded = 0;
+
+        let header = ModelFile::parse_chunked(input, limits, lines, |chunk| {
+            if loaded.is_none() {
+                loaded = Some(prepare_svm!(chunk, chunk.header.total_sv as usize, None::<Box<dyn KernelSparse>>, dyn KernelSparse, SparseMatrix<f32>));
+            }
+
+            if let Some((svm, nr_sv)) = &mut loaded {
+                svm.set_vectors(nr_sv, num_loaded, &chunk.vectors)?;
             }
 
-            // Update last offset.
-            start_offset = stop_offset;
+            num_loaded += chunk.vectors.len();
+            Ok(())
+        })?;
+
+        let (mut svm, _) = loaded.ok_or(Error::MissingSection("SV"))?;
+
+        if num_loaded != svm.num_total_sv {
+            return Err(Error::CountMismatch {
+                section: "SV",
+                expected: svm.num_total_sv,
+                found: num_loaded,
+            });
         }
 
+        // Metadata follow the support vectors, so chunks didn't have them.
+        svm.metadata = header.metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        Ok(svm)
+    }
+
+    /// Creates a SVM from `raw_model`, using `kernel` instead of the model's `kernel_type` if given.
+    fn load(raw_model: &ModelFile<'_>, kernel: Option<Box<dyn KernelSparse>>) -> Result<SparseSVM, Error> {
+        trace_span!(DEBUG, "ffsvm::load", kind = "sparse", total_sv = raw_model.header.total_sv, classes = raw_model.header.nr_class);
+
+        let (mut svm, nr_sv) = prepare_svm!(raw_model, raw_model.vectors.len(), kernel, dyn KernelSparse, SparseMatrix<f32>);
+
+        svm.set_vectors(&nr_sv, 0, &raw_model.vectors)?;
+
         // Return what we have
         Result::Ok(svm)
     }
+
+    /// Sets the support vectors and coefficients of `vectors`, the first of which is support vector
+    /// `first` of the model file, which has `nr_sv` support vectors per class.
+    fn set_vectors(&mut self, nr_sv: &[u32], first: usize, vectors: &[SupportVector]) -> Result<(), Error> {
+        let num_coefficients = self.classes.len().max(2) - 1;
+
+        if first + vectors.len() > self.num_total_sv {
+            return Err(Error::CountMismatch {
+                section: "SV",
+                expected: self.num_total_sv,
+                found: first + vectors.len(),
+            });
+        }
+
+        // In the raw file, support vectors are grouped by class
+        let mut class = 0;
+        let mut class_offset = 0;
+
+        for (i, vector) in vectors.iter().enumerate() {
+            while first + i >= class_offset + nr_sv[class] as usize {
+                class_offset += nr_sv[class] as usize;
+                class += 1;
+            }
+
+            let i_vector = first + i - class_offset;
+
+            if vector.coefs.len() != num_coefficients {
+                return Err(Error::CountMismatch {
+                    section: "coefficients",
+                    expected: num_coefficients,
+                    found: vector.coefs.len(),
+                });
+            }
+
+            // Set support vectors
+            for attribute in &vector.features {
+                let support_vectors = &mut self.classes[class].support_vectors;
+                support_vectors[(i_vector, attribute.index as usize)] = attribute.value;
+            }
+
+            // Set coefficients
+            for (i_coefficient, coefficient) in vector.coefs.iter().enumerate() {
+                let mut coefficients = self.classes[class].coefficients.flat_mut();
+                coefficients[(i_coefficient, i_vector)] = f64::from(*coefficient);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn predict_values_batch() -> Result<(), Error> {
+        let mut svm = SparseSVM::try_from(SAMPLE_MODEL)?;
+
+        for threads in &[None, Some(1), Some(3)] {
+            svm.set_threads(*threads)?;
+
+            let mut problems = (0 .. 16).map(|_| Problem::from(&svm)).collect::<Vec<_>>();
+
+            for (i, problem) in problems.iter_mut().enumerate() {
+                set_sample_features(problem);
+                problem.features()[0] -= i as f32 * 0.25;
+            }
+
+            svm.predict_values(&mut problems)?;
+
+            // Each problem of the batch must get its own solution, however they are split up.
+            for problem in &problems {
+                let mut single = problem.clone();
+                svm.predict_value(&mut single)?;
+
+                assert_eq!(problem.solution(), single.solution());
+                assert_eq!(problem.margin(), single.margin());
+            }
+
+            assert_ne!(problems[0].margin(), problems[15].margin());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn thread_pool() -> Result<(), Error> {
+        let pool = std::sync::Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build()?);
+        let mut a = SparseSVM::try_from(SAMPLE_MODEL)?;
+        let mut b = SparseSVM::try_from(SAMPLE_MODEL)?;
+
+        a.set_thread_pool(pool.clone());
+        b.set_thread_pool(pool);
+
+        assert_eq!(a.threads(), Some(2));
+        assert_eq!(b.threads(), Some(2));
+
+        let mut problems = (0 .. 8).map(|_| Problem::from(&a)).collect::<Vec<_>>();
+
+        a.predict_values(&mut problems)?;
+        b.predict_values(&mut problems)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn min_parallel_batch() -> Result<(), Error> {
+        let mut svm = SparseSVM::try_from(SAMPLE_MODEL)?;
+
+        // The sample model is tiny, so only large batches are worth running in parallel.
+        assert_eq!(svm.min_parallel_batch(), 64);
+
+        svm.set_min_parallel_batch(Some(4));
+        assert_eq!(svm.min_parallel_batch(), 4);
+
+        for len in &[3, 4] {
+            let mut problems = (0 .. *len).map(|_| Problem::from(&svm)).collect::<Vec<_>>();
+            svm.predict_values(&mut problems)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn predict_probabilities_batch() -> Result<(), Error> {
+        let mut svm = SparseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_pseudo_probabilities(true);
+
+        let mut problems = (0 .. 4).map(|_| Problem::from(&svm)).collect::<Vec<_>>();
+
+        for problem in problems.iter_mut() {
+            set_sample_features(problem);
+        }
+
+        let matrix = svm.predict_probabilities_batch(&mut problems)?;
+
+        assert_eq!(matrix.rows(), 4);
+        assert_eq!(matrix.labels(), &[21, 42]);
+        assert_eq!(matrix.row(3), &problems[3].probabilities()[.. 2]);
+        assert_eq!(matrix.get(0, 42), Some(matrix[(0, 1)]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_features() -> Result<(), Error> {
+        let svm = SparseSVM::try_from(SAMPLE_MODEL)?;
+        let mut problem = Problem::from(&svm);
+
+        problem.set_features(&[0, 1, 2, 3], &SAMPLE_FEATURES)?;
+        svm.predict_value(&mut problem)?;
+
+        assert_eq!(problem.solution(), Solution::Label(42));
+        assert!(problem.set_features(&[2, 1], &[0.5, 0.5]).is_err());
+
+        match problem.set_features(&[0, 1], &[0.5]) {
+            Err(Error::FeatureCountMismatch { indices, values }) => assert_eq!((indices, values), (2, 1)),
+            _ => panic!("Expected a `FeatureCountMismatch`."),
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,304 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+
+use crate::{
+    errors::Error,
+    svm::{
+        introspect::Introspect,
+        predict::Predict,
+        problem::{DenseProblem, ProbabilityKind, Problem, Solution},
+        DenseSVM, SVMType,
+    },
+    util::find_max_index,
+};
+
+/// How the members of an [Ensemble] are combined.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Voting {
+    /// Each member votes for its predicted label, the label with most votes wins. Ties are
+    /// resolved in favor of the class that comes first in the ensemble.
+    Majority,
+
+    /// The probabilities of all members are averaged, the label with the highest average wins.
+    /// Requires all members to predict probabilities, see [SVMCore::set_pseudo_probabilities].
+    AverageProbabilities,
+}
+
+/// A single SVM of an [Ensemble].
+struct Member {
+    svm: DenseSVM,
+
+    /// Index of the ensemble's feature for each feature of the member, if it uses a subset.
+    features: Option<Vec<usize>>,
+
+    /// Index of the ensemble's class for each class of the member.
+    classes: Vec<usize>,
+}
+
+/// Combines several [DenseSVM]s into one classifier.
+///
+/// # Description
+///
+/// Each member can be trained on its own subset of the ensemble's features. When predicting,
+/// all members predict the [Problem] on their features, and their results are combined as
+/// configured by [Voting]. All members must be classifiers with the same set of labels, but
+/// may list them in any order.
+///
+/// Problems must be created via `Problem::from(&ensemble)` **after** all members were added.
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let mut ensemble = Ensemble::new(Voting::Majority);
+///     ensemble.add(DenseSVM::try_from(SAMPLE_MODEL)?, None)?;
+///     ensemble.add(DenseSVM::try_from(SAMPLE_MODEL)?, None)?;
+///
+///     let mut problem = Problem::from(&ensemble);
+///     ensemble.predict_value(&mut problem)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Ensemble {
+    members: Vec<Member>,
+    voting: Voting,
+    labels: Vec<i64>,
+    num_attributes: usize,
+}
+
+impl Ensemble {
+    /// Creates an empty ensemble combining its members by `voting`.
+    pub fn new(voting: Voting) -> Self {
+        Ensemble {
+            members: Vec::new(),
+            voting,
+            labels: Vec::new(),
+            num_attributes: 0,
+        }
+    }
+
+    /// Adds a member to the ensemble.
+    ///
+    /// If `features` is `Some`, it must hold the index of the ensemble's feature for each feature
+    /// of `svm`. Otherwise `svm` uses the ensemble's first `svm.attributes()` features.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::IncompatibleModel] if `svm` is no classifier, the number of classes differs, or
+    /// `features` does not match `svm.attributes()`. An [Error::UnknownLabel] if `svm` has a label
+    /// the other members don't have.
+    pub fn add(&mut self, svm: DenseSVM, features: Option<&[usize]>) -> Result<(), Error> {
+        match svm.svm_type {
+            SVMType::CSvc | SVMType::NuSvc => {}
+            SVMType::ESvr | SVMType::NuSvr => return Err(Error::IncompatibleModel),
+        }
+
+        if let Some(features) = features {
+            if features.len() != svm.num_attributes {
+                return Err(Error::IncompatibleModel);
+            }
+        }
+
+        if self.members.is_empty() {
+            self.labels = svm.classes.iter().map(|c| c.label).collect();
+        } else if self.labels.len() != svm.classes.len() {
+            return Err(Error::IncompatibleModel);
+        }
+
+        let classes = map_classes(&self.labels, &svm)?;
+
+        let num_attributes = match features {
+            Some(features) => features.iter().map(|f| f + 1).max().unwrap_or(0),
+            None => svm.num_attributes,
+        };
+
+        self.num_attributes = self.num_attributes.max(num_attributes);
+        self.members.push(Member {
+            svm,
+            features: features.map(|f| f.to_vec()),
+            classes,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the number of members.
+    pub fn members(&self) -> usize { self.members.len() }
+
+    /// Returns the number of features a [Problem] of this ensemble has.
+    pub fn attributes(&self) -> usize { self.num_attributes }
+
+    /// Returns the number of classes.
+    pub fn classes(&self) -> usize { self.labels.len() }
+
+    /// Returns the name of `label` given by the first member naming it, see [SVMCore::label_name].
+    pub fn label_name(&self, label: i64) -> Option<&str> { self.members.iter().filter_map(|m| m.svm.label_name(label)).next() }
+
+    /// Returns how the members are combined.
+    pub fn voting(&self) -> Voting { self.voting }
+
+    /// Sets how the members are combined.
+    pub fn set_voting(&mut self, voting: Voting) { self.voting = voting; }
+
+    /// Predicts `problem` with all members and combines their results.
+    ///
+    /// Fails with [Error::IncompatibleModel] if the ensemble has no members, or `problem` was not
+    /// created for its current members.
+    fn predict(&self, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        if self.members.is_empty() || problem.members.len() != self.members.len() {
+            return Err(Error::IncompatibleModel);
+        }
+
+        let with_probabilities = with_probabilities || self.voting == Voting::AverageProbabilities;
+        let mut calibrated = true;
+
+        for v in problem.vote.iter_mut() {
+            *v = 0;
+        }
+
+        for p in problem.probabilities.flat_mut() {
+            *p = 0.0;
+        }
+
+        for (member, member_problem) in self.members.iter().zip(problem.members.iter_mut()) {
+            load_features(problem.features.as_raw().flat(), member_problem, member.features.as_ref().map(|f| f.as_slice()));
+
+            if with_probabilities {
+                member.svm.predict_probability(member_problem)?;
+                calibrated &= member_problem.probability_kind == ProbabilityKind::Calibrated;
+
+                for (index, p) in member.classes.iter().zip(member_problem.probabilities()) {
+                    problem.probabilities.flat_mut()[*index] += p;
+                }
+            } else {
+                member.svm.predict_value(member_problem)?;
+            }
+
+            // Members that could not decide abstain from voting.
+            if let Solution::Label(label) = member_problem.solution() {
+                if let Some(index) = member.svm.class_index_for_label(label) {
+                    problem.vote[member.classes[index]] += 1;
+                }
+            }
+        }
+
+        if with_probabilities {
+            let num_members = self.members.len() as f64;
+
+            for p in problem.probabilities.flat_mut()[.. self.labels.len()].iter_mut() {
+                *p /= num_members;
+            }
+
+            problem.probability_kind = if calibrated { ProbabilityKind::Calibrated } else { ProbabilityKind::Pseudo };
+        }
+
+        problem.result = match self.voting {
+            // If all members abstained there is nothing to report.
+            Voting::Majority if problem.vote.iter().all(|v| *v == 0) => Solution::Rejected,
+            Voting::Majority => Solution::Label(self.labels[find_max_index(&problem.vote)]),
+            Voting::AverageProbabilities => Solution::Label(self.labels[find_max_index(&problem.probabilities.flat()[.. self.labels.len()])]),
+        };
+
+        Ok(())
+    }
+}
+
+/// Returns the index in `labels` for each class of `svm`.
+crate fn map_classes(labels: &[i64], svm: &DenseSVM) -> Result<Vec<usize>, Error> {
+    svm.classes
+        .iter()
+        .map(|c| labels.iter().position(|l| *l == c.label).ok_or(Error::UnknownLabel(c.label)))
+        .collect()
+}
+
+/// Copies the `features` at `indices` (or the first ones if `None`) into the features of `problem`.
+crate fn load_features(features: &[f32], problem: &mut DenseProblem, indices: Option<&[usize]>) {
+    let problem_features = problem.features().as_slice_mut();
+
+    match indices {
+        Some(indices) => {
+            for (i, index) in indices.iter().enumerate() {
+                problem_features[i] = features[*index];
+            }
+        }
+        None => problem_features.copy_from_slice(&features[.. problem_features.len()]),
+    }
+}
+
+impl Predict<SimdVector<f32s>, SimdVector<f64s>> for Ensemble {
+    fn predict_value(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, false) }
+
+    fn predict_probability(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, true) }
+
+    fn problem(&self) -> DenseProblem { DenseProblem::from(self) }
+}
+
+impl Introspect for Ensemble {
+    fn attributes(&self) -> usize { self.num_attributes }
+
+    fn classes(&self) -> usize { self.labels.len() }
+
+    fn labels(&self) -> Vec<i64> { self.labels.clone() }
+}
+
+impl<'a> From<&'a Ensemble> for DenseProblem {
+    fn from(ensemble: &Ensemble) -> Self {
+        let mut problem = Problem::<SimdVector<f32s>>::with_dimension(0, ensemble.labels.len(), ensemble.num_attributes, ensemble.labels.clone());
+        problem.members = ensemble.members.iter().map(|m| Problem::from(&m.svm)).collect();
+        problem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn agrees_with_members() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let mut ensemble = Ensemble::new(Voting::Majority);
+
+        ensemble.add(DenseSVM::try_from(SAMPLE_MODEL)?, None)?;
+        ensemble.add(DenseSVM::try_from(SAMPLE_MODEL)?, Some(&[0, 1, 2, 3]))?;
+
+        let mut problem = Problem::from(&svm);
+        let mut ensemble_problem = Problem::from(&ensemble);
+
+        set_sample_features(&mut problem);
+        set_sample_features(&mut ensemble_problem);
+
+        svm.predict_value(&mut problem)?;
+        ensemble.predict_value(&mut ensemble_problem)?;
+
+        assert_eq!(ensemble.members(), 2);
+        assert_eq!(ensemble_problem.solution(), problem.solution());
+        assert_eq!(ensemble_problem.votes_for(42), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_predictions() -> Result<(), Error> {
+        let mut ensemble = Ensemble::new(Voting::Majority);
+        let mut problem = Problem::from(&ensemble);
+
+        assert!(ensemble.predict_value(&mut problem).is_err());
+
+        let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        svm.set_reject(Reject::BelowMargin(std::f64::INFINITY));
+        ensemble.add(svm, None)?;
+
+        // Created before the member was added.
+        assert!(ensemble.predict_value(&mut problem).is_err());
+
+        let mut problem = Problem::from(&ensemble);
+        ensemble.predict_value(&mut problem)?;
+        assert_eq!(problem.solution(), Solution::Rejected);
+
+        Ok(())
+    }
+}
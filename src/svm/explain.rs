@@ -0,0 +1,66 @@
+use std::cmp::Ordering;
+
+use crate::svm::{core::SVMCore, problem::Problem};
+
+/// How much a single support vector contributed to a classification, see [SVMCore::explain].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Contribution {
+    /// Label of the class the support vector belongs to.
+    pub label: i64,
+
+    /// Index of the support vector in the model file, counting from the line after `SV`.
+    pub index: usize,
+
+    /// Kernel value between the problem and the support vector, i.e., how similar they are.
+    pub kernel_value: f64,
+
+    /// Kernel value weighted by the absolute coefficients of the support vector.
+    pub contribution: f64,
+}
+
+impl<K, M32, V32, V64> SVMCore<K, M32, V32, V64>
+where
+    K: ?Sized,
+{
+    /// Explains a classification by the support vectors that contributed most to it.
+    ///
+    /// # Description
+    ///
+    /// Each support vector is a sample of the training data. This method returns, for each class,
+    /// up to `k` support vectors with the largest contribution to the decision values of
+    /// `problem`, largest first. You can think of them as "the training samples this problem
+    /// looked most like".
+    ///
+    /// The `problem` must have been predicted with this SVM before. For linear [DenseSVM]s
+    /// this requires [DenseSVM::set_fold_linear] to be disabled.
+    pub fn explain(&self, problem: &Problem<V32>, k: usize) -> Vec<Contribution> {
+        let mut rval = Vec::new();
+        let mut offset = 0;
+
+        for (c, class) in self.classes.iter().enumerate() {
+            let kvalues = problem.kernel_values.row_as_flat(c);
+            let coefficients = class.coefficients.flat();
+            let num_coefficients = self.classes.len().max(2) - 1;
+
+            let mut contributions = (0 .. class.num_support_vectors)
+                .map(|i| {
+                    let weight = (0 .. num_coefficients).map(|j| coefficients[(j, i)].abs()).sum::<f64>();
+
+                    Contribution {
+                        label: class.label,
+                        index: offset + i,
+                        kernel_value: kvalues[i],
+                        contribution: weight * kvalues[i].abs(),
+                    }
+                }).collect::<Vec<_>>();
+
+            contributions.sort_by(|a, b| b.contribution.partial_cmp(&a.contribution).unwrap_or(Ordering::Equal));
+            contributions.truncate(k);
+
+            rval.extend(contributions);
+            offset += class.num_support_vectors;
+        }
+
+        rval
+    }
+}
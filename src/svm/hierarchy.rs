@@ -0,0 +1,305 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+
+use crate::{
+    errors::Error,
+    svm::{
+        ensemble::{load_features, map_classes},
+        introspect::Introspect,
+        manifest::Manifest,
+        predict::Predict,
+        problem::{DenseProblem, ProbabilityKind, Problem, Solution},
+        DenseSVM, SVMType,
+    },
+};
+
+/// Where a class of the coarse SVM of a [Hierarchy] leads to.
+enum Route {
+    /// The coarse label is final, and has this index in the hierarchy's labels.
+    Leaf(usize),
+
+    /// The problem is passed on to this branch.
+    Branch(usize),
+}
+
+/// A fine SVM of a [Hierarchy].
+struct Branch {
+    svm: DenseSVM,
+
+    /// Index of the hierarchy's class for each class of the branch.
+    classes: Vec<usize>,
+}
+
+/// Classifies in two levels, a coarse SVM routes problems to fine SVMs per branch.
+///
+/// # Description
+///
+/// Large taxonomies are often classified faster and more accurately by first deciding on a coarse
+/// category (e.g., "vehicle" or "animal"), and then only distinguishing the classes within that
+/// category. The coarse SVM predicts the branch, the SVM of that branch predicts the final label.
+/// Coarse labels without a branch are final labels themselves.
+///
+/// All labels of the branches must be distinct, and all SVMs use the first `svm.attributes()`
+/// features of the problem. When predicting probabilities, the probability of a label is the
+/// product of the probabilities of its branch and of the label within the branch. Only the
+/// predicted branch is evaluated, labels of all other branches have a probability of `0`.
+///
+/// # Manifest
+///
+/// Hierarchies are usually loaded via [Hierarchy::from_manifest] from a manifest in a subset of
+/// TOML like this:
+///
+/// ```text
+/// # Lines starting with `#` are comments.
+/// root = "coarse.model"
+///
+/// [[branch]]
+/// label = 1
+/// model = "vehicles.model"
+///
+/// [[branch]]
+/// label = 2
+/// model = "animals.model"
+/// ```
+///
+/// Strings are given in double quotes and can't contain escapes. [Pipeline] manifests share
+/// this format.
+pub struct Hierarchy {
+    coarse: DenseSVM,
+    routes: Vec<Route>,
+    branches: Vec<Branch>,
+    labels: Vec<i64>,
+    num_attributes: usize,
+}
+
+impl Hierarchy {
+    /// Creates a hierarchy from the `coarse` SVM and one fine SVM per coarse label in `branches`.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::IncompatibleModel] if a SVM is no classifier or two branches share a label, an
+    /// [Error::UnknownLabel] if a branch is given for a label the coarse SVM does not have.
+    pub fn new(coarse: DenseSVM, mut branches: Vec<(i64, DenseSVM)>) -> Result<Self, Error> {
+        let mut routes = Vec::with_capacity(coarse.classes.len());
+        let mut fine = Vec::with_capacity(branches.len());
+        let mut labels = Vec::new();
+
+        for (label, svm) in &branches {
+            if coarse.class_index_for_label(*label).is_none() {
+                return Err(Error::UnknownLabel(*label));
+            }
+
+            if !is_classifier(svm) {
+                return Err(Error::IncompatibleModel);
+            }
+        }
+
+        if !is_classifier(&coarse) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        for class in &coarse.classes {
+            match branches.iter().position(|(label, _)| *label == class.label) {
+                Some(position) => {
+                    let (_, svm) = branches.swap_remove(position);
+
+                    labels.extend(svm.classes.iter().map(|c| c.label));
+                    routes.push(Route::Branch(fine.len()));
+                    fine.push(svm);
+                }
+                None => {
+                    routes.push(Route::Leaf(labels.len()));
+                    labels.push(class.label);
+                }
+            }
+        }
+
+        for (i, label) in labels.iter().enumerate() {
+            if labels[.. i].contains(label) {
+                return Err(Error::IncompatibleModel);
+            }
+        }
+
+        let num_attributes = fine.iter().map(|svm| svm.num_attributes).fold(coarse.num_attributes, usize::max);
+        let branches = fine
+            .into_iter()
+            .map(|svm| {
+                let classes = map_classes(&labels, &svm)?;
+                Ok(Branch { svm, classes })
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Hierarchy {
+            coarse,
+            routes,
+            branches,
+            labels,
+            num_attributes,
+        })
+    }
+
+    /// Creates a hierarchy from a `manifest`, using `load` to obtain the SVM for each model name.
+    ///
+    /// See [Hierarchy] for the manifest format.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::ParsingError] if the manifest is malformed, any error returned by `load`, or
+    /// any error of [Hierarchy::new].
+    pub fn from_manifest<F>(manifest: &str, mut load: F) -> Result<Self, Error>
+    where
+        F: FnMut(&str) -> Result<DenseSVM, Error>,
+    {
+        let manifest = Manifest::parse(manifest)?;
+
+        manifest.expect_tables(&["branch"])?;
+        manifest.root().expect_keys(&["root"])?;
+
+        let coarse = load(manifest.root().require_text("root")?)?;
+        let branches = manifest
+            .tables("branch")
+            .map(|table| {
+                table.expect_keys(&["label", "model"])?;
+                Ok((table.require_number::<i64>("label")?, load(table.require_text("model")?)?))
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        Hierarchy::new(coarse, branches)
+    }
+
+    /// Returns the number of branches.
+    pub fn branches(&self) -> usize { self.branches.len() }
+
+    /// Returns the number of features a [Problem] of this hierarchy has.
+    pub fn attributes(&self) -> usize { self.num_attributes }
+
+    /// Returns the number of final classes.
+    pub fn classes(&self) -> usize { self.labels.len() }
+
+    /// Predicts `problem` with the coarse SVM and the SVM of the predicted branch.
+    fn predict(&self, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        let (coarse_problem, branch_problems) = problem.members.split_first_mut()?;
+        let features = problem.features.as_raw().flat();
+
+        load_features(features, coarse_problem, None);
+        self.predict_member(&self.coarse, coarse_problem, with_probabilities)?;
+
+        for v in problem.vote.iter_mut() {
+            *v = 0;
+        }
+
+        for p in problem.probabilities.flat_mut() {
+            *p = 0.0;
+        }
+
+        problem.probability_kind = coarse_problem.probability_kind;
+
+        // Labels without a branch directly take the coarse probability.
+        for (route, p) in self.routes.iter().zip(coarse_problem.probabilities()) {
+            if let Route::Leaf(index) = route {
+                problem.probabilities.flat_mut()[*index] = *p;
+            }
+        }
+
+        let coarse_index = match coarse_problem.solution() {
+            Solution::Label(label) => self.coarse.class_index_for_label(label)?,
+            solution => {
+                problem.result = solution;
+                return Ok(());
+            }
+        };
+
+        problem.result = match self.routes[coarse_index] {
+            Route::Leaf(_) => coarse_problem.solution(),
+            Route::Branch(b) => {
+                let branch = &self.branches[b];
+                let branch_problem = &mut branch_problems[b];
+                let coarse_probability = coarse_problem.probabilities()[coarse_index];
+
+                load_features(features, branch_problem, None);
+                self.predict_member(&branch.svm, branch_problem, with_probabilities)?;
+
+                for (index, p) in branch.classes.iter().zip(branch_problem.probabilities()) {
+                    problem.probabilities.flat_mut()[*index] = coarse_probability * p;
+                }
+
+                if branch_problem.probability_kind != ProbabilityKind::Calibrated {
+                    problem.probability_kind = branch_problem.probability_kind;
+                }
+
+                branch_problem.solution()
+            }
+        };
+
+        Ok(())
+    }
+
+    fn predict_member(&self, svm: &DenseSVM, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        if with_probabilities {
+            svm.predict_probability(problem)
+        } else {
+            svm.predict_value(problem)
+        }
+    }
+}
+
+fn is_classifier(svm: &DenseSVM) -> bool {
+    match svm.svm_type {
+        SVMType::CSvc | SVMType::NuSvc => true,
+        SVMType::ESvr | SVMType::NuSvr => false,
+    }
+}
+
+impl Predict<SimdVector<f32s>, SimdVector<f64s>> for Hierarchy {
+    fn predict_value(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, false) }
+
+    fn predict_probability(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, true) }
+
+    fn problem(&self) -> DenseProblem { DenseProblem::from(self) }
+}
+
+impl Introspect for Hierarchy {
+    fn attributes(&self) -> usize { self.num_attributes }
+
+    fn classes(&self) -> usize { self.labels.len() }
+
+    fn labels(&self) -> Vec<i64> { self.labels.clone() }
+}
+
+impl<'a> From<&'a Hierarchy> for DenseProblem {
+    fn from(hierarchy: &Hierarchy) -> Self {
+        let mut problem = Problem::<SimdVector<f32s>>::with_dimension(0, hierarchy.labels.len(), hierarchy.num_attributes, hierarchy.labels.clone());
+
+        problem.members.push(Problem::from(&hierarchy.coarse));
+        problem.members.extend(hierarchy.branches.iter().map(|b| Problem::from(&b.svm)));
+        problem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::set_sample_features, *};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn from_manifest() -> Result<(), Error> {
+        let manifest = "# Sample\nroot = \"coarse.model\"\n\n[[branch]]\nlabel = 42\nmodel = \"fine.model\"\n";
+        let fine = SAMPLE_MODEL.replace("label 21 42", "label 1 2");
+        let load = |name: &str| match name {
+            "coarse.model" => DenseSVM::try_from(SAMPLE_MODEL),
+            _ => DenseSVM::try_from(fine.as_str()),
+        };
+
+        let hierarchy = Hierarchy::from_manifest(manifest, load)?;
+        let mut problem = Problem::from(&hierarchy);
+        set_sample_features(&mut problem);
+
+        hierarchy.predict_value(&mut problem)?;
+
+        // The coarse SVM predicts `42`, so its branch decides.
+        assert_eq!(hierarchy.branches(), 1);
+        assert_eq!(hierarchy.classes(), 3);
+        assert_eq!(problem.solution(), Solution::Label(2));
+        assert!(Hierarchy::from_manifest("[[branch]]\nlabel = 42\nmodel = \"fine.model\"\n", |_| DenseSVM::try_from(SAMPLE_MODEL)).is_err());
+        assert!(Hierarchy::from_manifest("root = \"coarse.model\"\n[[branch]]\nlabel = 42\n", |_| DenseSVM::try_from(SAMPLE_MODEL)).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::svm::{core::SVMCore, reload::ReloadableSvm};
+
+/// Describes the shape of a predictor, implemented by all SVMs and their compositions.
+///
+/// # Description
+///
+/// [Predict] only predicts, this trait tells what a predictor expects and returns. Both are
+/// separate so that a predictor only implements what it supports, and both are object safe,
+/// so generic code and registries of trait objects can check a predictor before creating
+/// problems for it.
+///
+/// For SVMs the inherent methods of the same name, like [SVMCore::labels], are usually more
+/// convenient. The kernels of a SVM are computed via [KernelDense] and [KernelSparse].
+pub trait Introspect {
+    /// Returns the number of attributes a problem must have.
+    fn attributes(&self) -> usize;
+
+    /// Returns the number of classes, `1` for regression.
+    fn classes(&self) -> usize;
+
+    /// Returns the labels of all classes, in the order of [Problem::probabilities].
+    fn labels(&self) -> Vec<i64>;
+}
+
+impl<K, M32, V32, V64> Introspect for SVMCore<K, M32, V32, V64>
+where
+    K: ?Sized,
+{
+    fn attributes(&self) -> usize { self.num_attributes }
+
+    fn classes(&self) -> usize { self.classes.len() }
+
+    fn labels(&self) -> Vec<i64> { self.classes.iter().map(|class| class.label).collect() }
+}
+
+impl<S> Introspect for ReloadableSvm<S>
+where
+    S: Introspect,
+{
+    fn attributes(&self) -> usize { self.current().attributes() }
+
+    fn classes(&self) -> usize { self.current().classes() }
+
+    fn labels(&self) -> Vec<i64> { self.current().labels() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::convert::TryFrom;
+
+    fn describe(predictor: &dyn Introspect) -> (usize, usize, Vec<i64>) { (predictor.attributes(), predictor.classes(), predictor.labels()) }
+
+    #[test]
+    fn introspect() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let reloadable = ReloadableSvm::new(SparseSVM::try_from(SAMPLE_MODEL)?);
+
+        assert_eq!(describe(&svm), (4, 2, vec![21, 42]));
+        assert_eq!(describe(&reloadable), describe(&svm));
+
+        Ok(())
+    }
+}
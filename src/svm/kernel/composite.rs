@@ -0,0 +1,233 @@
+use super::{KernelDense, KernelParameters, KernelSparse};
+use crate::{
+    errors::Error,
+    sparse::{SparseMatrix, SparseVector},
+};
+
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
+
+/// How the kernels of a [Composite] are combined.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Combination {
+    /// `w_1 * k_1 + w_2 * k_2 + ...`
+    Sum,
+
+    /// `w_1 * k_1 * w_2 * k_2 * ...`
+    Product,
+}
+
+/// Combines several weighted kernels into one, for example `0.7 * rbf + 0.3 * linear`.
+///
+/// # Description
+///
+/// All kernels are built-in kernels given by their [KernelParameters]. They only depend on the
+/// dot product or the squared distance of two vectors, so both are computed in a single pass over
+/// each support vector, and all kernels are evaluated from them. Use [SVMCore::set_kernel]
+/// to use a composite kernel for a [DenseSVM], or `Box::new(composite)` as [KernelSparse] for a
+/// [SparseSVM].
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+///
+///     let kernel = Composite::new(Combination::Sum, &[(0.7, KernelParameters::Rbf { gamma: 0.5 }), (0.3, KernelParameters::Linear)])?;
+///     svm.set_kernel(Box::new(kernel));
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Composite {
+    combination: Combination,
+    kernels: Vec<(f64, KernelParameters)>,
+    uses_dot: bool,
+    uses_distance: bool,
+}
+
+impl Composite {
+    /// Creates a kernel combining the weighted `kernels` by `combination`.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::UnsupportedSection] if one of the kernels is a [KernelParameters::Custom] kernel.
+    pub fn new(combination: Combination, kernels: &[(f64, KernelParameters)]) -> Result<Self, Error> {
+        if let Some((_, KernelParameters::Custom { name })) = kernels.iter().find(|(_, k)| !k.is_builtin()) {
+            return Err(Error::UnsupportedSection {
+                section: "kernel_type",
+                value: (*name).to_owned(),
+            });
+        }
+
+        Ok(Composite {
+            combination,
+            kernels: kernels.to_vec(),
+            uses_dot: kernels.iter().any(|(_, k)| !k.uses_distance()),
+            uses_distance: kernels.iter().any(|(_, k)| k.uses_distance()),
+        })
+    }
+
+    /// Returns the combination and the weighted kernels.
+    pub fn kernels(&self) -> (Combination, &[(f64, KernelParameters)]) { (self.combination, &self.kernels) }
+
+    /// Combines all kernels for two vectors with dot product `dot` and squared distance `distance`.
+    fn combine(&self, dot: f32, distance: f32) -> f64 {
+        let values = self.kernels.iter().map(|(weight, kernel)| weight * kernel.evaluate(dot, distance));
+
+        match self.combination {
+            Combination::Sum => values.sum(),
+            Combination::Product => values.product(),
+        }
+    }
+}
+
+impl KernelDense for Composite {
+    fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let feature: &[f32s] = &feature;
+
+        for (i, sv) in vectors.row_iter().enumerate() {
+            let mut dot = f32s::splat(0.0);
+            let mut distance = f32s::splat(0.0);
+
+            for (a, b) in sv.iter().zip(feature) {
+                if self.uses_dot {
+                    dot += *a * *b;
+                }
+
+                if self.uses_distance {
+                    distance += (*a - *b) * (*a - *b);
+                }
+            }
+
+            output[i] = self.combine(dot.sum(), distance.sum());
+        }
+    }
+
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let num_chunks = vectors.column(0).len();
+
+        // Each SIMD chunk holds the same attribute of `f32s::lanes()` consecutive support vectors.
+        for chunk in 0 .. num_chunks {
+            let mut dot = f32s::splat(0.0);
+            let mut distance = f32s::splat(0.0);
+
+            for (column, value) in vectors.column_iter().zip(feature.flat()) {
+                let sv = &column[chunk];
+                let value = f32s::splat(*value);
+
+                if self.uses_dot {
+                    dot += *sv * value;
+                }
+
+                if self.uses_distance {
+                    distance += (*sv - value) * (*sv - value);
+                }
+            }
+
+            for lane in 0 .. f32s::lanes() {
+                let i = chunk * f32s::lanes() + lane;
+
+                if i >= output.len() {
+                    break;
+                }
+
+                output[i] = self.combine(dot.extract(lane), distance.extract(lane));
+            }
+        }
+    }
+
+    fn compute_self(&self, feature: &SimdVector<f32s>) -> f64 {
+        let dot = feature.flat().iter().map(|x| x * x).sum();
+        self.combine(dot, 0.0)
+    }
+
+    fn to_sparse(&self) -> Option<Box<dyn KernelSparse>> { Some(Box::new(self.clone())) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Custom { name: "composite" } }
+}
+
+impl KernelSparse for Composite {
+    fn compute(&self, vectors: &SparseMatrix<f32>, feature: &SparseVector<f32>, output: &mut [f64]) {
+        for (i, sv) in vectors.row_iter().enumerate() {
+            let (mut dot, mut distance) = (0.0, 0.0);
+            let mut a_iter = sv.iter();
+            let mut b_iter = feature.iter();
+
+            let (mut a, mut b) = (a_iter.next(), b_iter.next());
+
+            // Attributes present in only one vector still count towards the distance.
+            output[i] = loop {
+                match (a, b) {
+                    (Some((i_a, x)), Some((i_b, y))) if i_a == i_b => {
+                        dot += x * y;
+                        distance += (x - y) * (x - y);
+
+                        a = a_iter.next();
+                        b = b_iter.next();
+                    }
+                    (Some((i_a, x)), Some((i_b, _))) if i_a < i_b => {
+                        distance += x * x;
+                        a = a_iter.next();
+                    }
+                    (Some(_), Some((_, y))) => {
+                        distance += y * y;
+                        b = b_iter.next();
+                    }
+                    (Some((_, x)), None) => {
+                        distance += x * x;
+                        a = a_iter.next();
+                    }
+                    (None, Some((_, y))) => {
+                        distance += y * y;
+                        b = b_iter.next();
+                    }
+                    (None, None) => break self.combine(dot, distance),
+                }
+            }
+        }
+    }
+
+    fn to_dense(&self) -> Option<Box<dyn KernelDense>> { Some(Box::new(self.clone())) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Custom { name: "composite" } }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{svm::kernel::test_vectors, *};
+
+    #[test]
+    fn combinations() -> Result<(), Error> {
+        let rows: &[&[f32]] = &[&[0.5, -1.0, 2.0, 0.25, 1.5], &[-0.75, 0.5, 0.0, 1.0, -2.0]];
+        let (row_major, column_major, feature) = test_vectors(rows, &[0.25, 0.5, -1.5, 1.0, 0.75]);
+        let linear: &dyn KernelDense = &Linear {};
+
+        let mut expected = [0.0; 2];
+        linear.compute(&row_major, &feature, &mut expected);
+
+        let sum = Composite::new(Combination::Sum, &[(0.25, KernelParameters::Linear), (0.75, KernelParameters::Linear)])?;
+        let product = Composite::new(Combination::Product, &[(2.0, KernelParameters::Linear), (0.5, KernelParameters::Rbf { gamma: 0.0 })])?;
+
+        for kernel in &[sum, product] {
+            let kernel: &dyn KernelDense = kernel;
+            let mut from_rows = [0.0; 2];
+            let mut from_columns = [0.0; 2];
+
+            kernel.compute(&row_major, &feature, &mut from_rows);
+            kernel.compute_columns(&column_major, &feature, &mut from_columns);
+
+            for i in 0 .. 2 {
+                assert!((from_rows[i] - expected[i]).abs() < 1e-5);
+                assert!((from_columns[i] - expected[i]).abs() < 1e-5);
+            }
+        }
+
+        assert!(Composite::new(Combination::Sum, &[(1.0, KernelParameters::Custom { name: "custom" })]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,30 +1,146 @@
 use std::convert::From;
 
-use super::{KernelDense, KernelSparse};
+use super::{KernelDense, KernelParameters, KernelSparse};
 use crate::{
     parser::ModelFile,
     sparse::{SparseMatrix, SparseVector},
 };
 
-use simd_aligned::{f32s, RowOptimized, SimdMatrix, SimdVector};
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
 
 #[derive(Copy, Clone, Debug, Default)]
 #[doc(hidden)]
 pub struct Linear {}
 
+/// Number of support vectors we prefetch ahead of the one currently computed.
+#[cfg(feature = "prefetch")]
+crate const PREFETCH_ROWS: usize = 2;
+
+/// Number of support vectors [dot_rows] processes together.
+const TILE_ROWS: usize = 16;
+
+/// Number of SIMD chunks of each support vector [dot_rows] processes at once. Together with
+/// [TILE_ROWS] this keeps a tile of support vectors and the features within the L1 cache.
+const TILE_CHUNKS: usize = 64;
+
+/// Computes the dot product of `feature` with each row of `vectors`, and passes it with the row's
+/// index to `f`. This is the core of all kernels based on dot products.
+///
+/// Rows are processed in tiles of [TILE_ROWS], and each tile in blocks of [TILE_CHUNKS] attributes,
+/// so that large models don't evict the features from the cache while streaming through the
+/// support vectors. Each row still sums its attributes in order, so results are identical to a
+/// row-by-row loop.
+crate fn dot_rows(vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, mut f: impl FnMut(usize, f32)) {
+    // Runs ahead of the current support vector. For large models the loop below is memory bound.
+    #[cfg(feature = "prefetch")]
+    let mut ahead = vectors.row_iter().skip(PREFETCH_ROWS);
+
+    let feature: &[f32s] = &feature;
+    let mut rows = vectors.row_iter();
+    let mut first = 0;
+
+    loop {
+        let mut tile = [&[] as &[f32s]; TILE_ROWS];
+        let mut len = 0;
+
+        for (slot, sv) in tile.iter_mut().zip(&mut rows) {
+            #[cfg(feature = "prefetch")]
+            {
+                if let Some(row) = ahead.next() {
+                    crate::util::prefetch(row);
+                }
+            }
+
+            *slot = sv;
+            len += 1;
+        }
+
+        if len == 0 {
+            break;
+        }
+
+        let mut sums = [f32s::splat(0.0); TILE_ROWS];
+
+        for start in (0 .. feature.len()).step_by(TILE_CHUNKS) {
+            let end = (start + TILE_CHUNKS).min(feature.len());
+            let block = &feature[start .. end];
+
+            for (sum, sv) in sums.iter_mut().zip(&tile[.. len]) {
+                for (a, b) in sv.iter().skip(start).zip(block) {
+                    *sum += *a * *b;
+                }
+            }
+        }
+
+        for (i, sum) in sums[.. len].iter().enumerate() {
+            f(first + i, sum.sum());
+        }
+
+        first += len;
+    }
+}
+
+/// Same as [dot_rows] for support vectors stored in columns, for at most `len` support vectors.
+crate fn dot_columns(vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, len: usize, mut f: impl FnMut(usize, f32)) {
+    let num_chunks = vectors.column(0).len();
+
+    // Each SIMD chunk holds the same attribute of `f32s::lanes()` consecutive support vectors.
+    for chunk in 0 .. num_chunks {
+        let mut sum = f32s::splat(0.0);
+
+        for (column, value) in vectors.column_iter().zip(feature.flat()) {
+            let sv = &column[chunk];
+            let value = f32s::splat(*value);
+
+            sum += *sv * value;
+        }
+
+        for lane in 0 .. f32s::lanes() {
+            let i = chunk * f32s::lanes() + lane;
+
+            if i >= len {
+                break;
+            }
+
+            f(i, sum.extract(lane));
+        }
+    }
+}
+
 impl KernelDense for Linear {
     fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        dot_rows(vectors, feature, |i, dot| output[i] = f64::from(dot));
+    }
+
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let len = output.len();
+        dot_columns(vectors, feature, len, |i, dot| output[i] = f64::from(dot));
+    }
+
+    fn compute_incremental(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, previous: &SimdVector<f32s>, changed: &[usize], output: &mut [f64]) -> bool {
+        let feature = feature.flat();
+        let previous = previous.flat();
+
+        // Since the kernel is linear we only need to add the change of each attribute.
         for (i, sv) in vectors.row_iter().enumerate() {
-            let mut sum = f32s::splat(0.0);
-            let feature: &[f32s] = &feature;
+            let mut delta = 0.0;
 
-            for (a, b) in sv.iter().zip(feature) {
-                sum += *a * *b;
+            for &j in changed {
+                let x = sv[j / f32s::lanes()].extract(j % f32s::lanes());
+                delta += x * (feature[j] - previous[j]);
             }
 
-            output[i] = f64::from(sum.sum());
+            output[i] += f64::from(delta);
         }
+
+        true
     }
+
+    fn is_linear(&self) -> bool { true }
+
+    fn to_sparse(&self) -> Option<Box<dyn KernelSparse>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Linear }
 }
 
 impl KernelSparse for Linear {
@@ -51,6 +167,10 @@ impl KernelSparse for Linear {
             }
         }
     }
+
+    fn to_dense(&self) -> Option<Box<dyn KernelDense>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Linear }
 }
 
 impl<'a> From<&'a ModelFile<'a>> for Linear {
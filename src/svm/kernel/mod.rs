@@ -1,27 +1,231 @@
+mod composite;
 mod linear;
+mod normalized;
 mod poly;
 mod rbf;
 mod sigmoid;
 
 use crate::sparse::{SparseMatrix, SparseVector};
-use simd_aligned::{f32s, RowOptimized, SimdMatrix, SimdVector};
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
 
-pub use self::{linear::*, poly::*, rbf::*, sigmoid::*};
+pub use self::{composite::*, linear::*, normalized::*, poly::*, rbf::*, sigmoid::*};
 
-/// Base trait for kernels
-#[doc(hidden)]
+/// Type and parameters of a kernel, as given in the model file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KernelParameters {
+    /// `<u, v>`
+    Linear,
+
+    /// `(gamma * <u, v> + coef0)^degree`
+    Poly { degree: u32, gamma: f32, coef0: f32 },
+
+    /// `exp(-gamma * |u - v|^2)`
+    Rbf { gamma: f32 },
+
+    /// `tanh(gamma * <u, v> + coef0)`
+    Sigmoid { gamma: f32, coef0: f32 },
+
+    /// A kernel implemented outside of FFSVM, with its `kernel_type` in the model file.
+    Custom { name: &'static str },
+}
+
+impl KernelParameters {
+    /// Returns `true` if this kernel can be computed by [KernelParameters::compute_sequential].
+    crate fn is_builtin(&self) -> bool {
+        match self {
+            KernelParameters::Custom { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Computes the kernel between the support vector `sv` and `feature` with scalar math, summing
+    /// all attributes in order. Unlike the SIMD kernels, the result does not depend on the vector
+    /// width, see [SVMCore::set_deterministic].
+    crate fn compute_sequential(&self, sv: impl Iterator<Item = f32>, feature: &[f32]) -> f64 {
+        let pairs = sv.zip(feature);
+
+        if self.uses_distance() {
+            self.evaluate(0.0, pairs.fold(0.0, |sum, (a, b)| sum + (a - b) * (a - b)))
+        } else {
+            self.evaluate(pairs.fold(0.0, |sum, (a, b)| sum + a * b), 0.0)
+        }
+    }
+
+    /// Returns `true` if this kernel is computed from the squared distance of two vectors, rather
+    /// than their dot product.
+    crate fn uses_distance(&self) -> bool {
+        match self {
+            KernelParameters::Rbf { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Computes the kernel of two vectors from their dot product `dot` or squared distance
+    /// `distance`, see [KernelParameters::uses_distance].
+    crate fn evaluate(&self, dot: f32, distance: f32) -> f64 {
+        match *self {
+            KernelParameters::Linear => f64::from(dot),
+            KernelParameters::Poly { degree, gamma, coef0 } => crate::util::powi(f64::from(gamma * dot + coef0), degree),
+            KernelParameters::Rbf { gamma } => f64::from((-gamma * distance).exp()),
+            KernelParameters::Sigmoid { gamma, coef0 } => f64::from(gamma * dot + coef0).tanh(),
+            KernelParameters::Custom { name } => unreachable!("Kernel `{}` has no sequential implementation.", name),
+        }
+    }
+}
+
+/// Kernel used by a [DenseSVM] to compare a problem's features with all support vectors of a class.
+///
+/// # Implementing
+///
+/// Downstream crates can implement this trait to use their own kernels, see [Rbf] for an example.
+/// All matrices and vectors are [simd_aligned] types as re-exported by this crate:
+///
+/// * Support vectors are stored one per row (or column, see [KernelDense::compute_columns]),
+///   each padded with zeros to a multiple of `f32s::lanes()` attributes.
+/// * The feature vector is padded the same way. Padding is zero on both sides, so it adds
+///   nothing to dot products or distances.
+/// * `output` has at least one entry per support vector. The kernel value of support vector `i`
+///   must be written to `output[i]`, any further entries must be ignored.
+///
+/// Kernels are shared between threads and must not keep state between calls.
 pub trait KernelDense
 where
-    Self: Sync,
+    Self: Send + Sync,
 {
+    /// Computes the kernel between `feature` and each row of `vectors` into `output`.
     fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]);
+
+    /// Same as `compute`, but for support vectors stored attribute-major, see [Layout::Columns].
+    /// Each SIMD chunk of a column holds the same attribute of `f32s::lanes()` consecutive
+    /// support vectors, the last chunk is padded with zeros.
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]);
+
+    /// Updates `output`, previously computed for `previous`, after only the attributes `changed` were
+    /// modified to produce `feature`. Returns `false` if this kernel can't be updated incrementally.
+    fn compute_incremental(
+        &self,
+        _vectors: &SimdMatrix<f32s, RowOptimized>,
+        _feature: &SimdVector<f32s>,
+        _previous: &SimdVector<f32s>,
+        _changed: &[usize],
+        _output: &mut [f64],
+    ) -> bool {
+        false
+    }
+
+    /// Computes values per support vector when a model is loaded, for example their norms, which
+    /// are then passed to [KernelDense::compute_prepared]. `vectors` holds the support vectors of
+    /// one class in rows. By default nothing is prepared.
+    fn prepare(&self, _vectors: &SimdMatrix<f32s, RowOptimized>) -> Vec<f64> { Vec::new() }
+
+    /// Same as `compute`, with the values returned by [KernelDense::prepare] for `vectors`.
+    fn compute_prepared(&self, vectors: &SimdMatrix<f32s, RowOptimized>, _prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        self.compute(vectors, feature, output)
+    }
+
+    /// Same as `compute_columns`, with the values returned by [KernelDense::prepare] for `vectors`.
+    fn compute_columns_prepared(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, _prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        self.compute_columns(vectors, feature, output)
+    }
+
+    /// Computes the kernel of `feature` with itself, `k(x, x)`.
+    fn compute_self(&self, feature: &SimdVector<f32s>) -> f64 {
+        let parameters = self.parameters();
+        let flat = feature.flat();
+
+        if parameters.is_builtin() {
+            return parameters.compute_sequential(flat.iter().cloned(), flat);
+        }
+
+        let mut vectors = SimdMatrix::<f32s, RowOptimized>::with_dimension(1, flat.len());
+        let mut output = [0.0];
+
+        vectors.row_as_flat_mut(0).copy_from_slice(flat);
+        self.compute(&vectors, feature, &mut output);
+        output[0]
+    }
+
+    /// Returns `true` if this is the linear kernel `<a, b>`, which allows folding support vectors.
+    fn is_linear(&self) -> bool { false }
+
+    /// Returns the same kernel for use with sparse data, if this kernel supports it.
+    fn to_sparse(&self) -> Option<Box<dyn KernelSparse>> { None }
+
+    /// Returns the type and parameters of this kernel. Kernels implemented outside of FFSVM
+    /// return [KernelParameters::Custom], which [SVMCore::set_deterministic] can't compute
+    /// sequentially, so their results may depend on the SIMD width.
+    fn parameters(&self) -> KernelParameters;
 }
 
-/// Base trait for kernels
-#[doc(hidden)]
+/// Kernel used by a [SparseSVM] to compare a problem's features with all support vectors of a class.
+///
+/// # Implementing
+///
+/// Support vectors are stored one per row of `vectors`, and only contain their non-zero
+/// attributes in ascending order of their index, see [SparseVector::iter]. The kernel value of
+/// support vector `i` must be written to `output[i]`, any further entries must be ignored. As for
+/// [KernelDense], kernels must not keep state between calls.
 pub trait KernelSparse
 where
-    Self: Sync,
+    Self: Send + Sync,
 {
+    /// Computes the kernel between `feature` and each row of `vectors` into `output`.
     fn compute(&self, vectors: &SparseMatrix<f32>, feature: &SparseVector<f32>, output: &mut [f64]);
+
+    /// Returns the same kernel for use with dense data, if this kernel supports it.
+    fn to_dense(&self) -> Option<Box<dyn KernelDense>> { None }
+
+    /// Returns the type and parameters of this kernel, see [KernelDense::parameters].
+    fn parameters(&self) -> KernelParameters;
+}
+
+/// Returns `rows` as support vectors in both layouts, and `feature` as feature vector.
+#[cfg(test)]
+crate fn test_vectors(rows: &[&[f32]], feature: &[f32]) -> (SimdMatrix<f32s, RowOptimized>, SimdMatrix<f32s, ColumnOptimized>, SimdVector<f32s>) {
+    let mut row_major = SimdMatrix::with_dimension(rows.len(), feature.len());
+    let mut column_major = SimdMatrix::with_dimension(rows.len(), feature.len());
+    let mut vector = SimdVector::with(0.0, feature.len());
+
+    for (i, row) in rows.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            row_major.flat_mut()[(i, j)] = *value;
+            column_major.flat_mut()[(i, j)] = *value;
+        }
+    }
+
+    for (x, value) in vector.flat_mut().iter_mut().zip(feature) {
+        *x = *value;
+    }
+
+    (row_major, column_major, vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_vectors;
+    use crate::*;
+    use simd_aligned::f32s;
+
+    #[test]
+    fn compute_sequential() {
+        let rows: &[&[f32]] = &[&[0.5, -1.0, 2.0, 0.25, 1.5], &[-0.75, 0.5, 0.0, 1.0, -2.0]];
+        let values = [0.25, 0.5, -1.5, 1.0, 0.75];
+        let (row_major, column_major, feature) = test_vectors(rows, &values);
+        let kernels: [&dyn KernelDense; 2] = [&Linear {}, &Rbf { gamma: 0.5 }];
+
+        for kernel in kernels.iter() {
+            let parameters = kernel.parameters();
+            let mut expected = [0.0; 2];
+            kernel.compute(&row_major, &feature, &mut expected);
+
+            for i in 0 .. rows.len() {
+                let from_rows = parameters.compute_sequential(row_major.row_as_flat(i).iter().cloned(), &values);
+                let from_columns = parameters.compute_sequential(column_major.column_iter().map(|column| column[i / f32s::lanes()].extract(i % f32s::lanes())), &values);
+
+                // The SIMD kernels sum columns in another order than rows, but sequentially the order is the same.
+                assert_eq!(from_rows.to_bits(), from_columns.to_bits());
+                assert!((from_rows - expected[i]).abs() < 1e-5);
+            }
+        }
+    }
 }
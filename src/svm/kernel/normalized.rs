@@ -0,0 +1,124 @@
+use super::{KernelDense, KernelParameters};
+
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
+
+/// Normalizes a base kernel to `k(x, y) / sqrt(k(x, x) * k(y, y))`.
+///
+/// # Description
+///
+/// Normalized kernels are `1` for identical vectors, independent of their scale, which is why
+/// they are common for histogram data. Use [SVMCore::normalize_kernel] to normalize the kernel
+/// of a loaded SVM. The self-kernels `k(y, y)` of the support vectors are then computed once via
+/// [KernelDense::prepare], so a prediction only additionally computes `k(x, x)`.
+///
+/// If `k(x, x)` or `k(y, y)` is `0` the kernel value is `0`.
+pub struct Normalized {
+    base: Box<dyn KernelDense>,
+}
+
+impl Normalized {
+    /// Creates a normalized version of `base`.
+    pub fn new(base: Box<dyn KernelDense>) -> Self { Normalized { base } }
+
+    /// Returns the kernel being normalized.
+    pub fn base(&self) -> &dyn KernelDense { self.base.as_ref() }
+}
+
+/// Copies `values` into a new vector.
+fn to_vector(values: impl Iterator<Item = f32>, len: usize) -> SimdVector<f32s> {
+    let mut vector = SimdVector::with(0.0, len);
+
+    for (x, value) in vector.flat_mut().iter_mut().zip(values) {
+        *x = value;
+    }
+
+    vector
+}
+
+/// Divides each kernel value in `output` by the root of `self_feature` and its support vector's self-kernel.
+fn normalize(output: &mut [f64], self_feature: f64, self_vectors: &[f64]) {
+    for (value, self_vector) in output.iter_mut().zip(self_vectors) {
+        let norm = (self_feature * self_vector).sqrt();
+        *value = if norm > 0.0 { *value / norm } else { 0.0 };
+    }
+}
+
+impl KernelDense for Normalized {
+    fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let prepared = self.prepare(vectors);
+        self.compute_prepared(vectors, &prepared, feature, output);
+    }
+
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let num_attributes = vectors.column_iter().count();
+        let num_vectors = output.len().min(vectors.column(0).len() * f32s::lanes());
+
+        let prepared = (0 .. num_vectors)
+            .map(|i| {
+                let values = vectors.column_iter().map(|column| column[i / f32s::lanes()].extract(i % f32s::lanes()));
+                self.base.compute_self(&to_vector(values, num_attributes))
+            }).collect::<Vec<_>>();
+
+        self.compute_columns_prepared(vectors, &prepared, feature, output);
+    }
+
+    fn prepare(&self, vectors: &SimdMatrix<f32s, RowOptimized>) -> Vec<f64> {
+        vectors
+            .row_iter()
+            .map(|sv| {
+                let values = sv.iter().flat_map(|x| (0 .. f32s::lanes()).map(move |lane| x.extract(lane)));
+                self.base.compute_self(&to_vector(values, sv.len() * f32s::lanes()))
+            }).collect()
+    }
+
+    fn compute_prepared(&self, vectors: &SimdMatrix<f32s, RowOptimized>, prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        self.base.compute(vectors, feature, output);
+        normalize(output, self.base.compute_self(feature), prepared);
+    }
+
+    fn compute_columns_prepared(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        self.base.compute_columns(vectors, feature, output);
+        normalize(output, self.base.compute_self(feature), prepared);
+    }
+
+    fn compute_self(&self, feature: &SimdVector<f32s>) -> f64 {
+        if self.base.compute_self(feature) > 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Custom { name: "normalized" } }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{svm::kernel::test_vectors, *};
+
+    #[test]
+    fn scale_invariant() {
+        let normalized: &dyn KernelDense = &Normalized::new(Box::new(Linear {}));
+        let rows: &[&[f32]] = &[&[0.5, -1.0, 2.0, 0.25, 1.5], &[0.0, 0.0, 0.0, 0.0, 0.0]];
+
+        // Scaling a vector must not change its normalized kernel values.
+        let (row_major, column_major, feature) = test_vectors(rows, &[1.5, -3.0, 6.0, 0.75, 4.5]);
+        let prepared = normalized.prepare(&row_major);
+
+        let mut from_rows = [0.0; 2];
+        let mut from_prepared = [0.0; 2];
+        let mut from_columns = [0.0; 2];
+
+        normalized.compute(&row_major, &feature, &mut from_rows);
+        normalized.compute_prepared(&row_major, &prepared, &feature, &mut from_prepared);
+        normalized.compute_columns(&column_major, &feature, &mut from_columns);
+
+        for output in &[from_rows, from_prepared, from_columns] {
+            assert!((output[0] - 1.0).abs() < 1e-5);
+            assert_eq!(output[1], 0.0);
+        }
+
+        assert_eq!(normalized.compute_self(&feature), 1.0);
+        assert_eq!(normalized.parameters(), KernelParameters::Custom { name: "normalized" });
+    }
+}
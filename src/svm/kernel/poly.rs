@@ -1,13 +1,13 @@
 use std::convert::{From, TryFrom};
 
-use super::{KernelDense, KernelSparse};
+use super::{KernelDense, KernelParameters, KernelSparse};
 use crate::{
     errors::Error,
     parser::ModelFile,
     sparse::{SparseMatrix, SparseVector},
 };
 
-use simd_aligned::{f32s, RowOptimized, SimdMatrix, SimdVector};
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
 
 #[derive(Copy, Clone, Debug, Default)]
 #[doc(hidden)]
@@ -30,6 +30,42 @@ impl KernelDense for Poly {
             output[i] = crate::util::powi(f64::from(self.gamma * sum.sum() + self.coef0), self.degree);
         }
     }
+
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let num_chunks = vectors.column(0).len();
+
+        // Each SIMD chunk holds the same attribute of `f32s::lanes()` consecutive support vectors.
+        for chunk in 0 .. num_chunks {
+            let mut sum = f32s::splat(0.0);
+
+            for (column, value) in vectors.column_iter().zip(feature.flat()) {
+                let sv = &column[chunk];
+                let value = f32s::splat(*value);
+
+                sum += *sv * value;
+            }
+
+            for lane in 0 .. f32s::lanes() {
+                let i = chunk * f32s::lanes() + lane;
+
+                if i >= output.len() {
+                    break;
+                }
+
+                output[i] = crate::util::powi(f64::from(self.gamma * sum.extract(lane) + self.coef0), self.degree);
+            }
+        }
+    }
+
+    fn to_sparse(&self) -> Option<Box<dyn KernelSparse>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters {
+        KernelParameters::Poly {
+            degree: self.degree,
+            gamma: self.gamma,
+            coef0: self.coef0,
+        }
+    }
 }
 
 impl KernelSparse for Poly {
@@ -56,6 +92,16 @@ impl KernelSparse for Poly {
             }
         }
     }
+
+    fn to_dense(&self) -> Option<Box<dyn KernelDense>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters {
+        KernelParameters::Poly {
+            degree: self.degree,
+            gamma: self.gamma,
+            coef0: self.coef0,
+        }
+    }
 }
 
 impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for Poly {
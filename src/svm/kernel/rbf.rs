@@ -1,13 +1,23 @@
 use std::convert::{From, TryFrom};
 
-use super::{KernelDense, KernelSparse};
+use super::{
+    linear::{dot_columns, dot_rows},
+    KernelDense, KernelParameters, KernelSparse,
+};
 use crate::{
     errors::Error,
     parser::ModelFile,
     sparse::{SparseMatrix, SparseVector},
 };
 
-use simd_aligned::{f32s, RowOptimized, SimdMatrix, SimdVector};
+use simd_aligned::{f32s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
+
+#[cfg(feature = "prefetch")]
+use super::linear::PREFETCH_ROWS;
+
+/// Expanded distances below this fraction of `|sv|^2 + |x|^2` can be dominated by rounding errors
+/// of the `f32` dot product, so they are computed directly instead.
+const CANCELLATION: f64 = 0.1;
 
 #[derive(Copy, Clone, Debug, Default)]
 #[doc(hidden)]
@@ -17,9 +27,20 @@ pub struct Rbf {
 
 impl KernelDense for Rbf {
     fn compute(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        // Runs ahead of the current support vector. For large models the loop below is memory bound.
+        #[cfg(feature = "prefetch")]
+        let mut ahead = vectors.row_iter().skip(PREFETCH_ROWS);
+
         // According to Instruments, for realistic SVMs and problems, the VAST majority of our
         // CPU time is spent in this loop.
         for (i, sv) in vectors.row_iter().enumerate() {
+            #[cfg(feature = "prefetch")]
+            {
+                if let Some(row) = ahead.next() {
+                    crate::util::prefetch(row);
+                }
+            }
+
             let mut sum = f32s::splat(0.0);
             let feature: &[f32s] = &feature;
 
@@ -33,6 +54,116 @@ impl KernelDense for Rbf {
             output[i] = f64::from((-self.gamma * sum.sum()).exp());
         }
     }
+
+    fn compute_columns(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let num_chunks = vectors.column(0).len();
+
+        // Each SIMD chunk holds the same attribute of `f32s::lanes()` consecutive support vectors.
+        for chunk in 0 .. num_chunks {
+            let mut sum = f32s::splat(0.0);
+
+            for (column, value) in vectors.column_iter().zip(feature.flat()) {
+                let sv = &column[chunk];
+                let value = f32s::splat(*value);
+
+                sum += (*sv - value) * (*sv - value);
+            }
+
+            for lane in 0 .. f32s::lanes() {
+                let i = chunk * f32s::lanes() + lane;
+
+                if i >= output.len() {
+                    break;
+                }
+
+                output[i] = f64::from((-self.gamma * sum.extract(lane)).exp());
+            }
+        }
+    }
+
+    fn prepare(&self, vectors: &SimdMatrix<f32s, RowOptimized>) -> Vec<f64> { vectors.row_iter().map(squared_norm).collect() }
+
+    fn compute_prepared(&self, vectors: &SimdMatrix<f32s, RowOptimized>, prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let gamma = f64::from(self.gamma);
+        let feature_norm = squared_norm(feature);
+        let mut inexact = Vec::new();
+
+        // Since |a - b|^2 = |a|^2 + |b|^2 - 2 <a, b>, only dot products are left to compute.
+        dot_rows(vectors, feature, |i, dot| match expanded_distance(prepared[i], feature_norm, dot) {
+            Some(distance) => output[i] = (-gamma * distance).exp(),
+            None => inexact.push(i),
+        });
+
+        // Rows are visited in order, so `inexact` is sorted.
+        let feature: &[f32s] = &feature;
+
+        for (i, sv) in vectors.row_iter().enumerate().filter(|(i, _)| inexact.binary_search(i).is_ok()) {
+            let distance = sv.iter().zip(feature).fold(f32s::splat(0.0), |sum, (a, b)| sum + (*a - *b) * (*a - *b));
+            output[i] = (-gamma * f64::from(distance.sum())).exp();
+        }
+    }
+
+    fn compute_columns_prepared(&self, vectors: &SimdMatrix<f32s, ColumnOptimized>, prepared: &[f64], feature: &SimdVector<f32s>, output: &mut [f64]) {
+        let gamma = f64::from(self.gamma);
+        let feature_norm = squared_norm(feature);
+        let len = output.len().min(prepared.len());
+        let mut inexact = Vec::new();
+
+        dot_columns(vectors, feature, len, |i, dot| match expanded_distance(prepared[i], feature_norm, dot) {
+            Some(distance) => output[i] = (-gamma * distance).exp(),
+            None => inexact.push(i),
+        });
+
+        for i in inexact {
+            let (chunk, lane) = (i / f32s::lanes(), i % f32s::lanes());
+            let distance = vectors
+                .column_iter()
+                .zip(feature.flat())
+                .map(|(column, x)| f64::from(column[chunk].extract(lane) - *x).powi(2))
+                .sum::<f64>();
+
+            output[i] = (-gamma * distance).exp();
+        }
+    }
+
+    fn compute_self(&self, _feature: &SimdVector<f32s>) -> f64 { 1.0 }
+
+    fn compute_incremental(&self, vectors: &SimdMatrix<f32s, RowOptimized>, feature: &SimdVector<f32s>, previous: &SimdVector<f32s>, changed: &[usize], output: &mut [f64]) -> bool {
+        let feature_simd: &[f32s] = &feature;
+        let feature = feature.flat();
+        let previous = previous.flat();
+
+        // Since exp(a + b) = exp(a) * exp(b), we can scale the previous kernel value by the change of
+        // the squared distance caused by the modified attributes.
+        for (i, sv) in vectors.row_iter().enumerate() {
+            // If the old value underflowed there is nothing left to scale, so compute it from scratch.
+            if output[i] == 0.0 {
+                let mut sum = f32s::splat(0.0);
+
+                for (a, b) in sv.iter().zip(feature_simd) {
+                    sum += (*a - *b) * (*a - *b);
+                }
+
+                output[i] = f64::from((-self.gamma * sum.sum()).exp());
+                continue;
+            }
+
+            let mut delta = 0.0;
+
+            for &j in changed {
+                let x = sv[j / f32s::lanes()].extract(j % f32s::lanes());
+                delta += (x - feature[j]) * (x - feature[j]) - (x - previous[j]) * (x - previous[j]);
+            }
+
+            output[i] *= f64::from((-self.gamma * delta).exp());
+        }
+
+        true
+    }
+
+    fn to_sparse(&self) -> Option<Box<dyn KernelSparse>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Rbf { gamma: self.gamma } }
 }
 
 impl KernelSparse for Rbf {
@@ -44,6 +175,7 @@ impl KernelSparse for Rbf {
 
             let (mut a, mut b) = (a_iter.next(), b_iter.next());
 
+            // Attributes present in only one vector still count towards the distance.
             output[i] = loop {
                 match (a, b) {
                     (Some((i_a, x)), Some((i_b, y))) if i_a == i_b => {
@@ -52,13 +184,46 @@ impl KernelSparse for Rbf {
                         a = a_iter.next();
                         b = b_iter.next();
                     }
-                    (Some((i_a, _)), Some((i_b, _))) if i_a < i_b => a = a_iter.next(),
-                    (Some((i_a, _)), Some((i_b, _))) if i_a > i_b => b = b_iter.next(),
-                    _ => break f64::from((-self.gamma * sum).exp()),
+                    (Some((i_a, x)), Some((i_b, _))) if i_a < i_b => {
+                        sum += x * x;
+                        a = a_iter.next();
+                    }
+                    (Some(_), Some((_, y))) => {
+                        sum += y * y;
+                        b = b_iter.next();
+                    }
+                    (Some((_, x)), None) => {
+                        sum += x * x;
+                        a = a_iter.next();
+                    }
+                    (None, Some((_, y))) => {
+                        sum += y * y;
+                        b = b_iter.next();
+                    }
+                    (None, None) => break f64::from((-self.gamma * sum).exp()),
                 }
             }
         }
     }
+
+    fn to_dense(&self) -> Option<Box<dyn KernelDense>> { Some(Box::new(*self)) }
+
+    fn parameters(&self) -> KernelParameters { KernelParameters::Rbf { gamma: self.gamma } }
+}
+
+/// Returns `|vector|^2`, accumulated in `f64`.
+fn squared_norm(vector: &[f32s]) -> f64 { vector.iter().flat_map(|x| (0 .. f32s::lanes()).map(move |lane| f64::from(x.extract(lane)).powi(2))).sum() }
+
+/// Returns `|a - b|^2` from `|a|^2`, `|b|^2` and `<a, b>`, or `None` if it would be too inexact.
+fn expanded_distance(a_norm: f64, b_norm: f64, dot: f32) -> Option<f64> {
+    let magnitude = a_norm + b_norm;
+    let distance = magnitude - 2.0 * f64::from(dot);
+
+    if distance < CANCELLATION * magnitude {
+        None
+    } else {
+        Some(distance)
+    }
 }
 
 impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for Rbf {
@@ -70,3 +235,58 @@ impl<'a, 'b> TryFrom<&'a ModelFile<'b>> for Rbf {
         Ok(Rbf { gamma })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{expanded_distance, squared_norm};
+    use crate::{svm::kernel::test_vectors, *};
+
+    #[test]
+    fn prepared() {
+        let rbf: &dyn KernelDense = &Rbf { gamma: 0.5 };
+        let rows: &[&[f32]] = &[&[0.5, -1.0, 2.0, 0.25, 1.5], &[-0.75, 0.5, 0.0, 1.0, -2.0], &[0.25, 0.5, -1.5, 1.0, 0.75]];
+        let (row_major, column_major, feature) = test_vectors(rows, &[0.25, 0.5, -1.5, 1.0, 0.75]);
+        let prepared = rbf.prepare(&row_major);
+
+        let mut expected = [0.0; 3];
+        let mut from_rows = [0.0; 3];
+        let mut from_columns = [0.0; 3];
+
+        rbf.compute(&row_major, &feature, &mut expected);
+        rbf.compute_prepared(&row_major, &prepared, &feature, &mut from_rows);
+        rbf.compute_columns_prepared(&column_major, &prepared, &feature, &mut from_columns);
+
+        for i in 0 .. 3 {
+            assert!((from_rows[i] - expected[i]).abs() < 1e-5);
+            assert!((from_columns[i] - expected[i]).abs() < 1e-5);
+        }
+
+        // The third support vector equals the feature.
+        assert_eq!((from_rows[2], from_columns[2]), (1.0, 1.0));
+    }
+
+    #[test]
+    fn near_equal() {
+        let rbf: &dyn KernelDense = &Rbf { gamma: 0.5 };
+        let rows: &[&[f32]] = &[&[1000.0, 1000.0, 1000.0, 1000.0], &[-1000.0, -1000.0, -1000.0, -1000.0]];
+        let values = [1000.0, 1000.0, 1000.0, 1000.01];
+        let (row_major, column_major, feature) = test_vectors(rows, &values);
+        let prepared = rbf.prepare(&row_major);
+
+        let dot = rows[0].iter().zip(&values).map(|(a, b)| a * b).sum::<f32>();
+
+        // Expanding |a - b|^2 in `f32` would be off by far more than the distance itself here.
+        assert_eq!(expanded_distance(prepared[0], squared_norm(&feature), dot), None);
+
+        let expected = rbf.parameters().compute_sequential(rows[0].iter().cloned(), &values);
+        let mut from_rows = [0.0; 2];
+        let mut from_columns = [0.0; 2];
+
+        rbf.compute_prepared(&row_major, &prepared, &feature, &mut from_rows);
+        rbf.compute_columns_prepared(&column_major, &prepared, &feature, &mut from_columns);
+
+        assert!(expected < 1.0 && expected > 0.9999);
+        assert!((from_rows[0] - expected).abs() < 1e-9);
+        assert!((from_columns[0] - expected).abs() < 1e-9);
+    }
+}
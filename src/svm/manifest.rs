@@ -0,0 +1,249 @@
+use std::str::FromStr;
+
+use crate::errors::Error;
+
+/// A value of a manifest entry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Value<'a> {
+    /// A string, without its quotes.
+    Text(&'a str),
+
+    /// A number, as written.
+    Number(&'a str),
+}
+
+/// A table of a [Manifest] holding `key = value` entries.
+#[derive(Debug, Default)]
+crate struct Table<'a> {
+    /// Name of the table, empty for the entries before the first table.
+    name: &'a str,
+
+    /// Whether the table is part of an array of tables `[[name]]`.
+    array: bool,
+
+    entries: Vec<(&'a str, Value<'a>)>,
+}
+
+/// A manifest describing a composition of models, like those of [Hierarchy] and [Pipeline].
+///
+/// # Description
+///
+/// Manifests are written in a subset of [TOML](https://toml.io):
+///
+/// ```text
+/// # Comments start with `#`.
+/// key = "string"
+///
+/// [table]
+/// 42 = 0.5
+///
+/// [[array]]
+/// key = "first"
+///
+/// [[array]]
+/// key = "second"
+/// ```
+///
+/// Keys are bare, values are either numbers or strings in double quotes without escapes. Nested
+/// tables, inline tables and arrays are not supported.
+#[derive(Debug)]
+crate struct Manifest<'a> {
+    /// All tables in order, starting with the unnamed table of the top level entries.
+    tables: Vec<Table<'a>>,
+}
+
+impl<'a> Manifest<'a> {
+    /// Parses a manifest, returns an [Error::ParsingError] naming the first invalid line.
+    crate fn parse(input: &'a str) -> Result<Self, Error> {
+        let mut tables = vec![Table::default()];
+
+        for (number, line) in input.lines().enumerate() {
+            let line = without_comment(line).trim();
+            let invalid = || Error::ParsingError(format!("Invalid manifest line {}: {}", number + 1, line));
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with("[[") && line.ends_with("]]") {
+                let name = bare_key(&line[2 .. line.len() - 2]).ok_or_else(invalid)?;
+
+                if tables.iter().any(|t| t.name == name && !t.array) {
+                    return Err(invalid());
+                }
+
+                tables.push(Table {
+                    name,
+                    array: true,
+                    entries: Vec::new(),
+                });
+            } else if line.starts_with('[') && line.ends_with(']') {
+                let name = bare_key(&line[1 .. line.len() - 1]).ok_or_else(invalid)?;
+
+                // Unlike arrays of tables, tables can only be defined once.
+                if tables.iter().any(|t| t.name == name) {
+                    return Err(invalid());
+                }
+
+                tables.push(Table {
+                    name,
+                    array: false,
+                    entries: Vec::new(),
+                });
+            } else {
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().and_then(bare_key).ok_or_else(invalid)?;
+                let value = parts.next().and_then(parse_value).ok_or_else(invalid)?;
+                let table = tables.last_mut()?;
+
+                if table.get(key).is_some() {
+                    return Err(invalid());
+                }
+
+                table.entries.push((key, value));
+            }
+        }
+
+        Ok(Manifest { tables })
+    }
+
+    /// Returns the unnamed table of the top level entries.
+    crate fn root(&self) -> &Table<'a> { &self.tables[0] }
+
+    /// Returns the table `[name]`, if present.
+    crate fn table(&self, name: &str) -> Option<&Table<'a>> { self.tables.iter().skip(1).find(|t| t.name == name && !t.array) }
+
+    /// Returns all tables of the array `[[name]]` in order.
+    crate fn tables<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b Table<'a>> + 'b { self.tables.iter().skip(1).filter(move |t| t.name == name && t.array) }
+
+    /// Fails if the manifest has tables with other names than `names`.
+    crate fn expect_tables(&self, names: &[&str]) -> Result<(), Error> {
+        match self.tables.iter().skip(1).find(|t| !names.contains(&t.name)) {
+            Some(table) => Err(Error::ParsingError(format!("Manifest has an unknown table `{}`", table.name))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Table<'a> {
+    fn get(&self, key: &str) -> Option<Value<'a>> { self.entries.iter().find(|(k, _)| *k == key).map(|(_, v)| *v) }
+
+    fn invalid(&self, key: &str) -> Error { Error::ParsingError(format!("Manifest has an invalid `{}` in `[{}]`", key, self.name)) }
+
+    fn missing(&self, key: &str) -> Error { Error::ParsingError(format!("Manifest has no `{}` in `[{}]`", key, self.name)) }
+
+    /// Returns the string `key`, if present.
+    crate fn text(&self, key: &str) -> Result<Option<&'a str>, Error> {
+        match self.get(key) {
+            Some(Value::Text(text)) => Ok(Some(text)),
+            Some(Value::Number(_)) => Err(self.invalid(key)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the number `key`, if present.
+    crate fn number<T: FromStr>(&self, key: &str) -> Result<Option<T>, Error> {
+        match self.get(key) {
+            Some(Value::Number(number)) => number.parse::<T>().map(Some).map_err(|_| self.invalid(key)),
+            Some(Value::Text(_)) => Err(self.invalid(key)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the string `key`, fails if it is not present.
+    crate fn require_text(&self, key: &str) -> Result<&'a str, Error> { self.text(key)?.ok_or_else(|| self.missing(key)) }
+
+    /// Returns the number `key`, fails if it is not present.
+    crate fn require_number<T: FromStr>(&self, key: &str) -> Result<T, Error> { self.number(key)?.ok_or_else(|| self.missing(key)) }
+
+    /// Returns all entries of the table, which must all be strings.
+    crate fn texts(&self) -> Result<Vec<(&'a str, &'a str)>, Error> { self.entries.iter().map(|(key, _)| Ok((*key, self.require_text(key)?))).collect() }
+
+    /// Fails if the table has other keys than `keys`.
+    crate fn expect_keys(&self, keys: &[&str]) -> Result<(), Error> {
+        match self.entries.iter().find(|(key, _)| !keys.contains(key)) {
+            Some((key, _)) => Err(Error::ParsingError(format!("Manifest has an unknown `{}` in `[{}]`", key, self.name))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns `line` up to a `#` that is not part of a string.
+fn without_comment(line: &str) -> &str {
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[.. i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Returns `key` if it is a valid bare key.
+fn bare_key(key: &str) -> Option<&str> {
+    let key = key.trim();
+
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        Some(key)
+    } else {
+        None
+    }
+}
+
+/// Parses a value, returns `None` if it is not supported.
+fn parse_value(value: &str) -> Option<Value<'_>> {
+    let value = value.trim();
+
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let text = &value[1 .. value.len() - 1];
+
+        if text.contains(|c| c == '"' || c == '\\') {
+            None
+        } else {
+            Some(Value::Text(text))
+        }
+    } else if value.parse::<f64>().is_ok() {
+        Some(Value::Number(value))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+    use crate::*;
+
+    #[test]
+    fn parse() -> Result<(), Error> {
+        let manifest = Manifest::parse("# Comment\nname = \"a # b\" # Comment\n\n[labels]\n42 = \"blink\"\n\n[[stage]]\nmargin = 0.5\n\n[[stage]]\nmargin = -1\n")?;
+
+        assert_eq!(manifest.root().text("name")?, Some("a # b"));
+        assert_eq!(manifest.table("labels")?.texts()?, vec![("42", "blink")]);
+        assert_eq!(
+            manifest.tables("stage").map(|t| t.require_number::<f64>("margin")).collect::<Result<Vec<_>, Error>>()?,
+            vec![0.5, -1.0]
+        );
+
+        assert!(manifest.root().number::<f64>("name").is_err());
+        assert!(manifest.root().require_text("other").is_err());
+        assert!(manifest.expect_tables(&["labels"]).is_err());
+
+        for invalid in &[
+            "name = unquoted",
+            "name = \"a\"\nname = \"b\"",
+            "[a]\n[a]",
+            "[[a]]\n[a]",
+            "key with space = 1",
+            "name = \"a\\\"\"",
+        ] {
+            assert!(Manifest::parse(invalid).is_err());
+        }
+
+        Ok(())
+    }
+}
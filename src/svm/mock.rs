@@ -0,0 +1,196 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    errors::Error,
+    sparse::SparseVector,
+    svm::{
+        introspect::Introspect,
+        predict::Predict,
+        problem::{DenseProblem, ProbabilityKind, Problem, Solution, SparseProblem},
+    },
+    util::set_all,
+};
+
+/// What a [MockSvm] predicts.
+pub enum Script {
+    /// Always predicts the same solution.
+    Fixed(Solution),
+
+    /// Predicts the solutions in order, starting over after the last one.
+    Sequence(Vec<Solution>),
+
+    /// Predicts the solution returned for the features of the problem.
+    Function(Box<dyn Fn(&[f32]) -> Solution + Send + Sync>),
+}
+
+/// A predictor with scripted solutions, to unit test code using FFSVM without a model.
+///
+/// # Description
+///
+/// `MockSvm` implements [Predict] for dense and sparse problems, and [Introspect], so it can
+/// stand in for a [DenseSVM] or [SparseSVM] in generic code or as [DensePredict]. Each prediction
+/// sets [Problem::solution] according to the [Script]. Probability predictions additionally set
+/// a probability of `1` for the predicted label and `0` for all others.
+///
+/// ```rust
+/// use ffsvm::*;
+///
+/// fn main() -> Result<(), Error> {
+///     let mock = MockSvm::new(&[0, 1], 4, Script::Sequence(vec![Solution::Label(1), Solution::Label(0)]));
+///     let mut problem = DenseProblem::from(&mock);
+///
+///     mock.predict_value(&mut problem)?;
+///     assert_eq!(problem.solution(), Solution::Label(1));
+///
+///     mock.predict_value(&mut problem)?;
+///     assert_eq!(problem.solution(), Solution::Label(0));
+///     assert_eq!(mock.calls(), 2);
+///
+///     Ok(())
+/// }
+/// ```
+pub struct MockSvm {
+    labels: Vec<i64>,
+    num_attributes: usize,
+    script: Script,
+
+    /// Number of predictions made, also the position in a [Script::Sequence].
+    calls: AtomicUsize,
+}
+
+impl MockSvm {
+    /// Creates a mock classifying problems with `attributes` features into `labels` as scripted.
+    pub fn new(labels: &[i64], attributes: usize, script: Script) -> Self {
+        MockSvm {
+            labels: labels.to_vec(),
+            num_attributes: attributes,
+            script,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of predictions made, including those of batch predictions.
+    pub fn calls(&self) -> usize { self.calls.load(Ordering::SeqCst) }
+
+    /// Restarts a [Script::Sequence] and sets [MockSvm::calls] to `0`.
+    pub fn reset(&self) { self.calls.store(0, Ordering::SeqCst); }
+
+    /// Predicts `problem` with the given `features` as scripted.
+    fn solve<V32>(&self, problem: &mut Problem<V32>, features: &[f32], probabilities: bool) -> Result<(), Error> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+
+        let solution = match &self.script {
+            Script::Fixed(solution) => *solution,
+            Script::Sequence(solutions) if solutions.is_empty() => Solution::None,
+            Script::Sequence(solutions) => solutions[call % solutions.len()],
+            Script::Function(f) => f(features),
+        };
+
+        if let Solution::Label(label) = solution {
+            if !self.labels.contains(&label) {
+                return Err(Error::UnknownLabel(label));
+            }
+        }
+
+        problem.result = solution;
+
+        if probabilities {
+            let flat = problem.probabilities.flat_mut();
+
+            set_all(flat, 0.0);
+
+            if let Solution::Label(label) = solution {
+                flat[self.labels.iter().position(|l| *l == label).unwrap()] = 1.0;
+            }
+
+            problem.probability_kind = ProbabilityKind::Calibrated;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the features of a sparse problem as dense values.
+    fn dense_features(&self, problem: &SparseProblem) -> Vec<f32> { (0 .. self.num_attributes).map(|i| problem.features[i]).collect() }
+}
+
+impl fmt::Debug for MockSvm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "MockSvm {{ labels: {:?}, calls: {} }}", self.labels, self.calls()) }
+}
+
+impl Predict<SimdVector<f32s>, SimdVector<f64s>> for MockSvm {
+    fn predict_value(&self, problem: &mut DenseProblem) -> Result<(), Error> {
+        let features = problem.features.as_raw().flat()[.. self.num_attributes].to_vec();
+        self.solve(problem, &features, false)
+    }
+
+    fn predict_probability(&self, problem: &mut DenseProblem) -> Result<(), Error> {
+        let features = problem.features.as_raw().flat()[.. self.num_attributes].to_vec();
+        self.solve(problem, &features, true)
+    }
+
+    fn problem(&self) -> DenseProblem { DenseProblem::from(self) }
+}
+
+impl Predict<SparseVector<f32>, SparseVector<f64>> for MockSvm {
+    fn predict_value(&self, problem: &mut SparseProblem) -> Result<(), Error> {
+        let features = self.dense_features(problem);
+        self.solve(problem, &features, false)
+    }
+
+    fn predict_probability(&self, problem: &mut SparseProblem) -> Result<(), Error> {
+        let features = self.dense_features(problem);
+        self.solve(problem, &features, true)
+    }
+
+    fn problem(&self) -> SparseProblem { SparseProblem::from(self) }
+}
+
+impl Introspect for MockSvm {
+    fn attributes(&self) -> usize { self.num_attributes }
+
+    fn classes(&self) -> usize { self.labels.len() }
+
+    fn labels(&self) -> Vec<i64> { self.labels.clone() }
+}
+
+impl<'a> From<&'a MockSvm> for DenseProblem {
+    fn from(mock: &MockSvm) -> Self { Problem::<SimdVector<f32s>>::with_dimension(0, mock.labels.len(), mock.num_attributes, mock.labels.clone()) }
+}
+
+impl<'a> From<&'a MockSvm> for SparseProblem {
+    fn from(mock: &MockSvm) -> Self { Problem::<SparseVector<f32>>::with_dimension(0, mock.labels.len(), mock.num_attributes, mock.labels.clone()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn scripted_solutions() -> Result<(), Error> {
+        let mock = MockSvm::new(&[3, 7], 2, Script::Function(Box::new(|f| Solution::Label(if f[1] > 0.5 { 7 } else { 3 }))));
+        let mut problem = DenseProblem::from(&mock);
+
+        problem.features()[1] = 1.0;
+        mock.predict_probability(&mut problem)?;
+
+        assert_eq!(problem.solution(), Solution::Label(7));
+        assert_eq!(problem.probabilities(), &[0.0, 1.0]);
+
+        let mut sparse = SparseProblem::from(&mock);
+        mock.predict_value(&mut sparse)?;
+        assert_eq!(sparse.solution(), Solution::Label(3));
+
+        let mut problems = vec![DenseProblem::from(&mock); 4];
+        mock.predict_values(&mut problems)?;
+        assert_eq!(mock.calls(), 6);
+
+        let unknown = MockSvm::new(&[3, 7], 2, Script::Fixed(Solution::Label(5)));
+        assert!(unknown.predict_value(&mut problem).is_err());
+
+        Ok(())
+    }
+}
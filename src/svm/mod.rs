@@ -1,16 +1,32 @@
+crate mod batch;
+crate mod cascade;
 crate mod class;
 crate mod core;
+crate mod ensemble;
+crate mod explain;
+crate mod hierarchy;
+crate mod introspect;
 crate mod kernel;
+crate mod manifest;
+crate mod mock;
+crate mod observer;
+crate mod pipeline;
 crate mod predict;
 crate mod problem;
+crate mod reload;
+crate mod sensitivity;
+crate mod settings;
+crate mod stats;
+crate mod stream;
 
 use self::kernel::{KernelDense, KernelSparse};
 use crate::{
+    dense::DenseMatrix,
     sparse::{SparseMatrix, SparseVector},
     vectors::Triangular,
 };
 
-use simd_aligned::{f32s, f64s, RowOptimized, SimdMatrix, SimdVector};
+use simd_aligned::{f32s, f64s, SimdVector};
 
 #[derive(Clone, Debug, Default)]
 crate struct Probabilities {
@@ -21,6 +37,7 @@ crate struct Probabilities {
 
 /// Classifier type.
 #[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum SVMType {
     CSvc,
     NuSvc,
@@ -29,7 +46,7 @@ pub enum SVMType {
 }
 
 /// **Start here** to classify dense models with highest performance.
-pub type DenseSVM = core::SVMCore<dyn KernelDense, SimdMatrix<f32s, RowOptimized>, SimdVector<f32s>, SimdVector<f64s>>;
+pub type DenseSVM = core::SVMCore<dyn KernelDense, DenseMatrix, SimdVector<f32s>, SimdVector<f64s>>;
 
 /// Use this to load any `libSVM` model with normal performance.
 pub type SparseSVM = core::SVMCore<dyn KernelSparse, SparseMatrix<f32>, SparseVector<f32>, SparseVector<f64>>;
@@ -0,0 +1,59 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::svm::problem::Solution;
+
+/// Summary of a single prediction, passed to a [PredictObserver].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// How long the prediction took.
+    pub duration: Duration,
+
+    /// The solution of the prediction.
+    pub solution: Solution,
+
+    /// The highest of the [Problem::probabilities], if probabilities were predicted.
+    pub probability: Option<f64>,
+
+    /// The [Problem::margin], if the model is a binary classifier.
+    pub margin: Option<f64>,
+}
+
+/// Receives an [Observation] after every prediction of a SVM, see [SVMCore::set_observer].
+///
+/// This is meant to feed metrics like latency histograms or confidence distributions without
+/// wrapping every call site. Observers are called on the predicting thread, so they should be
+/// cheap and must not block. Any `Fn(&Observation)` closure is an observer:
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::{convert::TryFrom, sync::Arc};
+///
+/// fn main() -> Result<(), Error> {
+///     let mut svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+///
+///     svm.set_observer(Some(Arc::new(|o: &Observation| println!("{:?} in {:?}", o.solution, o.duration))));
+///
+///     Ok(())
+/// }
+/// ```
+pub trait PredictObserver: Send + Sync {
+    /// Called after each successful prediction.
+    fn observe(&self, observation: &Observation);
+}
+
+impl<F> PredictObserver for F
+where
+    F: Fn(&Observation) + Send + Sync,
+{
+    fn observe(&self, observation: &Observation) { self(observation) }
+}
+
+/// The observer of a SVM, a wrapper since trait objects are no [fmt::Debug].
+#[derive(Clone)]
+crate struct Observer(crate Arc<dyn PredictObserver>);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "Observer") }
+}
@@ -0,0 +1,408 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+use std::{convert::TryFrom, fs, path::Path};
+
+use crate::{
+    errors::Error,
+    svm::{
+        cascade::Cascade,
+        ensemble::{load_features, Ensemble, Voting},
+        introspect::Introspect,
+        manifest::Manifest,
+        predict::{DensePredict, Predict},
+        problem::{DenseProblem, Problem},
+        settings::Reject,
+        DenseSVM,
+    },
+};
+
+/// Scales features to the ranges a model was trained on, like libSVM's `svm-scale`.
+///
+/// # Description
+///
+/// Scalers are created from the range files `svm-scale -s` saves, which look like this:
+///
+/// ```text
+/// x
+/// -1 1
+/// 0 0.5 12.5
+/// 1 -3 3
+/// ```
+///
+/// The second line holds the target range, each following line the index of a feature and the
+/// range it had in the training data. The feature with index `i` is feature `i` of a problem, as
+/// with the attributes of a model. Features that were constant during training are set to `0`,
+/// as `svm-scale` omits them. Features without a line are left as they are. A `y` section for
+/// regression targets is ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scaler {
+    lower: f32,
+    upper: f32,
+
+    /// Training range of each feature, if it has one.
+    ranges: Vec<Option<(f32, f32)>>,
+}
+
+impl Scaler {
+    /// Scales the `features` of one problem in place.
+    pub fn scale(&self, features: &mut [f32]) {
+        for (feature, range) in features.iter_mut().zip(&self.ranges) {
+            *feature = match *range {
+                None => *feature,
+                Some((min, max)) if min == max => 0.0,
+                Some((min, _)) if *feature <= min => self.lower,
+                Some((_, max)) if *feature >= max => self.upper,
+                Some((min, max)) => self.lower + (self.upper - self.lower) * (*feature - min) / (max - min),
+            };
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Scaler {
+    type Error = Error;
+
+    fn try_from(input: &'a str) -> Result<Scaler, Error> {
+        let mut lines = input.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut target = None;
+        let mut ranges = Vec::new();
+
+        while let Some(line) = lines.next() {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+            match tokens.as_slice() {
+                ["x"] => target = lines.next().map(|l| l.split_whitespace().collect::<Vec<_>>()),
+                ["y"] => {
+                    lines.next();
+                    lines.next();
+                }
+                [index, min, max] => {
+                    let index = index.parse::<usize>()?;
+
+                    if ranges.len() <= index {
+                        ranges.resize(index + 1, None);
+                    }
+
+                    ranges[index] = Some((min.parse::<f32>()?, max.parse::<f32>()?));
+                }
+                _ => return Err(Error::ParsingError(format!("Invalid scaler line: {}", line))),
+            }
+        }
+
+        match target.as_ref().map(Vec::as_slice) {
+            Some([lower, upper]) => Ok(Scaler {
+                lower: lower.parse::<f32>()?,
+                upper: upper.parse::<f32>()?,
+                ranges,
+            }),
+            _ => Err(Error::ParsingError("Scaler has no `x` section".to_owned())),
+        }
+    }
+}
+
+/// The predictor a [Pipeline] passes scaled problems to.
+enum Graph {
+    Svm(DenseSVM),
+    Cascade(Cascade),
+    Ensemble(Ensemble),
+}
+
+impl Graph {
+    fn predictor(&self) -> &DensePredict {
+        match self {
+            Graph::Svm(svm) => svm,
+            Graph::Cascade(cascade) => cascade,
+            Graph::Ensemble(ensemble) => ensemble,
+        }
+    }
+
+    fn label_name(&self, label: i64) -> Option<&str> {
+        match self {
+            Graph::Svm(svm) => svm.label_name(label),
+            Graph::Cascade(cascade) => cascade.label_name(label),
+            Graph::Ensemble(ensemble) => ensemble.label_name(label),
+        }
+    }
+
+    fn introspect(&self) -> &dyn Introspect {
+        match self {
+            Graph::Svm(svm) => svm,
+            Graph::Cascade(cascade) => cascade,
+            Graph::Ensemble(ensemble) => ensemble,
+        }
+    }
+}
+
+/// A complete inference graph, scaling features and predicting them with one or more SVMs.
+///
+/// # Description
+///
+/// Products usually don't ship a lone model, but a scaler, a [Cascade] or [Ensemble] of models,
+/// and names for the labels, and used to glue these together by hand. A pipeline is loaded from
+/// a manifest describing all of it, and predicts like a single [DenseSVM]: the features of a
+/// problem are scaled (if there is a scaler) and passed on to the models. Problems must be
+/// created via `Problem::from(&pipeline)`.
+///
+/// # Manifest
+///
+/// Pipelines are loaded via [Pipeline::from_manifest_file] or [Pipeline::from_manifest] from a
+/// manifest in the same subset of TOML as [Hierarchy] manifests, like this:
+///
+/// ```text
+/// # Lines starting with `#` are comments.
+/// scaler = "gaze.range"
+///
+/// # Either one `model`, `stage`s of a cascade, or `member`s of an ensemble.
+/// [[stage]]
+/// model = "fast.model"
+/// margin = 0.5
+///
+/// [[stage]]
+/// model = "full.model"
+/// probability = 0.8
+///
+/// [[stage]]
+/// model = "final.model"
+///
+/// [labels]
+/// 21 = "open"
+/// 42 = "blink"
+/// ```
+///
+/// * `scaler` names a range file, see [Scaler].
+/// * `model` names the only model of the pipeline.
+/// * `[[stage]]` adds a `model` to a [Cascade], with an optional threshold `margin` (see
+///   [Reject::BelowMargin]) or `probability` (see [Reject::BelowProbability]).
+/// * `[[member]]` adds a `model` to an [Ensemble], which combines them by `voting = "majority"`
+///   (default) or `voting = "probabilities"`. Manifests without members can't have `voting`.
+/// * `[labels]` names labels, which are set on all models via [SVMCore::set_label_names].
+pub struct Pipeline {
+    scaler: Option<Scaler>,
+    graph: Graph,
+}
+
+impl Pipeline {
+    /// Loads the pipeline described by the manifest file at `path`.
+    ///
+    /// Files named in the manifest are read relative to the directory of the manifest.
+    pub fn from_manifest_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let origin = path.display().to_string();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let manifest = fs::read_to_string(path).map_err(|e| Error::from(e).with_origin(&origin))?;
+
+        Pipeline::from_manifest(&manifest, |name| {
+            let file = directory.join(name);
+            fs::read_to_string(&file).map_err(|e| Error::from(e).with_origin(&file.display().to_string()))
+        })
+    }
+
+    /// Loads the pipeline described by `manifest`, using `read` to obtain the text of each file named.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::ParsingError] if the manifest is malformed, any error returned by `read` or when
+    /// parsing a file, an [Error::UnknownLabel] if a label is named that a model does not have, or
+    /// any error of [Cascade::add_stage] or [Ensemble::add].
+    pub fn from_manifest<F>(manifest: &str, mut read: F) -> Result<Self, Error>
+    where
+        F: FnMut(&str) -> Result<String, Error>,
+    {
+        let manifest = Manifest::parse(manifest)?;
+        let root = manifest.root();
+
+        manifest.expect_tables(&["stage", "member", "labels"])?;
+        root.expect_keys(&["scaler", "model", "voting"])?;
+
+        let label_names = match manifest.table("labels") {
+            Some(table) => table
+                .texts()?
+                .into_iter()
+                .map(|(label, name)| Ok((label.parse::<i64>()?, name)))
+                .collect::<Result<Vec<_>, Error>>()?,
+            None => Vec::new(),
+        };
+
+        let mut load = |name: &str| -> Result<DenseSVM, Error> {
+            let mut svm = DenseSVM::try_from(read(name)?.as_str()).map_err(|e| e.with_origin(name))?;
+
+            // Names from the manifest replace those of the model file.
+            if !label_names.is_empty() {
+                svm.set_label_names(Some(&label_names)).map_err(|e| e.with_origin(name))?;
+            }
+
+            Ok(svm)
+        };
+
+        let model = match root.text("model")? {
+            Some(name) => Some(load(name)?),
+            None => None,
+        };
+
+        let stages = manifest
+            .tables("stage")
+            .map(|table| {
+                table.expect_keys(&["model", "margin", "probability"])?;
+
+                let threshold = match (table.number::<f64>("margin")?, table.number::<f64>("probability")?) {
+                    (None, None) => Reject::Never,
+                    (Some(margin), None) => Reject::BelowMargin(margin),
+                    (None, Some(p)) => Reject::BelowProbability(p),
+                    _ => return Err(Error::ParsingError("Manifest has a `stage` with both `margin` and `probability`".to_owned())),
+                };
+
+                Ok((load(table.require_text("model")?)?, threshold))
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        let members = manifest
+            .tables("member")
+            .map(|table| {
+                table.expect_keys(&["model"])?;
+                load(table.require_text("model")?)
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        // Only ensembles vote, so `voting` would otherwise be silently ignored.
+        if members.is_empty() && root.text("voting")?.is_some() {
+            return Err(Error::ParsingError("Manifest has a `voting` but no `member`s".to_owned()));
+        }
+
+        let voting = match root.text("voting")? {
+            None | Some("majority") => Voting::Majority,
+            Some("probabilities") => Voting::AverageProbabilities,
+            Some(voting) => return Err(Error::ParsingError(format!("Manifest has an unknown `voting` of `{}`", voting))),
+        };
+
+        let graph = match (model, stages.is_empty(), members.is_empty()) {
+            (Some(svm), true, true) => Graph::Svm(svm),
+            (None, false, true) => {
+                let mut cascade = Cascade::new();
+
+                for (svm, threshold) in stages {
+                    cascade.add_stage(svm, threshold)?;
+                }
+
+                Graph::Cascade(cascade)
+            }
+            (None, true, false) => {
+                let mut ensemble = Ensemble::new(voting);
+
+                for svm in members {
+                    ensemble.add(svm, None)?;
+                }
+
+                Graph::Ensemble(ensemble)
+            }
+            _ => return Err(Error::ParsingError("Manifest needs either one `model`, `stage`s or `member`s".to_owned())),
+        };
+
+        let scaler = match root.text("scaler")? {
+            Some(name) => Some(Scaler::try_from(read(name)?.as_str()).map_err(|e| e.with_origin(name))?),
+            None => None,
+        };
+
+        Ok(Pipeline { scaler, graph })
+    }
+
+    /// Returns the scaler of this pipeline, if any.
+    pub fn scaler(&self) -> Option<&Scaler> { self.scaler.as_ref() }
+
+    /// Returns the name of `label` the models were given by the manifest or their model files, if any.
+    pub fn label_name(&self, label: i64) -> Option<&str> { self.graph.label_name(label) }
+
+    /// Scales the features of `problem` into its member and predicts that.
+    fn predict(&self, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        let member = problem.members.first_mut()?;
+
+        load_features(problem.features.as_raw().flat(), member, None);
+
+        if let Some(scaler) = &self.scaler {
+            scaler.scale(member.features().as_slice_mut());
+        }
+
+        if with_probabilities {
+            self.graph.predictor().predict_probability(member)?;
+        } else {
+            self.graph.predictor().predict_value(member)?;
+        }
+
+        problem.vote.copy_from_slice(&member.vote);
+        problem.probabilities.flat_mut().copy_from_slice(member.probabilities.flat());
+        problem.probability_kind = member.probability_kind;
+        problem.result = member.result;
+
+        Ok(())
+    }
+}
+
+impl Predict<SimdVector<f32s>, SimdVector<f64s>> for Pipeline {
+    fn predict_value(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, false) }
+
+    fn predict_probability(&self, problem: &mut DenseProblem) -> Result<(), Error> { self.predict(problem, true) }
+
+    fn problem(&self) -> DenseProblem { DenseProblem::from(self) }
+}
+
+impl Introspect for Pipeline {
+    fn attributes(&self) -> usize { self.graph.introspect().attributes() }
+
+    fn classes(&self) -> usize { self.graph.introspect().classes() }
+
+    fn labels(&self) -> Vec<i64> { self.graph.introspect().labels() }
+}
+
+impl<'a> From<&'a Pipeline> for DenseProblem {
+    fn from(pipeline: &Pipeline) -> Self {
+        let mut problem = Problem::<SimdVector<f32s>>::with_dimension(0, pipeline.classes(), pipeline.attributes(), pipeline.labels());
+        problem.members.push(pipeline.graph.predictor().problem());
+        problem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn from_manifest() -> Result<(), Error> {
+        let manifest = "# Sample\nscaler = \"sample.range\"\n\n[[stage]]\nmodel = \"fast.model\"\nmargin = 0.5\n\n[[stage]]\nmodel = \"full.model\"\n\n[labels]\n42 = \"blink\"\n";
+        let read = |name: &str| match name {
+            "sample.range" => Ok("x\n-1 1\n0 0 1\n".to_owned()),
+            _ => Ok(SAMPLE_MODEL.to_owned()),
+        };
+
+        let pipeline = Pipeline::from_manifest(manifest, read)?;
+        let mut problem = Problem::from(&pipeline);
+        let features = problem.features();
+
+        // Scaled from [0, 1] to [-1, 1], this is feature 0 of the sample.
+        features[0] = 0.77919;
+        features[1] = -0.157895;
+        features[2] = 0.581292;
+        features[3] = -0.221184;
+
+        pipeline.predict_value(&mut problem)?;
+
+        assert_eq!(problem.solution(), Solution::Label(42));
+        assert_eq!(pipeline.label_name(42), Some("blink"));
+        assert_eq!(pipeline.label_name(21), None);
+
+        let ensemble = Pipeline::from_manifest("voting = \"probabilities\"\n[[member]]\nmodel = \"a.model\"\n[[member]]\nmodel = \"b.model\"\n", read)?;
+        assert_eq!(ensemble.classes(), 2);
+
+        assert!(Pipeline::from_manifest("model = \"a.model\"\n[[member]]\nmodel = \"b.model\"\n", read).is_err());
+        assert!(Pipeline::from_manifest("model = \"a.model\"\n[labels]\n7 = \"unknown\"\n", read).is_err());
+        assert!(Pipeline::from_manifest("model = \"a.model\"\nvoting = \"veto\"\n", read).is_err());
+        assert!(Pipeline::from_manifest("model = \"a.model\"\nvoting = \"probabilities\"\n", read).is_err());
+        assert!(Pipeline::from_manifest("voting = \"majority\"\n[[stage]]\nmodel = \"a.model\"\n", read).is_err());
+        assert!(Pipeline::from_manifest("[[stage]]\nmodel = \"a.model\"\nmargin = 0.5\nprobability = 0.5\n", read).is_err());
+        assert!(Pipeline::from_manifest("scaler = \"sample.range\"\n", read).is_err());
+
+        let scaler = Scaler::try_from("y\n0 1\n5 10\nx\n0 10\n0 -1 1\n1 3 3\n")?;
+        let mut values = [0.0, 3.0, 7.0];
+
+        scaler.scale(&mut values);
+        assert_eq!(values, [5.0, 0.0, 7.0]);
+
+        Ok(())
+    }
+}
@@ -1,4 +1,17 @@
-use crate::{errors::Error, svm::problem::Problem};
+use rayon::prelude::*;
+use simd_aligned::{f32s, f64s, SimdVector};
+
+use crate::{
+    errors::Error,
+    sparse::SparseVector,
+    svm::{problem::Problem, stream::PredictIter},
+};
+
+/// Any predictor of dense problems as trait object, see [Predict].
+pub type DensePredict = dyn Predict<SimdVector<f32s>, SimdVector<f64s>>;
+
+/// Any predictor of sparse problems as trait object, see [Predict].
+pub type SparsePredict = dyn Predict<SparseVector<f32>, SparseVector<f64>>;
 
 /// Implemented by [DenseSVM] and [SparseSVM] to predict a [Problem].
 ///
@@ -36,6 +49,34 @@ use crate::{errors::Error, svm::problem::Problem};
 /// will be updated accordingly. The class labels for each probablity entry can be obtained
 /// by the [SVMCore::class_label_for_index] and [SVMCore::class_index_for_label] methods.
 ///
+/// # Trait objects
+///
+/// `Predict` is object safe, so different predictors can be kept in one registry. All predictors
+/// of dense problems, i.e., [DenseSVM], [Ensemble], [Cascade], [Hierarchy] and a [ReloadableSvm] of
+/// a [DenseSVM], can be used as [DensePredict]. [SparseSVM]s need a different [Problem] type and
+/// are used as [SparsePredict] instead. Problems are created via [Predict::problem]:
+///
+/// ```
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let mut cascade = Cascade::new();
+///     cascade.add_stage(DenseSVM::try_from(SAMPLE_MODEL)?, Reject::Never)?;
+///
+///     let registry: Vec<Box<DensePredict>> = vec![Box::new(DenseSVM::try_from(SAMPLE_MODEL)?), Box::new(cascade)];
+///
+///     for predictor in &registry {
+///         let mut problem = predictor.problem();
+///         predictor.predict_value(&mut problem)?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
 pub trait Predict<V32, V64>
 where
     Self: Sync,
@@ -52,4 +93,46 @@ where
     /// both [Problem::solution] will be set, and all [Problem::probabilities] will
     /// be available accordingly.
     fn predict_probability(&self, problem: &mut Problem<V32>) -> Result<(), Error>;
+
+    /// Creates a new [Problem] for this predictor, same as `Problem::from(self)`.
+    fn problem(&self) -> Problem<V32>;
+
+    /// Predicts all [Problem]s in parallel.
+    ///
+    /// This is the batch version of [Predict::predict_value] and uses [Rayon](https://github.com/rayon-rs/rayon)
+    /// to distribute the problems over all available cores. SVMs run on the threads configured via
+    /// [SVMCore::set_threads] or [SVMCore::set_thread_pool], other predictors on the current pool,
+    /// so call them within `ThreadPool::install` to use a pool of your own. Once this method returns,
+    /// each [Problem::solution] will be set. If any problem fails, one of the errors is returned.
+    fn predict_values(&self, problems: &mut [Problem<V32>]) -> Result<(), Error>
+    where
+        V32: Send,
+    {
+        problems.par_iter_mut().map(|problem| self.predict_value(problem)).collect()
+    }
+
+    /// Predicts all [Problem]s with probabilities in parallel.
+    ///
+    /// This is the batch version of [Predict::predict_probability]. Once this method returns,
+    /// each [Problem::solution] and [Problem::probabilities] will be set.
+    fn predict_probabilities(&self, problems: &mut [Problem<V32>]) -> Result<(), Error>
+    where
+        V32: Send,
+    {
+        problems.par_iter_mut().map(|problem| self.predict_probability(problem)).collect()
+    }
+
+    /// Returns an iterator predicting the value for each item of a stream of dense `features`.
+    ///
+    /// Each item holds the features of one problem, for example a `Vec<f32>` or `[f32; 4]`.
+    /// See [PredictIter] for how problems are reused, and [PredictIter::lookahead] to predict
+    /// several items at once. For trait objects use [PredictIter::new] instead.
+    fn predict_iter<I>(&self, features: I) -> PredictIter<'_, Self, I::IntoIter>
+    where
+        Self: Sized + Predict<SimdVector<f32s>, SimdVector<f64s>>,
+        I: IntoIterator,
+        I::Item: AsRef<[f32]>,
+    {
+        PredictIter::new(self, features.into_iter())
+    }
 }
@@ -1,8 +1,13 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    cmp::Ordering,
+    ops::{Index, IndexMut},
+};
 
 use crate::{
+    errors::Error,
     sparse::SparseVector,
-    svm::{DenseSVM, SparseSVM},
+    svm::{stats::PredictStats, DenseSVM, SparseSVM},
+    util::Pages,
     vectors::Triangular,
 };
 
@@ -22,15 +27,99 @@ pub type SparseProblem = Problem<SparseVector<f32>>;
 #[derive(Copy, Debug, Clone, PartialEq)]
 pub enum Solution {
     /// If classified this will hold the label.
-    Label(u32),
+    Label(i64),
 
     /// If regression was performed contains regression result.
     Value(f32),
 
+    /// If classified, but several classes received the same number of votes and the
+    /// SVM was configured with [TieBreak::Ambiguous].
+    Ambiguous,
+
+    /// If classified, but the result was not confident enough for the SVM's [Reject] policy.
+    Rejected,
+
+    /// If classified with multiple labels, see [SVMCore::set_multi_label]. The labels
+    /// can be obtained from [Problem::multi_label].
+    MultiLabel,
+
     /// No operation was performed yet.
     None,
 }
 
+/// How the [Problem::probabilities] of a problem were obtained.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum ProbabilityKind {
+    /// Calibrated probability estimates from a model trained with `svm-train -b 1`, like libSVM.
+    Calibrated,
+
+    /// Uncalibrated softmax scores of a model without probability estimates, see [SVMCore::set_pseudo_probabilities].
+    Pseudo,
+
+    /// No probabilities were predicted yet.
+    None,
+}
+
+/// Summary of the last classification of a [Problem], see [Problem::outcome].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Outcome {
+    /// The solution, same as [Problem::solution].
+    pub solution: Solution,
+
+    /// The best label, even if the solution is [Solution::Rejected], [Solution::Ambiguous] or
+    /// [Solution::MultiLabel]. `None` for regression and before the first prediction.
+    pub label: Option<i64>,
+
+    /// The probability of `label` as of the last [Predict::predict_probability], if there was one.
+    pub probability: Option<f64>,
+
+    /// How many more votes `label` received than the runner-up.
+    pub vote_margin: Option<u32>,
+
+    /// The margin of `label`, see [Problem::class_margins].
+    pub margin: Option<f64>,
+
+    /// How sure the model is, see [Problem::confidence].
+    pub confidence: Option<f64>,
+
+    /// If the solution is [Solution::Rejected].
+    pub rejected: bool,
+
+    /// If the solution is [Solution::Ambiguous].
+    pub ambiguous: bool,
+}
+
+/// Controls if a [Problem] may reuse the kernel values of its previous prediction.
+///
+/// Computing kernel values is by far the most expensive part of a prediction. If consecutive
+/// inputs are (almost) identical, for example in a sensor loop, they can be reused. The
+/// decision values, votes and probabilities are always recomputed.
+///
+/// # Invalidation Policy
+///
+/// * With [Cache::Tolerance] the cache is invalidated as soon as any feature differs by more
+/// than the tolerance from the features the kernel values were **last computed** with. Drifting
+/// features therefore can't accumulate a larger error than the tolerance.
+/// * With [Cache::Token] the cache is invalidated whenever the token differs from the token the
+/// kernel values were last computed with. Feature changes are ignored, it is up to you to
+/// change the token.
+/// * Calling [Problem::set_cache] or [Problem::invalidate_cache] always invalidates the cache.
+///
+/// Since the cache lives in the [Problem], you must not use a problem with an enabled cache
+/// with a different SVM than the one it was last predicted with. Linear [DenseSVM]s with folded
+/// weights don't compute kernel values at all, so the cache has no effect for them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cache {
+    /// Kernel values are recomputed for every prediction (default).
+    Disabled,
+
+    /// Reuse kernel values if no feature changed by more than the given amount.
+    Tolerance(f32),
+
+    /// Reuse kernel values as long as the token is unchanged.
+    Token(u64),
+}
+
 #[derive(Debug, Clone)]
 pub struct Features<V32> {
     data: V32,
@@ -103,22 +192,367 @@ pub struct Problem<V32> {
 
     /// Computed label that will be updated after this problem was processed.
     crate result: Solution,
+
+    /// How the `probabilities` were obtained.
+    crate probability_kind: ProbabilityKind,
+
+    /// Class labels of the SVM, in the order of `probabilities`.
+    crate labels: Vec<i64>,
+
+    /// Labels and probabilities above their threshold, if the SVM predicts multiple labels.
+    crate multi_label: Vec<(i64, f64)>,
+
+    /// If and how kernel values may be reused.
+    crate cache: Cache,
+
+    /// Features the current kernel values were computed with, if caching by tolerance.
+    crate cached_features: Option<V32>,
+
+    /// Token the current kernel values were computed with, if caching by token.
+    crate cached_token: Option<u64>,
+
+    /// Features the current kernel values were computed with by an incremental prediction.
+    crate previous_features: Option<V32>,
+
+    /// Number of support vectors of the model this problem was created for.
+    crate num_total_sv: usize,
+
+    /// Number of attributes of the model this problem was created for.
+    crate num_attributes: usize,
+
+    /// Problems of the members, if this problem was created for an [Ensemble], [Cascade] or [Hierarchy].
+    crate members: Vec<Problem<V32>>,
+
+    /// Which classes take part in the current prediction, all if `None`.
+    crate class_mask: Option<Vec<bool>>,
+
+    /// Timings and counters of the last prediction, if enabled.
+    crate stats: Option<PredictStats>,
+
+    /// Generation of the [ReloadableSvm] model the cached kernel values were computed with.
+    crate generation: Option<usize>,
 }
 
 impl<T> Problem<T> {
     /// After a [Problem] has been classified, this will hold the SVMs solution.
     pub fn solution(&self) -> Solution { self.result }
 
+    /// Returns timings and counters of the last prediction of this problem, or `None` if not
+    /// enabled via [SVMCore::set_predict_stats].
+    pub fn predict_stats(&self) -> Option<PredictStats> { self.stats }
+
+    /// Returns a summary of the last classification, so that the label, its probability and margins
+    /// don't have to be collected from several methods.
+    pub fn outcome(&self) -> Outcome {
+        let best = self.best_class();
+
+        let vote_margin = best.map(|best| {
+            let runner_up = (0 .. self.labels.len()).filter(|i| *i != best && self.class_enabled(*i)).map(|i| self.vote[i]).max();
+            self.vote[best].saturating_sub(runner_up.unwrap_or(0))
+        });
+
+        Outcome {
+            solution: self.result,
+            label: best.map(|i| self.labels[i]),
+            probability: best.filter(|_| self.probability_kind != ProbabilityKind::None).map(|i| self.probabilities.flat()[i]),
+            vote_margin,
+            margin: best.map(|i| self.class_margin(i)),
+            confidence: self.confidence(),
+            rejected: self.result == Solution::Rejected,
+            ambiguous: self.result == Solution::Ambiguous,
+        }
+    }
+
+    /// Returns the index of the best class of the last classification, if there was one.
+    crate fn best_class(&self) -> Option<usize> {
+        let enabled = (0 .. self.labels.len()).filter(|i| self.class_enabled(*i));
+
+        match self.result {
+            Solution::None | Solution::Value(_) => None,
+            Solution::Label(label) => self.labels.iter().position(|l| *l == label),
+            _ if self.probability_kind != ProbabilityKind::None => {
+                enabled.rev().max_by(|a, b| self.probabilities.flat()[*a].partial_cmp(&self.probabilities.flat()[*b]).unwrap_or(Ordering::Equal))
+            }
+            // Like libSVM, the first of the classes with the most votes.
+            _ => enabled.rev().max_by_key(|i| self.vote[*i]),
+        }
+    }
+
     /// Returns the probability estimates. Only really useful if the model was trained with probability estimates and you classified with them.
     pub fn probabilities(&self) -> &[f64] { self.probabilities.flat() }
 
+    /// Returns how the [Problem::probabilities] were obtained. Unless this is [ProbabilityKind::Calibrated]
+    /// they should not be interpreted as actual probabilities.
+    pub fn probability_kind(&self) -> ProbabilityKind { self.probability_kind }
+
     /// Returns the features. You must set them first and classifiy the problem before you can get a solution.
     pub fn features(&mut self) -> &mut Features<T> { &mut self.features }
+
+    /// Returns the pairwise decision values of the last classification as `(label_i, label_j, value)`.
+    ///
+    /// For each pair of classes the SVM computes one decision value. If it is positive, the
+    /// classifier voted for `label_i`, otherwise for `label_j`. The pairs are ordered like the
+    /// classes in the model, i.e., `label_i` comes before `label_j` in the model's `label` line.
+    pub fn decision_values(&self) -> impl Iterator<Item = (i64, i64, f64)> + '_ {
+        let num_classes = self.labels.len();
+
+        (0 .. num_classes).flat_map(move |i| (i + 1 .. num_classes).map(move |j| (self.labels[i], self.labels[j], self.decision_values[(i, j)])))
+    }
+
+    /// Returns the decision value of the classifier between `label_i` and `label_j`.
+    ///
+    /// The value is positive if the classifier voted for `label_i`, and is `None` if either label is
+    /// unknown or both are the same.
+    pub fn decision_value(&self, label_i: i64, label_j: i64) -> Option<f64> {
+        let i = self.labels.iter().position(|l| *l == label_i)?;
+        let j = self.labels.iter().position(|l| *l == label_j)?;
+
+        match i.cmp(&j) {
+            Ordering::Less => Some(self.decision_values[(i, j)]),
+            Ordering::Greater => Some(-self.decision_values[(j, i)]),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// Returns the raw decision value `f(x)` of the last classification of a binary model.
+    ///
+    /// The value is positive if the first label of the model was predicted, and its magnitude
+    /// grows with the distance to the decision boundary. Divide it by [SVMCore::weight_norm]
+    /// to obtain the geometric margin. Returns `None` for models with more than two classes.
+    pub fn margin(&self) -> Option<f64> {
+        match self.labels.len() {
+            2 => Some(self.decision_values[(0, 1)]),
+            _ => None,
+        }
+    }
+
+    /// Returns the margin of each class for the last classification as `(label, margin)`.
+    ///
+    /// The margin of a class is the smallest decision value in its favor over all pairwise
+    /// classifiers it takes part in. It is only positive if the class won against every other
+    /// class, and then tells by how much it won the closest of these duels. This makes it a
+    /// conservative confidence measure for multi-class models.
+    pub fn class_margins(&self) -> impl Iterator<Item = (i64, f64)> + '_ { (0 .. self.labels.len()).map(move |i| (self.labels[i], self.class_margin(i))) }
+
+    /// Returns the number of votes each class received during the last classification as `(label, votes)`.
+    ///
+    /// Each of the `n * (n - 1) / 2` pairwise classifiers votes for one class. This works for
+    /// all classification models, even if they were not trained with probability estimates.
+    pub fn votes(&self) -> impl Iterator<Item = (i64, u32)> + '_ { self.labels.iter().cloned().zip(self.vote.iter().cloned()) }
+
+    /// Returns the number of votes the class `label` received, or `None` if the label is unknown.
+    pub fn votes_for(&self, label: i64) -> Option<u32> {
+        let i = self.labels.iter().position(|l| *l == label)?;
+        Some(self.vote[i])
+    }
+
+    /// Returns a one-vs-rest style score for each class of the last classification as `(label, score)`.
+    ///
+    /// The score of a class is the sum of all pairwise decision values in its favor, i.e., how
+    /// much it won by (or lost by) against all other classes. Higher is better. Unlike
+    /// [Problem::probabilities] this is available for models without probability estimates.
+    pub fn scores(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (0 .. self.labels.len()).map(move |i| (self.labels[i], self.aggregate_decision_value(i)))
+    }
+
+    /// Returns `true` if the class at `index` takes part in the current prediction.
+    crate fn class_enabled(&self, index: usize) -> bool { self.class_mask.as_ref().map_or(true, |mask| mask[index]) }
+
+    /// Sum of all pairwise decision values in favor of the class at `index`, against all enabled classes.
+    crate fn aggregate_decision_value(&self, index: usize) -> f64 {
+        let before = (0 .. index).filter(|i| self.class_enabled(*i)).map(|i| -self.decision_values[(i, index)]);
+        let after = (index + 1 .. self.labels.len()).filter(|j| self.class_enabled(*j)).map(|j| self.decision_values[(index, j)]);
+
+        before.chain(after).sum()
+    }
+
+    /// Smallest decision value in favor of the class at `index` over its pairwise classifiers against all enabled classes.
+    crate fn class_margin(&self, index: usize) -> f64 {
+        let before = (0 .. index).filter(|i| self.class_enabled(*i)).map(|i| -self.decision_values[(i, index)]);
+        let after = (index + 1 .. self.labels.len()).filter(|j| self.class_enabled(*j)).map(|j| self.decision_values[(index, j)]);
+
+        before.chain(after).fold(std::f64::INFINITY, f64::min)
+    }
+
+    /// Returns all labels and their probabilities that exceeded their threshold, if the
+    /// solution is [Solution::MultiLabel]. The labels are in the order of the model.
+    pub fn multi_label(&self) -> &[(i64, f64)] { &self.multi_label }
+
+    /// Returns the probability estimate of each class as `(label, probability)`, in the order of the model.
+    ///
+    /// Only really useful after the problem was classified with [Predict::predict_probability].
+    pub fn label_probabilities(&self) -> impl Iterator<Item = (i64, f64)> + '_ { self.labels.iter().cloned().zip(self.probabilities().iter().cloned()) }
+
+    /// Returns the probability estimate of the class `label`, or `None` if the label is unknown.
+    pub fn probability_of(&self, label: i64) -> Option<f64> {
+        let i = self.labels.iter().position(|l| *l == label)?;
+        Some(self.probabilities()[i])
+    }
+
+    /// Returns the gap between the highest and the second highest probability estimate.
+    ///
+    /// This is a single number telling how sure the model is, `1.0` if one class got all the
+    /// probability and `0.0` if the two best classes are tied. Returns `None` if no probabilities
+    /// were predicted yet.
+    pub fn confidence(&self) -> Option<f64> {
+        if self.probability_kind == ProbabilityKind::None {
+            return None;
+        }
+
+        let (first, second) = self.probabilities()[.. self.labels.len()].iter().fold((0.0, 0.0), |(first, second), p| {
+            if *p > first {
+                (*p, first)
+            } else {
+                (first, second.max(*p))
+            }
+        });
+
+        Some(first - second)
+    }
+
+    /// Returns the Shannon entropy of the probability estimates in nats.
+    ///
+    /// The entropy is `0.0` if one class got all the probability and `ln(classes)` if all classes
+    /// are equally likely, i.e., lower is more certain. Unlike [Problem::confidence] it considers
+    /// all classes. Returns `None` if no probabilities were predicted yet.
+    pub fn entropy(&self) -> Option<f64> {
+        if self.probability_kind == ProbabilityKind::None {
+            return None;
+        }
+
+        Some(self.probabilities()[.. self.labels.len()].iter().filter(|p| **p > 0.0).map(|p| -p * p.ln()).sum())
+    }
+
+    /// Returns up to `k` labels with the highest probability estimates, most likely first.
+    ///
+    /// Only really useful after the problem was classified with [Predict::predict_probability].
+    pub fn top_k(&self, k: usize) -> Vec<(i64, f64)> {
+        let mut rval = self.label_probabilities().collect::<Vec<_>>();
+
+        rval.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        rval.truncate(k);
+        rval
+    }
+
+    /// Restricts all following predictions to the classes with the given `labels`, or enables all classes if `None`.
+    ///
+    /// # Description
+    ///
+    /// Excluded classes neither get kernel values nor take part in any pairwise classifier, so
+    /// predictions get cheaper the fewer classes are enabled, see [DenseSVM::predict_restricted].
+    /// Their votes, decision values and probabilities are `0`, and probabilities are coupled
+    /// among the enabled classes only. The mask stays with the problem until it is changed, and
+    /// [Problem::set_label_enabled] toggles single classes without allocating.
+    ///
+    /// While a mask is set the kernel values are never cached, see [Cache]. Regression models have
+    /// a single class and ignore the mask.
+    ///
+    /// # Returns
+    ///
+    /// An [Error::UnknownLabel] if any of the given labels is not part of the model, in which case the mask is unchanged.
+    pub fn set_enabled_labels(&mut self, labels: Option<&[i64]>) -> Result<(), Error> {
+        self.class_mask = match labels {
+            None => None,
+            Some(labels) => {
+                let mut mask = vec![false; self.labels.len()];
+
+                for label in labels {
+                    let index = self.labels.iter().position(|l| l == label).ok_or(Error::UnknownLabel(*label))?;
+                    mask[index] = true;
+                }
+
+                Some(mask)
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Enables or disables the class `label` for all following predictions, see [Problem::set_enabled_labels].
+    ///
+    /// # Returns
+    ///
+    /// An [Error::UnknownLabel] if the label is not part of the model.
+    pub fn set_label_enabled(&mut self, label: i64, enabled: bool) -> Result<(), Error> {
+        let index = self.labels.iter().position(|l| *l == label).ok_or(Error::UnknownLabel(label))?;
+        let num_classes = self.labels.len();
+
+        self.class_mask.get_or_insert_with(|| vec![true; num_classes])[index] = enabled;
+
+        Ok(())
+    }
+
+    /// Returns `true` if the class `label` is part of the model and enabled for predictions.
+    pub fn label_enabled(&self, label: i64) -> bool {
+        match self.labels.iter().position(|l| *l == label) {
+            Some(index) => self.class_enabled(index),
+            None => false,
+        }
+    }
+
+    /// Sets if and how kernel values may be reused between predictions, see [Cache].
+    pub fn set_cache(&mut self, cache: Cache) {
+        self.cache = cache;
+        self.invalidate_cache();
+    }
+
+    /// Forces the kernel values to be recomputed on the next prediction, including
+    /// incremental ones.
+    pub fn invalidate_cache(&mut self) {
+        self.cached_features = None;
+        self.cached_token = None;
+        self.previous_features = None;
+    }
+
+    /// Handles the token policy and returns `Some` if the policy was decided by the token.
+    fn token_cache_hit(&mut self) -> Option<bool> {
+        match self.cache {
+            Cache::Token(token) => {
+                let hit = self.cached_token == Some(token);
+                self.cached_token = Some(token);
+                Some(hit)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<V32> Problem<V32>
+where
+    V32: Pages,
+{
+    /// Reads every memory page of the features and the buffers used while predicting, see
+    /// [SVMCore::pretouch].
+    pub fn pretouch(&self) {
+        self.features.data.touch();
+        self.kernel_values.touch();
+        self.pairwise.touch();
+        self.q.touch();
+        self.qp.touch();
+        self.probabilities.touch();
+
+        for member in &self.members {
+            member.pretouch();
+        }
+    }
+
+    /// Locks the features and the buffers used while predicting into RAM, see [SVMCore::lock_memory].
+    #[cfg(feature = "mlock")]
+    pub fn lock_memory(&self) -> Result<(), Error> {
+        self.features.data.lock()?;
+        self.kernel_values.lock()?;
+        self.pairwise.lock()?;
+        self.q.lock()?;
+        self.qp.lock()?;
+        self.probabilities.lock()?;
+
+        self.members.iter().map(Problem::lock_memory).collect()
+    }
 }
 
 impl DenseProblem {
     /// Creates a new problem with the given parameters.
-    crate fn with_dimension(total_sv: usize, num_classes: usize, num_attributes: usize) -> Problem<SimdVector<f32s>> {
+    crate fn with_dimension(total_sv: usize, num_classes: usize, num_attributes: usize, labels: Vec<i64>) -> Problem<SimdVector<f32s>> {
         Problem {
             features: Features {
                 data: SimdVector::with(0.0, num_attributes),
@@ -131,7 +565,49 @@ impl DenseProblem {
             vote: vec![Default::default(); num_classes],
             probabilities: SimdVector::with(0.0, num_classes),
             result: Solution::None,
+            probability_kind: ProbabilityKind::None,
+            labels,
+            multi_label: Vec::with_capacity(num_classes),
+            cache: Cache::Disabled,
+            cached_features: None,
+            cached_token: None,
+            previous_features: None,
+            num_total_sv: total_sv,
+            num_attributes,
+            members: Vec::new(),
+            class_mask: None,
+            stats: None,
+            generation: None,
+        }
+    }
+
+    /// Returns `true` if the kernel values of the last prediction can be reused, and updates
+    /// the cache state otherwise.
+    crate fn kernel_values_cached(&mut self) -> bool {
+        if let Some(hit) = self.token_cache_hit() {
+            return hit;
+        }
+
+        let tolerance = match self.cache {
+            Cache::Tolerance(tolerance) => tolerance,
+            _ => return false,
+        };
+
+        let features = self.features.data.flat();
+
+        let hit = match &self.cached_features {
+            Some(cached) => cached.flat().iter().zip(features).all(|(a, b)| (a - b).abs() <= tolerance),
+            None => false,
+        };
+
+        if !hit {
+            match &mut self.cached_features {
+                Some(cached) => cached.flat_mut().copy_from_slice(features),
+                None => self.cached_features = Some(self.features.data.clone()),
+            }
         }
+
+        hit
     }
 }
 
@@ -139,8 +615,43 @@ impl SparseProblem {
     /// Clears the [Problem] when reusing it between calls. Only needed for [SparseSVM] problems.
     pub fn clear(&mut self) { self.features.data.clear(); }
 
+    /// Replaces all features with the attributes `indices` having the given `values`.
+    ///
+    /// # Description
+    ///
+    /// This is meant for callers holding features in parallel arrays, as bindings usually do.
+    /// Attributes not listed are zero. The `indices` must be strictly increasing, otherwise
+    /// [Error::AttributesUnordered] is returned and the problem is left cleared. If `indices`
+    /// and `values` differ in length, [Error::FeatureCountMismatch] is returned instead.
+    pub fn set_features(&mut self, indices: &[u32], values: &[f32]) -> Result<(), Error> {
+        self.clear();
+
+        if indices.len() != values.len() {
+            return Err(Error::FeatureCountMismatch {
+                indices: indices.len(),
+                values: values.len(),
+            });
+        }
+
+        let mut last: Option<u32> = None;
+
+        for (&index, &value) in indices.iter().zip(values) {
+            if let Some(last_index) = last {
+                if index <= last_index {
+                    self.clear();
+                    return Err(Error::AttributesUnordered { index, value, last_index });
+                }
+            }
+
+            self.features[index as usize] = value;
+            last = Some(index);
+        }
+
+        Ok(())
+    }
+
     /// Creates a new problem with the given parameters.
-    crate fn with_dimension(total_sv: usize, num_classes: usize, _num_attributes: usize) -> Problem<SparseVector<f32>> {
+    crate fn with_dimension(total_sv: usize, num_classes: usize, num_attributes: usize, labels: Vec<i64>) -> Problem<SparseVector<f32>> {
         Problem {
             features: Features { data: SparseVector::new() },
             kernel_values: SimdMatrix::with_dimension(num_classes, total_sv),
@@ -151,16 +662,70 @@ impl SparseProblem {
             vote: vec![Default::default(); num_classes],
             probabilities: SimdVector::with(0.0, num_classes),
             result: Solution::None,
+            probability_kind: ProbabilityKind::None,
+            labels,
+            multi_label: Vec::with_capacity(num_classes),
+            cache: Cache::Disabled,
+            cached_features: None,
+            cached_token: None,
+            previous_features: None,
+            num_total_sv: total_sv,
+            num_attributes,
+            members: Vec::new(),
+            class_mask: None,
+            stats: None,
+            generation: None,
         }
     }
+
+    /// Returns `true` if the kernel values of the last prediction can be reused, and updates
+    /// the cache state otherwise.
+    crate fn kernel_values_cached(&mut self) -> bool {
+        if let Some(hit) = self.token_cache_hit() {
+            return hit;
+        }
+
+        let tolerance = match self.cache {
+            Cache::Tolerance(tolerance) => tolerance,
+            _ => return false,
+        };
+
+        let hit = match &self.cached_features {
+            Some(cached) => {
+                let mut a_iter = cached.iter();
+                let mut b_iter = self.features.data.iter();
+
+                loop {
+                    match (a_iter.next(), b_iter.next()) {
+                        (Some((i_a, a)), Some((i_b, b))) if i_a == i_b && (a - b).abs() <= tolerance => continue,
+                        (None, None) => break true,
+                        _ => break false,
+                    }
+                }
+            }
+            None => false,
+        };
+
+        if !hit {
+            self.cached_features = Some(self.features.data.clone());
+        }
+
+        hit
+    }
 }
 
 impl<'a> From<&'a DenseSVM> for DenseProblem {
-    fn from(svm: &DenseSVM) -> Self { Problem::<SimdVector<f32s>>::with_dimension(svm.num_total_sv, svm.classes.len(), svm.num_attributes) }
+    fn from(svm: &DenseSVM) -> Self {
+        let labels = svm.classes.iter().map(|c| c.label).collect();
+        Problem::<SimdVector<f32s>>::with_dimension(svm.num_total_sv, svm.classes.len(), svm.num_attributes, labels)
+    }
 }
 
 impl<'a> From<&'a SparseSVM> for SparseProblem {
-    fn from(svm: &SparseSVM) -> Self { Problem::<SparseVector<f32>>::with_dimension(svm.num_total_sv, svm.classes.len(), svm.num_attributes) }
+    fn from(svm: &SparseSVM) -> Self {
+        let labels = svm.classes.iter().map(|c| c.label).collect();
+        Problem::<SparseVector<f32>>::with_dimension(svm.num_total_sv, svm.classes.len(), svm.num_attributes, labels)
+    }
 }
 
 impl<V32> Features<V32> {
@@ -0,0 +1,223 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock,
+};
+
+use crate::{
+    errors::Error,
+    svm::{core::SVMCore, predict::Predict, problem::Problem},
+};
+
+/// Wraps a SVM so it can be replaced while other threads are predicting with it.
+///
+/// # Description
+///
+/// Each prediction first takes a snapshot of the current model (an [Arc] clone, the lock is held
+/// only for that) and then predicts with the snapshot. A [ReloadableSvm::reload] therefore never
+/// waits for running predictions, and a prediction never sees a half-replaced model. Predictions
+/// that started before a reload finish with the old model, which is dropped once the last of them
+/// is done.
+///
+/// Problems are tied to the shape of the model they were created for. If a reloaded model has
+/// different labels, attributes or more support vectors, predicting an old problem fails with
+/// [Error::IncompatibleModel] and the problem has to be recreated from [ReloadableSvm::current].
+/// Kernel values a problem cached (see [Cache]) are dropped on its first prediction after a reload:
+///
+/// ```rust
+/// #![feature(try_from)]
+///
+/// use ffsvm::*;
+/// use std::convert::TryFrom;
+///
+/// fn main() -> Result<(), Error> {
+///     let svm = ReloadableSvm::new(DenseSVM::try_from(SAMPLE_MODEL)?);
+///     let mut problem = Problem::from(&*svm.current());
+///
+///     svm.reload(DenseSVM::try_from(SAMPLE_MODEL)?);
+///     svm.predict_value(&mut problem)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct ReloadableSvm<S> {
+    /// The current model and its generation.
+    current: RwLock<(Arc<S>, usize)>,
+}
+
+/// Source of model generations, unique across all [ReloadableSvm]s so problems can't confuse them.
+static GENERATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn next_generation() -> usize { GENERATIONS.fetch_add(1, Ordering::Relaxed) }
+
+impl<S> ReloadableSvm<S> {
+    /// Wraps `svm`.
+    pub fn new(svm: S) -> Self {
+        ReloadableSvm {
+            current: RwLock::new((Arc::new(svm), next_generation())),
+        }
+    }
+
+    /// Returns a snapshot of the current model, which stays valid even if the model is reloaded.
+    pub fn current(&self) -> Arc<S> { self.snapshot().0 }
+
+    /// Returns a snapshot of the current model and its generation.
+    fn snapshot(&self) -> (Arc<S>, usize) {
+        // Swapping an `Arc` can't leave it in an inconsistent state, so a poisoned lock is fine.
+        match self.current.read() {
+            Ok(current) => (current.0.clone(), current.1),
+            Err(poisoned) => {
+                let current = poisoned.into_inner();
+                (current.0.clone(), current.1)
+            }
+        }
+    }
+
+    /// Atomically replaces the model with `svm`, and returns the previous one.
+    pub fn reload(&self, svm: S) -> Arc<S> {
+        let svm = (Arc::new(svm), next_generation());
+
+        match self.current.write() {
+            Ok(mut current) => std::mem::replace(&mut *current, svm).0,
+            Err(poisoned) => std::mem::replace(&mut *poisoned.into_inner(), svm).0,
+        }
+    }
+}
+
+/// Drops the kernel values `problem` cached for another model than the one of `generation`.
+fn sync_generation<V32>(problem: &mut Problem<V32>, generation: usize) {
+    if problem.generation != Some(generation) {
+        problem.invalidate_cache();
+        problem.generation = Some(generation);
+    }
+}
+
+impl<K, M32, V32, V64> Predict<V32, V64> for ReloadableSvm<SVMCore<K, M32, V32, V64>>
+where
+    K: ?Sized,
+    SVMCore<K, M32, V32, V64>: Predict<V32, V64> + Send,
+{
+    fn predict_value(&self, problem: &mut Problem<V32>) -> Result<(), Error> {
+        let (svm, generation) = self.snapshot();
+
+        if !svm.fits(problem) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        sync_generation(problem, generation);
+
+        svm.predict_value(problem)
+    }
+
+    fn predict_probability(&self, problem: &mut Problem<V32>) -> Result<(), Error> {
+        let (svm, generation) = self.snapshot();
+
+        if !svm.fits(problem) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        sync_generation(problem, generation);
+
+        svm.predict_probability(problem)
+    }
+
+    fn problem(&self) -> Problem<V32> { self.current().problem() }
+
+    /// Predicts all problems with the same model, even if it is replaced meanwhile.
+    fn predict_values(&self, problems: &mut [Problem<V32>]) -> Result<(), Error>
+    where
+        V32: Send,
+    {
+        let (svm, generation) = self.snapshot();
+
+        if !problems.iter().all(|problem| svm.fits(problem)) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        for problem in problems.iter_mut() {
+            sync_generation(problem, generation);
+        }
+
+        svm.predict_values(problems)
+    }
+
+    /// Predicts all problems with the same model, even if it is replaced meanwhile.
+    fn predict_probabilities(&self, problems: &mut [Problem<V32>]) -> Result<(), Error>
+    where
+        V32: Send,
+    {
+        let (svm, generation) = self.snapshot();
+
+        if !problems.iter().all(|problem| svm.fits(problem)) {
+            return Err(Error::IncompatibleModel);
+        }
+
+        for problem in problems.iter_mut() {
+            sync_generation(problem, generation);
+        }
+
+        svm.predict_probabilities(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::{convert::TryFrom, sync::Arc, thread};
+
+    #[test]
+    fn reload_while_predicting() -> Result<(), Error> {
+        let svm = Arc::new(ReloadableSvm::new(DenseSVM::try_from(SAMPLE_MODEL)?));
+
+        let workers = (0 .. 4)
+            .map(|_| {
+                let svm = svm.clone();
+
+                thread::spawn(move || {
+                    let mut problem = Problem::from(&*svm.current());
+
+                    for _ in 0 .. 100 {
+                        svm.predict_value(&mut problem).unwrap();
+                    }
+                })
+            }).collect::<Vec<_>>();
+
+        for _ in 0 .. 10 {
+            svm.reload(DenseSVM::try_from(SAMPLE_MODEL)?);
+        }
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let other = DenseSVM::try_from(SAMPLE_MODEL.replace("label 21 42", "label 1 2").as_str())?;
+        let mut problem = Problem::from(&*svm.current());
+
+        svm.reload(other);
+        assert!(svm.predict_value(&mut problem).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reload_drops_cache() -> Result<(), Error> {
+        let model = samples::DENSE_RBF_BINARY.model;
+        let other = DenseSVM::try_from(model.replace("gamma 0.142857", "gamma 2").as_str())?;
+        let svm = ReloadableSvm::new(DenseSVM::try_from(model)?);
+
+        let mut problem = Problem::from(&*svm.current());
+        let mut expected = Problem::from(&other);
+
+        problem.set_cache(Cache::Token(1));
+        problem.features()[0] = 0.5;
+        expected.features()[0] = 0.5;
+
+        svm.predict_value(&mut problem)?;
+        svm.reload(other);
+        svm.predict_value(&mut problem)?;
+        svm.current().predict_value(&mut expected)?;
+
+        assert_eq!(problem.margin(), expected.margin());
+
+        Ok(())
+    }
+}
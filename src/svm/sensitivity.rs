@@ -0,0 +1,98 @@
+use crate::{
+    errors::Error,
+    svm::{
+        predict::Predict,
+        problem::{Cache, DenseProblem, Solution},
+        DenseSVM, SVMType,
+    },
+    util::find_max_index,
+};
+
+/// How sensitive a prediction is to a single feature, see [DenseSVM::sensitivity].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sensitivity {
+    /// Index of the feature.
+    pub feature: usize,
+
+    /// Change of the score per unit change of the feature. For classification this is the score of
+    /// the best class (see [Problem::scores]), for regression the predicted value.
+    pub score: f64,
+
+    /// Change of the probability of the best class per unit change of the feature, if the model
+    /// was trained with probability estimates.
+    pub probability: Option<f64>,
+}
+
+impl DenseSVM {
+    /// Estimates how much each feature of `problem` influences its prediction.
+    ///
+    /// # Description
+    ///
+    /// Each feature is changed by `+epsilon` and `-epsilon` in turn, and the resulting change of the
+    /// score (and probability, if available) of the best class is reported as a central difference.
+    /// Large absolute values indicate features the prediction is sensitive to around `problem`,
+    /// which gives a lightweight, local explanation of a prediction.
+    ///
+    /// The `problem` itself is not modified. This performs `2 * attributes + 1` predictions.
+    pub fn sensitivity(&self, problem: &DenseProblem, epsilon: f32) -> Result<Vec<Sensitivity>, Error> {
+        let mut scratch = problem.clone();
+        scratch.set_cache(Cache::Disabled);
+
+        let classification = match self.svm_type {
+            SVMType::CSvc | SVMType::NuSvc => true,
+            SVMType::ESvr | SVMType::NuSvr => false,
+        };
+
+        let with_probabilities = classification && self.probabilities.is_some();
+
+        // The class we report for is the best one of the unperturbed problem.
+        self.sensitivity_predict(&mut scratch, with_probabilities)?;
+        let best = find_max_index(&scratch.scores().map(|(_, score)| score).collect::<Vec<_>>());
+
+        let mut rval = Vec::with_capacity(self.num_attributes);
+
+        for feature in 0 .. self.num_attributes {
+            let original = scratch.features()[feature];
+
+            scratch.features()[feature] = original + epsilon;
+            self.sensitivity_predict(&mut scratch, with_probabilities)?;
+            let (score_plus, probability_plus) = sensitivity_outputs(&scratch, best, classification);
+
+            scratch.features()[feature] = original - epsilon;
+            self.sensitivity_predict(&mut scratch, with_probabilities)?;
+            let (score_minus, probability_minus) = sensitivity_outputs(&scratch, best, classification);
+
+            scratch.features()[feature] = original;
+
+            let width = 2.0 * f64::from(epsilon);
+
+            rval.push(Sensitivity {
+                feature,
+                score: (score_plus - score_minus) / width,
+                probability: if with_probabilities { Some((probability_plus - probability_minus) / width) } else { None },
+            });
+        }
+
+        Ok(rval)
+    }
+
+    fn sensitivity_predict(&self, problem: &mut DenseProblem, with_probabilities: bool) -> Result<(), Error> {
+        if with_probabilities {
+            self.predict_probability(problem)
+        } else {
+            self.predict_value(problem)
+        }
+    }
+}
+
+/// Returns the score and probability of class `best`.
+fn sensitivity_outputs(problem: &DenseProblem, best: usize, classification: bool) -> (f64, f64) {
+    let score = match (classification, problem.solution()) {
+        (false, Solution::Value(value)) => f64::from(value),
+        _ => problem.aggregate_decision_value(best),
+    };
+
+    let probability = problem.probabilities().get(best).cloned().unwrap_or(0.0);
+
+    (score, probability)
+}
@@ -0,0 +1,140 @@
+use rayon::{prelude::*, ThreadPool};
+use std::sync::Arc;
+
+use crate::{errors::Error, svm::observer::Observer};
+
+/// How a multi-class classification is resolved if several classes received the same number of votes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TieBreak {
+    /// Picks the tied class that comes first in the model, like libSVM does (default).
+    FirstClass,
+
+    /// Picks the tied class with the lowest label.
+    LowestLabel,
+
+    /// Picks the tied class with the highest sum of pairwise decision values in its favor.
+    DecisionValue,
+
+    /// Does not pick a class, but sets the solution to [Solution::Ambiguous].
+    Ambiguous,
+}
+
+/// When a classification should be rejected instead of reporting a label.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Reject {
+    /// Always report the best label (default).
+    Never,
+
+    /// Reject with [Solution::Rejected] if the probability of the best label is below this value.
+    /// Only applies to [Predict::predict_probability].
+    BelowProbability(f64),
+
+    /// Reject with [Solution::Rejected] if the winning class beat any other class with a decision
+    /// value below this margin. Applies to [Predict::predict_value] and [Predict::predict_probability].
+    BelowMargin(f64),
+}
+
+/// Where batch predictions like [Predict::predict_values] run.
+#[derive(Clone, Debug)]
+crate enum Threads {
+    /// On Rayon's global thread pool (default).
+    Global,
+
+    /// On the calling thread only.
+    Sequential,
+
+    /// On a dedicated thread pool.
+    Pool(Arc<ThreadPool>),
+}
+
+impl Threads {
+    /// Applies `f` to all `items`, and returns one of the errors if any failed. Runs on the calling
+    /// thread if there are fewer than `min_parallel` items.
+    crate fn run<T, F>(&self, items: &mut [T], min_parallel: usize, f: F) -> Result<(), Error>
+    where
+        T: Send,
+        F: Fn(&mut T) -> Result<(), Error> + Sync,
+    {
+        match self {
+            _ if items.len() < min_parallel => items.iter_mut().map(f).collect(),
+            Threads::Global => items.par_iter_mut().map(f).collect(),
+            Threads::Sequential => items.iter_mut().map(f).collect(),
+            Threads::Pool(pool) => pool.install(|| items.par_iter_mut().map(f).collect()),
+        }
+    }
+
+    /// Applies `f` to chunks of at most `size` items, and returns one of the errors if any failed.
+    /// Runs on the calling thread if there are fewer than `min_parallel` items.
+    crate fn run_chunks<T, F>(&self, items: &mut [T], size: usize, min_parallel: usize, f: F) -> Result<(), Error>
+    where
+        T: Send,
+        F: Fn(&mut [T]) -> Result<(), Error> + Sync,
+    {
+        match self {
+            _ if items.len() < min_parallel => items.chunks_mut(size).map(f).collect(),
+            Threads::Global => items.par_chunks_mut(size).map(f).collect(),
+            Threads::Sequential => items.chunks_mut(size).map(f).collect(),
+            Threads::Pool(pool) => pool.install(|| items.par_chunks_mut(size).map(f).collect()),
+        }
+    }
+}
+
+/// Runtime options of a SVM that are not part of the model.
+#[derive(Clone, Debug)]
+crate struct Settings {
+    crate tie_break: TieBreak,
+    crate reject: Reject,
+
+    /// Probability thresholds per class index, if predicting multiple labels.
+    crate multi_label: Option<Vec<f64>>,
+
+    /// Application names per class index, if any were given.
+    crate label_names: Option<Vec<Option<String>>>,
+
+    /// Temperature applied to the pairwise probabilities.
+    crate temperature: f64,
+
+    /// Iteration limit of the multi-class pairwise coupling, libSVM's default if `None`.
+    crate coupling_max_iterations: Option<usize>,
+
+    /// Convergence epsilon of the multi-class pairwise coupling, libSVM's default if `None`.
+    crate coupling_epsilon: Option<f64>,
+
+    /// If models without probability estimates predict softmax scores instead.
+    crate pseudo_probabilities: bool,
+
+    /// Where batch predictions run.
+    crate threads: Threads,
+
+    /// Smallest batch that is predicted in parallel, derived from the model size if `None`.
+    crate min_parallel_batch: Option<usize>,
+
+    /// Notified after each prediction.
+    crate observer: Option<Observer>,
+
+    /// If all sums are computed in a fixed order, independent of the SIMD width.
+    crate deterministic: bool,
+
+    /// If problems record [PredictStats] of their predictions.
+    crate predict_stats: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tie_break: TieBreak::FirstClass,
+            reject: Reject::Never,
+            multi_label: None,
+            label_names: None,
+            temperature: 1.0,
+            coupling_max_iterations: None,
+            coupling_epsilon: None,
+            pseudo_probabilities: false,
+            threads: Threads::Global,
+            min_parallel_batch: None,
+            observer: None,
+            deterministic: false,
+            predict_stats: false,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use simd_aligned::{f32s, f64s};
+use std::{
+    mem::size_of,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    svm::{class::Class, core::SVMCore, kernel::KernelParameters, SVMType},
+    vectors::Triangular,
+};
+
+/// Statistics about a loaded model, see [SVMCore::stats].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    /// Total number of support vectors.
+    pub total_support_vectors: usize,
+
+    /// Number of support vectors per class as `(label, count)`, in the order of the model.
+    pub support_vectors: Vec<(i64, usize)>,
+
+    /// Number of attributes per support vector.
+    pub attributes: usize,
+
+    /// Type of the SVM.
+    pub svm_type: SVMType,
+
+    /// Type and parameters of the kernel.
+    pub kernel: KernelParameters,
+
+    /// If the model was trained with probability estimates.
+    pub probabilities: bool,
+
+    /// Ratio of non-zero attributes over all attributes of all support vectors. Models with a
+    /// low density might be faster as a [SparseSVM], see [SparseSVM::from_dense].
+    pub density: f32,
+
+    /// Estimated number of bytes the model uses on the heap, including SIMD padding.
+    pub heap_bytes: usize,
+}
+
+/// Estimated heap usage of a model and its problems in bytes, see [SVMCore::memory_usage].
+///
+/// All numbers include SIMD padding. Stack sizes and allocator overhead are not included.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MemoryUsage {
+    /// Support vectors of all classes.
+    pub support_vectors: usize,
+
+    /// Coefficients of all support vectors, and folded linear weights if any.
+    pub coefficients: usize,
+
+    /// Calibrated probability parameters `probA` and `probB`.
+    pub probabilities: usize,
+
+    /// Everything else, like `rho`, class information, settings and metadata.
+    pub other: usize,
+
+    /// Scratch space of each [Problem] created for the model. Problems of [SparseSVM]s additionally
+    /// grow with the number of features set, and enabling a [Cache] adds a copy of the features.
+    pub problem: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the bytes used by the model itself, without any problems.
+    pub fn model(&self) -> usize { self.support_vectors + self.coefficients + self.probabilities + self.other }
+}
+
+/// Timings and counters of the last prediction of a [Problem], see [SVMCore::set_predict_stats].
+///
+/// Times are measured with [Instant], so they include any preemption of the predicting thread.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PredictStats {
+    /// Time spent computing kernel values.
+    pub kernel: Duration,
+
+    /// Time spent computing decision values and votes, and resolving the solution.
+    pub decision: Duration,
+
+    /// Time spent computing probabilities, zero unless predicted via [Predict::predict_probability].
+    pub probability: Duration,
+
+    /// Iterations of the multi-class pairwise coupling, zero for binary models.
+    pub probability_iterations: usize,
+
+    /// Number of support vectors the kernel was evaluated for. This is zero if the kernel values
+    /// were cached or linear support vectors are folded, and excludes classes left out of the
+    /// prediction.
+    pub support_vectors: usize,
+}
+
+/// Measures the phases of a prediction into its [PredictStats], if they are enabled.
+crate struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    crate fn start(enabled: bool) -> Self { Stopwatch(if enabled { Some(Instant::now()) } else { None }) }
+
+    /// Adds the time since the start or the previous lap to the `phase` of `stats`.
+    crate fn lap(&mut self, stats: &mut Option<PredictStats>, phase: impl FnOnce(&mut PredictStats) -> &mut Duration) {
+        if let (Some(last), Some(stats)) = (&mut self.0, stats) {
+            let now = Instant::now();
+
+            *phase(stats) += now - *last;
+            *last = now;
+        }
+    }
+}
+
+/// Bytes used by `rows` SIMD rows of `columns` values of type `T`, including padding.
+crate fn simd_bytes<T>(rows: usize, columns: usize, lanes: usize) -> usize { rows * ((columns + lanes - 1) / lanes) * size_of::<T>() }
+
+/// Ratio of non-zero attributes over all attributes of all support vectors.
+crate fn density(num_non_zero: usize, num_total_sv: usize, num_attributes: usize) -> f32 {
+    match num_total_sv * num_attributes {
+        0 => 0.0,
+        total => num_non_zero as f32 / total as f32,
+    }
+}
+
+fn triangular_bytes<T>(triangular: &Triangular<T>) -> usize
+where
+    T: Copy + Sized,
+{
+    triangular.data.capacity() * size_of::<T>()
+}
+
+impl<K, M32, V32, V64> SVMCore<K, M32, V32, V64>
+where
+    K: ?Sized,
+{
+    /// Estimates the memory usage, given the bytes of the support vectors and of the features of a problem.
+    crate fn common_memory_usage(&self, support_vectors: usize, features: usize) -> MemoryUsage {
+        let num_classes = self.classes.len();
+
+        let coefficients = self
+            .classes
+            .iter()
+            .map(|c| simd_bytes::<f64s>(num_classes.max(2) - 1, c.num_support_vectors, f64s::lanes()))
+            .sum::<usize>();
+
+        let probabilities = self.probabilities.as_ref().map_or(0, |p| triangular_bytes(&p.a) + triangular_bytes(&p.b));
+        let linear_weights = self.linear_weights.as_ref().map_or(0, |_| simd_bytes::<f32s>(self.rho.data.len(), self.num_attributes, f32s::lanes()));
+        let pairs = self.pairs.as_ref().map_or(0, triangular_bytes);
+        let multi_label = self.settings.multi_label.as_ref().map_or(0, |t| t.capacity() * size_of::<f64>());
+        let label_names = self.settings.label_names.as_ref().map_or(0, |names| {
+            names.capacity() * size_of::<Option<String>>() + names.iter().flatten().map(|n| n.capacity()).sum::<usize>()
+        });
+        let metadata = self.metadata.iter().map(|(k, v)| k.capacity() + v.capacity()).sum::<usize>();
+
+        // Mirrors the allocations of `Problem::with_dimension`.
+        let problem = features
+            + simd_bytes::<f64s>(num_classes, self.num_total_sv, f64s::lanes())
+            + 2 * simd_bytes::<f64s>(num_classes, num_classes, f64s::lanes())
+            + simd_bytes::<f64s>(1, num_classes, f64s::lanes())
+            + num_classes * (size_of::<f64>() + size_of::<u32>() + size_of::<i64>() + size_of::<(i64, f64)>())
+            + num_classes * num_classes.saturating_sub(1) / 2 * size_of::<f64>();
+
+        MemoryUsage {
+            support_vectors,
+            coefficients: coefficients + linear_weights,
+            probabilities,
+            other: self.classes.capacity() * size_of::<Class<M32>>()
+                + triangular_bytes(&self.rho)
+                + pairs
+                + multi_label
+                + label_names
+                + metadata
+                + self.metadata.capacity() * size_of::<(String, String)>(),
+            problem,
+        }
+    }
+}
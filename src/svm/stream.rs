@@ -0,0 +1,187 @@
+use simd_aligned::{f32s, f64s, SimdVector};
+use std::collections::VecDeque;
+
+use crate::{
+    errors::Error,
+    svm::{
+        predict::Predict,
+        problem::{DenseProblem, Solution},
+    },
+};
+
+/// Predicts a stream of features, see [Predict::predict_iter].
+///
+/// # Description
+///
+/// Each item of the stream holds the features of one problem. By default items are predicted
+/// one at a time, reusing a single [Problem]. With [PredictIter::lookahead] up to `n` items are
+/// taken from the stream at once and predicted via [Predict::predict_values], which can run in
+/// parallel, reusing `n` problems. Solutions are returned in the order of the stream either way.
+///
+/// Items shorter than the model's number of attributes are padded with zeros. Items longer than
+/// that fail with [Error::FeatureCountMismatch] without being predicted, and don't affect the other items.
+pub struct PredictIter<'a, P, I>
+where
+    P: Predict<SimdVector<f32s>, SimdVector<f64s>> + ?Sized,
+{
+    predictor: &'a P,
+    features: I,
+    problems: Vec<DenseProblem>,
+    results: VecDeque<Result<Solution, Error>>,
+    lookahead: usize,
+}
+
+impl<'a, P, I> PredictIter<'a, P, I>
+where
+    P: Predict<SimdVector<f32s>, SimdVector<f64s>> + ?Sized,
+    I: Iterator,
+    I::Item: AsRef<[f32]>,
+{
+    /// Creates an iterator predicting each item of `features` with `predictor`.
+    pub fn new(predictor: &'a P, features: I) -> Self {
+        PredictIter {
+            predictor,
+            features,
+            problems: Vec::new(),
+            results: VecDeque::new(),
+            lookahead: 1,
+        }
+    }
+
+    /// Takes up to `lookahead` items from the stream at once and predicts them as batch.
+    ///
+    /// Values below `1` are treated as `1`. Larger values increase throughput, but also the
+    /// latency of the first solution of each batch.
+    pub fn lookahead(mut self, lookahead: usize) -> Self {
+        self.lookahead = lookahead.max(1);
+        self
+    }
+
+    /// Loads and predicts the next batch of items, returns `false` if the stream is exhausted.
+    fn predict_batch(&mut self) -> bool {
+        // Per item, the error if it doesn't fit. Items that fit are loaded into the next problem.
+        let mut items = Vec::new();
+        let mut len = 0;
+
+        while items.len() < self.lookahead {
+            let features = match self.features.next() {
+                Some(features) => features,
+                None => break,
+            };
+
+            if self.problems.len() <= len {
+                self.problems.push(self.predictor.problem());
+            }
+
+            let problem = &mut self.problems[len];
+            let num_attributes = problem.num_attributes;
+            let features = features.as_ref();
+
+            // Items that don't fit get no problem, so they don't cost a prediction.
+            if features.len() > num_attributes {
+                items.push(Some(Error::FeatureCountMismatch {
+                    indices: num_attributes,
+                    values: features.len(),
+                }));
+                continue;
+            }
+
+            let slice = problem.features().as_slice_mut();
+            slice[.. features.len()].copy_from_slice(features);
+
+            for x in slice[features.len() ..].iter_mut() {
+                *x = 0.0;
+            }
+
+            items.push(None);
+            len += 1;
+        }
+
+        let problems = &mut self.problems[.. len];
+        let predictor = self.predictor;
+
+        // A batch only reports one error, so find out which problems failed.
+        let batch = if len <= 1 { Err(()) } else { predictor.predict_values(problems).map_err(|_| ()) };
+        let mut problems = problems.iter_mut();
+        let exhausted = items.is_empty();
+
+        for invalid in items {
+            let result = match invalid {
+                Some(e) => Err(e),
+                None => match (problems.next(), batch) {
+                    (Some(problem), Ok(())) => Ok(problem.solution()),
+                    (Some(problem), Err(())) => predictor.predict_value(problem).map(|_| problem.solution()),
+                    (None, _) => unreachable!(),
+                },
+            };
+
+            self.results.push_back(result);
+        }
+
+        !exhausted
+    }
+}
+
+impl<'a, P, I> Iterator for PredictIter<'a, P, I>
+where
+    P: Predict<SimdVector<f32s>, SimdVector<f64s>> + ?Sized,
+    I: Iterator,
+    I::Item: AsRef<[f32]>,
+{
+    type Item = Result<Solution, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.results.is_empty() && !self.predict_batch() {
+            return None;
+        }
+
+        self.results.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn predict_iter() -> Result<(), Error> {
+        let svm = DenseSVM::try_from(SAMPLE_MODEL)?;
+        let stream = (0 .. 5)
+            .map(|i| {
+                let mut features = SAMPLE_FEATURES.to_vec();
+                features[0] -= i as f32;
+                features
+            }).collect::<Vec<_>>();
+
+        let expected = stream
+            .iter()
+            .map(|features| {
+                let mut problem = Problem::from(&svm);
+                problem.features().as_slice_mut()[.. 4].copy_from_slice(features);
+                svm.predict_value(&mut problem).map(|_| problem.solution())
+            }).collect::<Result<Vec<_>, Error>>()?;
+
+        assert!(expected.contains(&Solution::Label(21)) && expected.contains(&Solution::Label(42)));
+
+        // Solutions come in the order of the stream, however many items are predicted at once.
+        for lookahead in &[1, 2, 8] {
+            let solutions = svm.predict_iter(stream.iter()).lookahead(*lookahead).collect::<Result<Vec<_>, Error>>()?;
+            assert_eq!(solutions, expected);
+        }
+
+        let stream = vec![SAMPLE_FEATURES.to_vec(), vec![0.0; 5], SAMPLE_FEATURES[.. 3].to_vec()];
+
+        // Items that fit are predicted together, even around one that doesn't.
+        for lookahead in &[1, 2, 3] {
+            let results = svm.predict_iter(stream.iter()).lookahead(*lookahead).collect::<Vec<_>>();
+
+            match results.as_slice() {
+                [Ok(Solution::Label(42)), Err(Error::FeatureCountMismatch { indices: 4, values: 5 }), Ok(_)] => {}
+                _ => panic!("Expected only the second item to fail."),
+            }
+        }
+
+        Ok(())
+    }
+}
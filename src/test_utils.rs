@@ -0,0 +1,212 @@
+//! Random models for property-based tests, and fixtures for the bundled sample model, enabled by
+//! the `test_utils` feature.
+//!
+//! The models are syntactically valid and load into any SVM, but have random coefficients
+//! and support vectors, so their predictions are meaningless. Use them to test code that handles
+//! arbitrary models, or round-trips through writing, parsing and predicting.
+//!
+//! ```rust
+//! #![feature(try_from)]
+//!
+//! use ffsvm::{test_utils::RandomModel, *};
+//!
+//! fn main() -> Result<(), Error> {
+//!     let svm = RandomModel { classes: 3, probabilities: true, ..RandomModel::default() }.dense_svm()?;
+//!     let mut problem = Problem::from(&svm);
+//!
+//!     svm.predict_value(&mut problem)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{convert::TryFrom, ops::IndexMut};
+
+use crate::{
+    errors::Error,
+    parser::{Attribute, Header, ModelFile, SupportVector},
+    svm::{
+        problem::{Features, Problem},
+        DenseSVM, SparseSVM,
+    },
+    SAMPLE_FEATURES,
+};
+
+/// Sets the first features of `problem` to `SAMPLE_FEATURES`, which `SAMPLE_MODEL` classifies as `42`.
+pub fn set_sample_features<V32>(problem: &mut Problem<V32>)
+where
+    Features<V32>: IndexMut<usize, Output = f32>,
+{
+    let features = problem.features();
+
+    for (i, value) in SAMPLE_FEATURES.iter().enumerate() {
+        features[i] = *value;
+    }
+}
+
+/// Shape of a random model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomModel {
+    /// Type of the SVM as in the model file, for example `c_svc` or `epsilon_svr` (default `c_svc`).
+    pub svm_type: &'static str,
+
+    /// Type of the kernel as in the model file, for example `rbf` or `linear` (default `rbf`).
+    pub kernel_type: &'static str,
+
+    /// Number of classes, ignored for regression models (default `2`).
+    pub classes: u32,
+
+    /// Number of attributes of each support vector (default `8`).
+    pub attributes: u32,
+
+    /// Number of support vectors per class, or in total for regression models (default `4`).
+    pub support_vectors: u32,
+
+    /// If the model has probability estimates (default `false`).
+    pub probabilities: bool,
+
+    /// Seed of the generated values, or `None` for a different model each time (default `None`).
+    pub seed: Option<u64>,
+}
+
+impl Default for RandomModel {
+    fn default() -> Self {
+        RandomModel {
+            svm_type: "c_svc",
+            kernel_type: "rbf",
+            classes: 2,
+            attributes: 8,
+            support_vectors: 4,
+            probabilities: false,
+            seed: None,
+        }
+    }
+}
+
+impl RandomModel {
+    /// Returns a random number generator for this model, `stream` tells apart generators of the same seed.
+    fn rng(&self, stream: u64) -> StdRng {
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen()) ^ stream;
+        let mut bytes = <StdRng as SeedableRng>::Seed::default();
+
+        for (i, byte) in bytes.iter_mut().take(8).enumerate() {
+            *byte = (seed >> (8 * i)) as u8;
+        }
+
+        StdRng::from_seed(bytes)
+    }
+
+    /// Generates the model file.
+    pub fn model_file(&self) -> ModelFile<'static> {
+        let mut rng = self.rng(0);
+        let classification = match self.svm_type {
+            "epsilon_svr" | "nu_svr" => false,
+            _ => true,
+        };
+
+        let num_classes = if classification { self.classes.max(2) } else { 1 };
+        let num_pairs = if classification { (num_classes * (num_classes - 1) / 2) as usize } else { 1 };
+        let num_coefficients = num_classes.max(2) - 1;
+        let total_sv = num_classes * self.support_vectors.max(1);
+
+        let mut random = |n: usize| (0 .. n).map(|_| rng.gen_range(-1.0, 1.0)).collect::<Vec<f64>>();
+
+        let header = Header {
+            svm_type: self.svm_type,
+            kernel_type: self.kernel_type,
+            gamma: Some(0.5),
+            coef0: Some(0.25),
+            degree: Some(3),
+            nr_class: if classification { num_classes } else { 2 },
+            total_sv,
+            rho: random(num_pairs),
+            label: if classification { (0 .. i64::from(num_classes)).collect() } else { Vec::new() },
+            label_names: Vec::new(),
+            prob_a: if self.probabilities { Some(random(num_pairs)) } else { None },
+            prob_b: if self.probabilities && classification { Some(random(num_pairs)) } else { None },
+            nr_sv: if classification { vec![self.support_vectors.max(1); num_classes as usize] } else { Vec::new() },
+            metadata: Vec::new(),
+            format_version: None,
+        };
+
+        let vectors = (0 .. total_sv)
+            .map(|_| SupportVector {
+                coefs: random(num_coefficients as usize).into_iter().map(|c| c as f32).collect(),
+                features: random(self.attributes as usize)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, value)| Attribute {
+                        index: i as u32,
+                        value: value as f32,
+                    }).collect(),
+            }).collect();
+
+        ModelFile { header, vectors }
+    }
+
+    /// Generates the model and loads it into a [DenseSVM].
+    pub fn dense_svm(&self) -> Result<DenseSVM, Error> { DenseSVM::try_from(&self.model_file()) }
+
+    /// Generates the model and loads it into a [SparseSVM].
+    pub fn sparse_svm(&self) -> Result<SparseSVM, Error> { SparseSVM::try_from(&self.model_file()) }
+
+    /// Generates `count` feature vectors with values in `[-1, 1)` for this model.
+    pub fn features(&self, count: usize) -> Vec<Vec<f32>> {
+        let mut rng = self.rng(1);
+
+        (0 .. count).map(|_| (0 .. self.attributes).map(|_| rng.gen_range(-1.0, 1.0)).collect()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RandomModel;
+    use crate::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn round_trips() -> Result<(), Error> {
+        for svm_type in &["c_svc", "nu_svc", "epsilon_svr", "nu_svr"] {
+            for kernel_type in &["linear", "rbf", "polynomial", "sigmoid"] {
+                for (classes, probabilities) in &[(2, false), (2, true), (4, false)] {
+                    let shape = RandomModel {
+                        svm_type: *svm_type,
+                        kernel_type: *kernel_type,
+                        classes: *classes,
+                        probabilities: *probabilities,
+                        seed: Some(42),
+                        ..RandomModel::default()
+                    };
+
+                    let model = shape.model_file();
+                    let text = model.to_string();
+
+                    assert!(model.validate().is_empty());
+                    assert_eq!(ModelFile::try_from(text.as_str())?.to_string(), text);
+
+                    let dense = shape.dense_svm()?;
+                    let sparse = shape.sparse_svm()?;
+
+                    for features in shape.features(3) {
+                        let mut dense_problem = Problem::from(&dense);
+                        let mut sparse_problem = Problem::from(&sparse);
+
+                        for (i, value) in features.iter().enumerate() {
+                            dense_problem.features()[i] = *value;
+                            sparse_problem.features()[i] = *value;
+                        }
+
+                        dense.predict_value(&mut dense_problem)?;
+                        sparse.predict_value(&mut sparse_problem)?;
+                    }
+                }
+            }
+        }
+
+        let seeded = RandomModel { seed: Some(7), ..RandomModel::default() };
+        assert_eq!(seeded.model_file().to_string(), seeded.model_file().to_string());
+
+        Ok(())
+    }
+}
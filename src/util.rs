@@ -1,4 +1,21 @@
-use std::{cmp::PartialOrd, marker::Copy};
+use simd_aligned::{f32s, f64s, ColumnOptimized, RowOptimized, SimdMatrix, SimdVector};
+use std::{
+    cmp::PartialOrd,
+    marker::Copy,
+    mem::size_of,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "mlock")]
+use crate::errors::Error;
+#[cfg(feature = "mlock")]
+use std::io;
+
+/// Bytes between two values read by [touch], at most the page size of all supported platforms.
+const PAGE_BYTES: usize = 4096;
+
+/// Receives the values read by [touch], so the reads can't be optimized out.
+static TOUCHED: AtomicUsize = AtomicUsize::new(0);
 
 /// Sets all items of a mutable vector to the given value.
 pub fn set_all<T>(vector: &mut [T], value: T)
@@ -56,3 +73,115 @@ pub fn powi(base: f64, times: u32) -> f64 {
 
     ret
 }
+
+/// Hints the CPU to load the cache line holding the beginning of `data`. This is a no-op on
+/// architectures where we don't have a prefetch intrinsic.
+#[cfg(feature = "prefetch")]
+#[inline]
+#[allow(unsafe_code)]
+pub fn prefetch<T>(data: &[T]) {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+    // Prefetching is only a hint and can never fault, even for invalid addresses.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    unsafe {
+        _mm_prefetch(data.as_ptr() as *const i8, _MM_HINT_T0)
+    }
+}
+
+/// Reads one value per memory page of all `slices`, so that pages which were swapped out or never
+/// mapped are faulted in now, rather than during the next prediction. `bits` is applied to each
+/// value read.
+pub fn touch<'a, T: 'a>(slices: impl IntoIterator<Item = &'a [T]>, bits: impl Fn(&T) -> usize) {
+    let step = (PAGE_BYTES / size_of::<T>().max(1)).max(1);
+    let sum = slices.into_iter().flat_map(|s| s.iter().step_by(step)).fold(0, |sum, x| sum ^ bits(x));
+
+    TOUCHED.fetch_xor(sum, Ordering::Relaxed);
+}
+
+/// Locks the memory of all `slices` into RAM. The slices should be part of the same allocation,
+/// like the rows of a matrix, as everything between the first and the last one is locked.
+#[cfg(feature = "mlock")]
+pub fn lock<'a, T: 'a>(slices: impl IntoIterator<Item = &'a [T]>) -> Result<(), Error> {
+    let (start, end) = slices
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| (s.as_ptr() as usize, s.as_ptr() as usize + s.len() * size_of::<T>()))
+        .fold((usize::max_value(), 0), |(start, end), (s, e)| (start.min(s), end.max(e)));
+
+    if start >= end {
+        return Ok(());
+    }
+
+    lock_range(start, end - start)
+}
+
+#[cfg(all(feature = "mlock", unix))]
+#[allow(unsafe_code)]
+fn lock_range(start: usize, len: usize) -> Result<(), Error> {
+    // `mlock` only pins the pages of the range, it never reads or writes them.
+    match unsafe { libc::mlock(start as *const libc::c_void, len) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error().into()),
+    }
+}
+
+#[cfg(all(feature = "mlock", not(unix)))]
+fn lock_range(_start: usize, _len: usize) -> Result<(), Error> {
+    Err(io::Error::new(io::ErrorKind::Other, "Locking memory is only supported on Unix.").into())
+}
+
+/// Buffers of models and problems that can be faulted in and locked, see [SVMCore::pretouch].
+pub trait Pages {
+    /// Reads one value per memory page.
+    fn touch(&self);
+
+    /// Locks all pages into RAM.
+    #[cfg(feature = "mlock")]
+    fn lock(&self) -> Result<(), Error>;
+}
+
+macro_rules! impl_pages_simd {
+    ($t:ty, $iter:ident) => {
+        impl Pages for $t {
+            fn touch(&self) { touch(self.$iter(), |x| x.extract(0).to_bits() as usize) }
+
+            #[cfg(feature = "mlock")]
+            fn lock(&self) -> Result<(), Error> { lock(self.$iter()) }
+        }
+    };
+}
+
+impl_pages_simd!(SimdMatrix<f32s, RowOptimized>, row_iter);
+impl_pages_simd!(SimdMatrix<f32s, ColumnOptimized>, column_iter);
+impl_pages_simd!(SimdMatrix<f64s, RowOptimized>, row_iter);
+
+macro_rules! impl_pages_vector {
+    ($t:ty) => {
+        impl Pages for SimdVector<$t> {
+            fn touch(&self) {
+                let slice: &[$t] = &self;
+                touch(Some(slice), |x| x.extract(0).to_bits() as usize)
+            }
+
+            #[cfg(feature = "mlock")]
+            fn lock(&self) -> Result<(), Error> {
+                let slice: &[$t] = &self;
+                lock(Some(slice))
+            }
+        }
+    };
+}
+
+impl_pages_vector!(f32s);
+impl_pages_vector!(f64s);
+
+impl Pages for Vec<f64> {
+    fn touch(&self) { touch(Some(self.as_slice()), |x| x.to_bits() as usize) }
+
+    #[cfg(feature = "mlock")]
+    fn lock(&self) -> Result<(), Error> { lock(Some(self.as_slice())) }
+}
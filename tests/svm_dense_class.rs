@@ -43,12 +43,19 @@ macro_rules! test_model {
             assert_eq!(problem_0.solution(), Solution::Label($libsvm[0]), "predict_value(problem_0)");
             assert_eq!(problem_7.solution(), Solution::Label($libsvm[1]), "predict_value(problem_7)");
 
+            let num_classes = svm.classes() as u32;
+            assert_eq!(problem_0.votes().map(|(_, v)| v).sum::<u32>(), num_classes * (num_classes - 1) / 2, "votes(problem_0)");
+            assert_eq!(problem_0.votes_for($libsvm[0]), problem_0.votes().map(|(_, v)| v).max(), "votes_for(problem_0)");
+
             if $prob {
                 svm.predict_probability(&mut problem_0)?;
                 svm.predict_probability(&mut problem_7)?;
 
                 assert_eq!(problem_0.solution(), Solution::Label($libsvm_prob[0]), "predict_probability(problem_0)");
                 assert_eq!(problem_7.solution(), Solution::Label($libsvm_prob[1]), "predict_probability(problem_7)");
+
+                assert_eq!(problem_0.top_k(1)[0].0, $libsvm_prob[0], "top_k(problem_0)");
+                assert_eq!(problem_7.top_k(1)[0].0, $libsvm_prob[1], "top_k(problem_7)");
             }
 
             Ok(())
@@ -81,6 +88,46 @@ mod svm_dense_class {
     test_model!(m_nusvm_rbf_prob, "m_nusvm_rbf_prob.libsvm", true, [0, 7], [0, 7]);
     test_model!(m_nusvm_sigmoid_prob, "m_nusvm_sigmoid_prob.libsvm", true, [0, 7], [0, 7]);
 
+    #[test]
+    fn temperature() -> Result<(), Error> {
+        let mut svm = DenseSVM::try_from(include_str!("data_dense/m_csvm_linear_prob.libsvm"))?;
+
+        for invalid in &[0.0, -1.0, std::f64::NAN, std::f64::INFINITY] {
+            match svm.set_temperature(*invalid) {
+                Err(Error::InvalidTemperature(_)) => {}
+                _ => panic!("Expected temperature {} to be rejected.", invalid),
+            }
+        }
+
+        assert_eq!(svm.temperature(), 1.0);
+
+        svm.set_temperature(2.0)?;
+
+        let mut problem = Problem::from(&svm);
+        problem.features().as_slice_mut().clone_from_slice(&[1.28, 0.98, 1.48, 1.12, 0.89, 1.16, 0.92, 1.14]);
+        svm.predict_probability(&mut problem)?;
+
+        assert!(problem.probabilities().iter().all(|p| p.is_finite()));
+        assert_eq!(svm.temperature(), 2.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_probabilities() {
+        let model = include_str!("data_dense/m_csvm_linear_prob.libsvm");
+
+        for (name, truncated) in &[
+            ("probA", model.replace("probA 2.25077 -4.51094", "probA")),
+            ("probB", model.replace("probB 0.0750038 0.45878", "probB")),
+        ] {
+            match DenseSVM::try_from(truncated.as_str()) {
+                Err(Error::CountMismatch { section, expected, found }) => assert_eq!((section, expected, found), (*name, 28, 26)),
+                _ => panic!("Expected a mismatch in `{}`.", name),
+            }
+        }
+    }
+
     // Temporarily disabled as they trigger ICE in Rust Nightly
     // test_model!(m_nusvm_linear, "m_nusvm_linear.libsvm", false, [0, 7], []);
     // test_model!(m_nusvm_poly, "m_nusvm_poly.libsvm", false, [0, 7], []);
@@ -0,0 +1,46 @@
+#![feature(try_from)]
+
+extern crate ffsvm;
+
+#[cfg(test)]
+mod svm_threads {
+    use ffsvm::*;
+    use std::{convert::TryFrom, sync::Arc, thread};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<DenseSVM>();
+        assert_send_sync::<SparseSVM>();
+        assert_send_sync::<DenseProblem>();
+        assert_send_sync::<SparseProblem>();
+        assert_send_sync::<Ensemble>();
+        assert_send_sync::<Cascade>();
+        assert_send_sync::<Hierarchy>();
+        assert_send_sync::<ReloadableSvm<DenseSVM>>();
+    }
+
+    #[test]
+    fn shared_model() -> Result<(), Error> {
+        let svm = Arc::new(DenseSVM::try_from(SAMPLE_MODEL)?);
+
+        let workers = (0 .. 64)
+            .map(|_| {
+                let svm = svm.clone();
+
+                thread::spawn(move || {
+                    let mut problem = Problem::from(&*svm);
+                    problem.features().as_slice_mut()[.. SAMPLE_FEATURES.len()].copy_from_slice(&SAMPLE_FEATURES);
+
+                    svm.predict_value(&mut problem).map(|_| problem.solution())
+                })
+            }).collect::<Vec<_>>();
+
+        for worker in workers {
+            assert_eq!(worker.join().unwrap()?, Solution::Label(42));
+        }
+
+        Ok(())
+    }
+}